@@ -14,8 +14,10 @@ use num_dual::{gradient, DualNum, DualVec};
 use thruster_sim::{
     heuristic::{ScoreResult, ScoreSettings},
     optimize::{
-        self, symetrical::SymerticalOptimization, AsyncOptimizationArena, OptimizationArena,
+        self, optimizers::Adam, symetrical::SymerticalOptimization, ConfigArena, Execution,
+        OptimizationArena,
     },
+    thruster_models::ThrusterModels,
 };
 
 pub const LEARN_RATE: FloatType = 0.01;
@@ -28,11 +30,17 @@ pub const CRITICAL_POINT_EPSILON: FloatType = 0.1;
 pub type Point<D: Number> = SVector<D, DIMENSIONALITY>;
 
 fn main() {
-    let motor_data =
-        motor_preformance::read_motor_data_from_path("motor_data.csv").expect("Read motor data");
+    let motor_data = ThrusterModels::from(
+        motor_preformance::read_motor_data_from_path("motor_data.csv").expect("Read motor data"),
+    );
 
-    let mut arena = AsyncOptimizationArena::new(SymerticalOptimization::<3>);
-    arena.reset(25, ScoreSettings::default());
+    let mut arena = ConfigArena::new(
+        SymerticalOptimization::<3>,
+        Adam::default(),
+        Execution::Rayon,
+    )
+    .expect("Create arena");
+    arena.reset(25, ScoreSettings::default(), 0);
 
     for _ in 0..500 {
         arena.step(&motor_data);
@@ -15,35 +15,68 @@ use bevy::{
 use bevy_egui::EguiPlugin;
 use bevy_panorbit_camera::{PanOrbitCamera, PanOrbitCameraPlugin};
 use camera::{set_camera_viewports, sync_cameras, CameraPos};
-use motor_config::{add_motor_conf, update_motor_conf, AxisGizmo, MotorConfigRes, ThrustGizmo};
-use motor_math::{
-    motor_preformance::{self, MotorData},
-    x3d::X3dMotorId,
-    Direction, FloatType, Motor, MotorConfig,
+use motor_config::{
+    add_motor_conf, update_hull, update_motor_conf, update_tube, AxisGizmo, MotorConfigRes,
+    ThrustGizmo,
 };
-use nalgebra::{vector, DMatrix};
+use nalgebra::DMatrix;
+use optimizer::ResetEvent;
 use optimizer::{
-    gui::render_gui, handle_reset, ArenaMode, ArenaType, OptimizerStatus, ShownConfig, TopConfigs,
+    evaluate_loaded_config, handle_constraints_change, handle_heuristic_change,
+    handle_hyperparameter_change, handle_motor_change, step_accent_points, ConstraintsRes,
+    HyperparametersRes, LoadedConfig, MountingPlateRes, OptimizerArenaRes, ScoreSettingsRes,
+};
+use optimizer::{
+    gui::render_gui, handle_reset, ArenaMode, OptimizerStatus, ShownConfig, TopConfigs,
+};
+use thruster_sim::heuristic::settings::ToggleableScoreSettings;
+use thruster_sim::layout;
+use thruster_sim::motor_library::MotorLibrary;
+use thruster_sim::optimize::{
+    optimizers::{Adam, OptimizerType},
+    ArenaType, ConfigArena, Execution, OptimizationOutput,
 };
-use optimizer::{handle_heuristic_change, step_accent_points, OptimizerArenaRes, ScoreSettingsRes};
-use optimizer::{settings::ToggleableScoreSettings, ResetEvent};
 use thruster_sim::optimize::{symetrical::SymerticalOptimization, x3d_fixed::FixedX3dOptimization};
-use thruster_sim::optimize::{AsyncOptimizationArena, OptimizationOutput};
-use thruster_sim::{HEIGHT, LENGTH, WIDTH};
+use thruster_sim::power::PowerBudget;
+use thruster_sim::thruster_models::ThrusterModels;
 
 #[derive(Resource)]
-pub struct MotorDataRes(pub MotorData);
+pub struct MotorDataRes(pub ThrusterModels);
+
+/// The thruster models to choose from, the motor data holds every model's curve at the bus
+/// voltage
+#[derive(Resource)]
+pub struct MotorLibraryRes {
+    pub library: MotorLibrary,
+    /// Index of the model used by slots without one
+    pub model: usize,
+    /// The model of each slot of the config, `None` for the default
+    pub slots: Vec<Option<usize>>,
+    /// Degree of the polynomials fitted to the curves, the tables are used if `None`
+    pub fit_degree: Option<usize>,
+}
 
 fn main() {
     #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
-    let motor_data =
-        motor_preformance::read_motor_data_from_path("motor_data.csv").expect("Read motor data");
+    let library = if std::path::Path::new("motors.toml").exists() {
+        MotorLibrary::load("motors.toml").expect("Load motor library")
+    } else {
+        let contents = std::fs::read_to_string("motor_data.csv").expect("Read motor data");
+        MotorLibrary::from_csv("Default", 12.0, &contents).expect("Read motor data")
+    };
     #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
-    let motor_data = {
+    let library = {
         panic::set_hook(Box::new(console_error_panic_hook::hook));
-        motor_preformance::read_motor_data_from_string(include_str!("../../../motor_data.csv"))
+        MotorLibrary::from_csv("Default", 12.0, include_str!("../../../motor_data.csv"))
             .expect("Read motor data")
     };
+    let motor_data = ThrusterModels::from_library(
+        &library,
+        PowerBudget::default().bus_voltage,
+        &library.models()[0].name,
+        None,
+    )
+    .expect("Read motor data");
 
     App::new()
         .add_plugins((
@@ -69,22 +102,40 @@ fn main() {
         )
         .init_gizmo_group::<ThrustGizmo>()
         .insert_resource(ScoreSettingsRes(ToggleableScoreSettings::default()))
-        .insert_resource(OptimizerArenaRes(Box::new(AsyncOptimizationArena::new(
-            SymerticalOptimization::<3>,
-        ))))
+        .insert_resource(OptimizerArenaRes(Box::new(
+            ConfigArena::new(
+                SymerticalOptimization::<3>,
+                Adam::default(),
+                Execution::Rayon,
+            )
+            .expect("Create optimizer arena"),
+        )))
         .insert_resource(ArenaMode {
             arena_type: ArenaType::Symmetrical3,
-            is_async: true,
+            optimizer: OptimizerType::default(),
+            execution: Execution::Rayon,
+            seed: rand::random::<u32>() as u64,
+            mounted_thrusters: None,
             #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
             point_count: 100,
             #[cfg(all(target_arch = "wasm32", target_os = "unknown"))]
             point_count: 10,
         })
         .insert_resource(MotorDataRes(motor_data))
+        .insert_resource(MotorLibraryRes {
+            library,
+            model: 0,
+            slots: vec![],
+            fit_degree: None,
+        })
         .insert_resource(ClearColor(Color::WHITE))
         .insert_resource(ShownConfig::Best)
         .insert_resource(OptimizerStatus::Running)
         .insert_resource(TopConfigs { configs: vec![] })
+        .init_resource::<LoadedConfig>()
+        .init_resource::<HyperparametersRes>()
+        .init_resource::<MountingPlateRes>()
+        .init_resource::<ConstraintsRes>()
         .add_event::<ResetEvent>()
         .add_systems(Startup, setup)
         .add_systems(
@@ -92,11 +143,17 @@ fn main() {
             (
                 render_gui,
                 update_motor_conf,
+                update_tube,
+                update_hull,
                 set_camera_viewports,
                 sync_cameras,
                 handle_heuristic_change,
+                handle_hyperparameter_change,
+                handle_constraints_change,
+                handle_motor_change,
                 handle_reset,
                 step_accent_points,
+                evaluate_loaded_config,
                 // screenshot_on_tab,
                 // auto_generate_constraints.before(sync_cameras),
                 // toggle_auto_gen_on_space,
@@ -110,21 +167,15 @@ fn setup(
     mut meshes: ResMut<Assets<Mesh>>,
     mut ambiant: ResMut<AmbientLight>,
     motor_data: Res<MotorDataRes>,
+    score_settings: Res<ScoreSettingsRes>,
     mut materials_pbr: ResMut<Assets<StandardMaterial>>,
 ) {
-    let motor_conf = MotorConfig::<X3dMotorId, FloatType>::new(
-        Motor {
-            position: vector![WIDTH, LENGTH, HEIGHT] / 2.0,
-            orientation: vector![-0.254, 0.571, -0.781].normalize(),
-            direction: Direction::Clockwise,
-        },
-        vector![0.0, 0.0, 0.0],
-    )
-    .erase();
+    let motor_conf = layout::x3d_motor_config();
 
     add_motor_conf(
         &motor_conf,
         &motor_data,
+        &score_settings.0,
         &mut commands,
         &mut meshes,
         &mut materials_pbr,
@@ -136,6 +187,10 @@ fn setup(
         parameters: DMatrix::default(),
         score_result_unscaled: Default::default(),
         score_result_scaled: Default::default(),
+        seed: None,
+        converged: true,
+        thruster_models: vec![],
+        constraint_violations: 0,
     }));
 
     // light
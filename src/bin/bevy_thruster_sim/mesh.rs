@@ -6,10 +6,11 @@ use bevy::{
     },
 };
 use hexasphere::shapes::IcoSphere;
-use motor_math::{
-    motor_preformance::MotorData, solve::reverse, ErasedMotorId, FloatType, MotorConfig, Movement,
-};
+use motor_math::{solve::reverse, ErasedMotorId, FloatType, MotorConfig, Movement};
 use nalgebra::{vector, Vector3};
+use thruster_sim::{
+    geometry::VehicleGeometry, physics, power::PowerBudget, thruster_models::ThrusterModels,
+};
 
 #[derive(Component)]
 pub enum HeuristicMesh {
@@ -30,9 +31,13 @@ const MESH_DETAIL: usize = 4;
 
 pub fn make_strength_mesh(
     motor_config: &MotorConfig<ErasedMotorId, FloatType>,
-    motor_data: &MotorData,
+    motor_data: &ThrusterModels,
+    geometry: &VehicleGeometry,
+    power: &PowerBudget,
     mesh_type: StrengthMesh,
 ) -> Mesh {
+    let motor_config = &physics::with_vehicle(motor_config.clone(), geometry);
+
     let generated = IcoSphere::new(MESH_DETAIL, |point| {
         let movement = match mesh_type {
             StrengthMesh::Force => Movement {
@@ -46,9 +51,8 @@ pub fn make_strength_mesh(
         };
 
         let forces = reverse::reverse_solve(movement, motor_config);
-        let motor_cmds = reverse::forces_to_cmds(forces, motor_config, motor_data);
-        let ratio =
-            reverse::binary_search_force_ratio(&motor_cmds, motor_config, motor_data, 25.0, 0.01);
+        let motor_cmds = motor_data.forces_to_cmds(forces, motor_config);
+        let ratio = power.force_ratio(&motor_cmds, motor_config, motor_data);
         // let ratio = 1.0;
 
         let type_ratio = match mesh_type {
@@ -1,9 +1,20 @@
-use bevy::{color, math::vec3, prelude::*, render::view::RenderLayers};
+use bevy::{
+    color,
+    math::vec3,
+    prelude::*,
+    render::{mesh::PrimitiveTopology, render_asset::RenderAssetUsages, view::RenderLayers},
+};
 use motor_math::{ErasedMotorId, FloatType, Motor, MotorConfig};
-use thruster_sim::optimize::OptimizationOutput;
+use thruster_sim::{
+    geometry::Tube,
+    heuristic::settings::ToggleableScoreSettings,
+    hull::{Hull, HullSource},
+    optimize::OptimizationOutput,
+};
 
 use crate::{
     mesh::{make_strength_mesh, StrengthMesh},
+    optimizer::ScoreSettingsRes,
     MotorDataRes,
 };
 
@@ -11,6 +22,10 @@ use crate::{
 pub struct MotorConfigRes(pub OptimizationOutput);
 #[derive(Component)]
 pub struct MotorMarker(pub ErasedMotorId, pub bool);
+#[derive(Component)]
+pub struct TubeMarker;
+#[derive(Component)]
+pub struct HullMarker;
 
 #[derive(Default, Reflect, GizmoConfigGroup)]
 pub struct ThrustGizmo;
@@ -22,6 +37,7 @@ pub fn update_motor_conf(
     mut commands: Commands,
     motor_conf: Res<MotorConfigRes>,
     motor_data: Res<MotorDataRes>,
+    score_settings: Res<ScoreSettingsRes>,
     motors_query: Query<Entity, With<MotorMarker>>,
     mut meshes: ResMut<Assets<Mesh>>,
     mesh_query: Query<(&Handle<Mesh>, &StrengthMesh)>,
@@ -43,8 +59,13 @@ pub fn update_motor_conf(
         }
 
         for (mesh, mesh_type) in mesh_query.iter() {
-            *meshes.get_mut(mesh).unwrap() =
-                make_strength_mesh(&motor_conf.0.motor_config, &motor_data.0, *mesh_type);
+            *meshes.get_mut(mesh).unwrap() = make_strength_mesh(
+                &motor_conf.0.motor_config,
+                &motor_data.0.resolved(motor_conf.0.thruster_models.clone()),
+                &score_settings.0.geometry,
+                &score_settings.0.power,
+                *mesh_type,
+            );
         }
 
         // let transform = Transform::from_rotation(Quat::from_rotation_x(90f32.to_radians()))
@@ -110,21 +131,112 @@ pub fn update_motor_conf(
     );
 }
 
+/// Keeps the tube in the scene in sync with the geometry the heuristic scores against
+pub fn update_tube(
+    score_settings: Res<ScoreSettingsRes>,
+    mut tubes: Query<(&mut Transform, &Handle<Mesh>), With<TubeMarker>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if !score_settings.is_changed() {
+        return;
+    }
+
+    let (cylinder, transform) = tube_mesh(&score_settings.0.geometry.tube);
+    for (mut tube_transform, mesh) in &mut tubes {
+        *tube_transform = transform;
+        if let Some(mesh) = meshes.get_mut(mesh) {
+            *mesh = cylinder.into();
+        }
+    }
+}
+
+/// Keeps the hull in the scene in sync with the mesh the heuristic scores against
+pub fn update_hull(
+    mut commands: Commands,
+    score_settings: Res<ScoreSettingsRes>,
+    hulls: Query<Entity, With<HullMarker>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials_pbr: ResMut<Assets<StandardMaterial>>,
+    mut shown: Local<Option<HullSource>>,
+) {
+    let hull = score_settings.0.geometry.hull.as_ref();
+    if hull.map(Hull::source) == (*shown).as_ref() {
+        return;
+    }
+
+    for entity in &hulls {
+        commands.entity(entity).despawn();
+    }
+    *shown = hull.map(|it| it.source().clone());
+
+    let Some(hull) = hull else {
+        return;
+    };
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(hull_mesh(hull)),
+            material: materials_pbr.add(StandardMaterial {
+                base_color: Color::srgba(0.5, 0.6, 0.7, 0.4),
+                alpha_mode: AlphaMode::Blend,
+                cull_mode: None,
+                double_sided: true,
+                ..default()
+            }),
+            transform: Transform::from_rotation(Quat::from_rotation_x(90f32.to_radians())),
+            ..default()
+        },
+        HullMarker,
+        RenderLayers::layer(0),
+    ));
+}
+
+fn hull_mesh(hull: &Hull) -> Mesh {
+    let positions: Vec<[f32; 3]> = hull
+        .triangles()
+        .iter()
+        .flat_map(|it| it.vertices)
+        .map(|it| (it * 2.0).cast::<f32>().into())
+        .collect();
+
+    Mesh::new(
+        PrimitiveTopology::TriangleList,
+        RenderAssetUsages::RENDER_WORLD | RenderAssetUsages::MAIN_WORLD,
+    )
+    .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+    .with_computed_flat_normals()
+}
+
+/// The scene is drawn at twice the scale of the vehicle
+fn tube_mesh(tube: &Tube) -> (Cylinder, Transform) {
+    let transform = Transform::from_rotation(Quat::from_rotation_x(90f32.to_radians()))
+        * Transform::from_translation((tube.offset() * 2.0).cast::<f32>().into()).with_rotation(
+            Quat::from_rotation_arc(Vec3::Y, Vec3::from(tube.axis().cast::<f32>())),
+        );
+
+    (
+        Cylinder::new(tube.radius as f32 * 2.0, tube.length as f32 * 2.0),
+        transform,
+    )
+}
+
 pub fn add_motor_conf(
     motor_conf: &MotorConfig<ErasedMotorId, FloatType>,
     motor_data: &Res<MotorDataRes>,
+    score_settings: &ToggleableScoreSettings,
 
     commands: &mut Commands,
     meshes: &mut ResMut<Assets<Mesh>>,
     materials_pbr: &mut ResMut<Assets<StandardMaterial>>,
 ) {
+    let (cylinder, transform) = tube_mesh(&score_settings.geometry.tube);
     commands.spawn((
         PbrBundle {
-            mesh: meshes.add(Cylinder::new(0.1, 0.76)),
+            mesh: meshes.add(cylinder),
             material: materials_pbr.add(Color::srgb(0.8, 0.7, 0.6)),
-            transform: Transform::from_rotation(Quat::from_rotation_x(90f32.to_radians())),
+            transform,
             ..default()
         },
+        TubeMarker,
         RenderLayers::layer(0),
     ));
 
@@ -143,6 +255,8 @@ pub fn add_motor_conf(
             mesh: meshes.add(make_strength_mesh(
                 motor_conf,
                 &motor_data.0,
+                &score_settings.geometry,
+                &score_settings.power,
                 StrengthMesh::Force,
             )),
             material: materials_pbr.add(Color::srgb(0.8, 0.7, 0.6)),
@@ -158,6 +272,8 @@ pub fn add_motor_conf(
             mesh: meshes.add(make_strength_mesh(
                 motor_conf,
                 &motor_data.0,
+                &score_settings.geometry,
+                &score_settings.power,
                 StrengthMesh::Torque,
             )),
             material: materials_pbr.add(Color::srgb(0.8, 0.7, 0.6)),
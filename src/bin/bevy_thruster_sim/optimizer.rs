@@ -1,39 +1,43 @@
 use std::time::Duration;
 
 use bevy::prelude::*;
-use motor_math::FloatType;
-use settings::ToggleableScoreSettings;
+use motor_math::{ErasedMotorId, FloatType, MotorConfig};
 use thruster_sim::{
+    constraints::PositionConstraints,
+    heuristic::settings::ToggleableScoreSettings,
     optimize::{
-        full::FullOptimization, symetrical::SymerticalOptimization,
-        x3d_fixed::FixedX3dOptimization, AsyncOptimizationArena, OptimizationArena,
-        OptimizationOutput, SyncOptimizationArena,
+        mounted::{self, MountingPlate},
+        optimizers::OptimizerType,
+        schedule::Hyperparameters,
+        ArenaType, Execution, OptimizationArena, OptimizationOutput,
     },
-    HEIGHT, LENGTH, WIDTH,
+    thruster_models::ThrusterModels,
 };
 
-use crate::{motor_config::MotorConfigRes, MotorDataRes};
+use crate::{motor_config::MotorConfigRes, MotorDataRes, MotorLibraryRes};
 
 pub mod gui;
-pub mod settings;
 
-#[derive(Resource, Clone, Copy, PartialEq, Eq)]
+#[derive(Resource, Clone, Copy, PartialEq)]
 pub struct ArenaMode {
     pub arena_type: ArenaType,
-    pub is_async: bool,
+    pub optimizer: OptimizerType,
+    pub execution: Execution,
+    pub seed: u64,
     pub point_count: usize,
-}
-
-#[derive(Clone, Copy, PartialEq, Eq)]
-pub enum ArenaType {
-    X3d,
-    Symmetrical3,
-    Unconstrained6,
+    /// Place this many thrusters on the loaded mounting plate instead of optimizing `arena_type`
+    pub mounted_thrusters: Option<usize>,
 }
 
 #[derive(Resource)]
 pub struct ScoreSettingsRes(pub ToggleableScoreSettings);
 
+#[derive(Resource, Default)]
+pub struct HyperparametersRes(pub Hyperparameters);
+
+#[derive(Resource, Default)]
+pub struct ConstraintsRes(pub PositionConstraints);
+
 #[derive(Resource)]
 pub struct OptimizerArenaRes(pub Box<dyn OptimizationArena + Send + Sync + 'static>);
 
@@ -41,8 +45,17 @@ pub struct OptimizerArenaRes(pub Box<dyn OptimizationArena + Send + Sync + 'stat
 pub enum ShownConfig {
     Best,
     Index(usize),
+    Loaded,
 }
 
+/// A user supplied configuration that is evaluated in place of the optimizer's points
+#[derive(Resource, Default)]
+pub struct LoadedConfig(pub Option<MotorConfig<ErasedMotorId, FloatType>>);
+
+/// The mounting holes thrusters can be placed on, see `ArenaMode::mounted_thrusters`
+#[derive(Resource, Default)]
+pub struct MountingPlateRes(pub Option<MountingPlate>);
+
 #[derive(Resource, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OptimizerStatus {
     Running,
@@ -67,6 +80,10 @@ pub fn step_accent_points(
     mut best: ResMut<TopConfigs>,
     time: Res<Time>,
 ) {
+    if let ShownConfig::Loaded = *shown_config {
+        return;
+    }
+
     if let OptimizerStatus::Running = *status {
         best.configs.clear();
         for config in optimizer.0.step(&motor_data.0).take(10) {
@@ -101,6 +118,29 @@ pub fn step_accent_points(
                 // }
             }
         }
+        ShownConfig::Loaded => unreachable!(),
+    }
+}
+
+pub fn evaluate_loaded_config(
+    mut commands: Commands,
+    loaded: Res<LoadedConfig>,
+    shown_config: Res<ShownConfig>,
+    score_settings: Res<ScoreSettingsRes>,
+    motor_data: Res<MotorDataRes>,
+) {
+    if *shown_config != ShownConfig::Loaded
+        || !(loaded.is_changed() || shown_config.is_changed() || score_settings.is_changed())
+    {
+        return;
+    }
+
+    if let Some(motor_config) = &loaded.0 {
+        commands.insert_resource(MotorConfigRes(OptimizationOutput::evaluate(
+            motor_config.clone(),
+            &score_settings.0.flatten(),
+            &motor_data.0,
+        )));
     }
 }
 
@@ -117,6 +157,68 @@ pub fn handle_heuristic_change(
     }
 }
 
+pub fn handle_hyperparameter_change(
+    hyperparameters: Res<HyperparametersRes>,
+    mut optimizer: ResMut<OptimizerArenaRes>,
+) {
+    if hyperparameters.is_changed() {
+        optimizer.0.set_hyperparameters(hyperparameters.0);
+    }
+}
+
+pub fn handle_constraints_change(
+    constraints: Res<ConstraintsRes>,
+    mut optimizer: ResMut<OptimizerArenaRes>,
+) {
+    if constraints.is_changed() {
+        optimizer.0.set_constraints(constraints.0.clone());
+    }
+}
+
+/// Recomputes the motor data when the thruster models or the bus voltage change
+pub fn handle_motor_change(
+    mut commands: Commands,
+    score_settings: Res<ScoreSettingsRes>,
+    motor_library: Res<MotorLibraryRes>,
+    mut motor_conf: ResMut<MotorConfigRes>,
+    mut last_voltage: Local<Option<FloatType>>,
+) {
+    let voltage = score_settings.0.power.bus_voltage;
+    let voltage_changed = last_voltage
+        .replace(voltage)
+        .is_some_and(|last| last != voltage);
+    if !(voltage_changed || motor_library.is_changed()) {
+        return;
+    }
+
+    let default = &motor_library.library.models()[motor_library.model];
+    match ThrusterModels::from_library(
+        &motor_library.library,
+        voltage,
+        &default.name,
+        motor_library.fit_degree,
+    ) {
+        Ok(models) => {
+            info!("Using {} at {voltage} V", default.name);
+
+            let assignment = motor_library
+                .slots
+                .iter()
+                .map(|it| it.unwrap_or(motor_library.model))
+                .collect();
+            commands.insert_resource(MotorDataRes(models.with_assignment(assignment)));
+            motor_conf.0.score = FloatType::NEG_INFINITY;
+            // The models change what the points are optimized for
+            if motor_library.is_changed() && !motor_library.is_added() {
+                commands.add(|world: &mut World| {
+                    world.send_event(ResetEvent);
+                });
+            }
+        }
+        Err(err) => error!("Could not read motor data: {err:?}"),
+    }
+}
+
 #[derive(Event)]
 pub struct ResetEvent;
 
@@ -124,54 +226,53 @@ pub fn handle_reset(
     mut commands: Commands,
     score_settings: Res<ScoreSettingsRes>,
     arena_mode: Res<ArenaMode>,
+    mounting_plate: Res<MountingPlateRes>,
+    hyperparameters: Res<HyperparametersRes>,
+    constraints: Res<ConstraintsRes>,
     mut motor_conf: ResMut<MotorConfigRes>,
     mut optimizer: ResMut<OptimizerArenaRes>,
     mut reset_event: EventReader<ResetEvent>,
 ) {
-    if arena_mode.is_changed() {
-        let arena: Box<dyn OptimizationArena + Send + Sync + 'static> =
-            match (arena_mode.arena_type, arena_mode.is_async) {
-                (ArenaType::X3d, true) => {
-                    Box::new(AsyncOptimizationArena::new(FixedX3dOptimization {
-                        width: WIDTH / 2.0,
-                        length: LENGTH / 2.0,
-                        height: HEIGHT / 2.0,
-                    }))
-                }
-                (ArenaType::X3d, false) => {
-                    Box::new(SyncOptimizationArena::new(FixedX3dOptimization {
-                        width: WIDTH / 2.0,
-                        length: LENGTH / 2.0,
-                        height: HEIGHT / 2.0,
-                    }))
-                }
-                (ArenaType::Symmetrical3, true) => {
-                    Box::new(AsyncOptimizationArena::new(SymerticalOptimization::<3>))
-                }
-                (ArenaType::Symmetrical3, false) => {
-                    Box::new(SyncOptimizationArena::new(SymerticalOptimization::<3>))
-                }
-                (ArenaType::Unconstrained6, true) => {
-                    Box::new(AsyncOptimizationArena::new(FullOptimization::<6>))
-                }
-                (ArenaType::Unconstrained6, false) => {
-                    Box::new(SyncOptimizationArena::new(FullOptimization::<6>))
-                }
-            };
-
-        commands.insert_resource(OptimizerArenaRes(arena));
-        commands.add(|world: &mut World| {
-            world.send_event(ResetEvent);
+    if arena_mode.is_changed() || mounting_plate.is_changed() {
+        let mounted = match (arena_mode.mounted_thrusters, &mounting_plate.0) {
+            (Some(thruster_count), Some(plate)) => mounted::new_arena(
+                plate,
+                thruster_count,
+                arena_mode.execution,
+                arena_mode.optimizer,
+            )
+            .inspect_err(|err| error!("Could not place thrusters on mounts: {err:?}"))
+            .ok(),
+            _ => None,
+        };
+        let arena = mounted.map(Ok).unwrap_or_else(|| {
+            arena_mode
+                .arena_type
+                .new_arena(arena_mode.execution, arena_mode.optimizer)
         });
+        match arena {
+            Ok(mut arena) => {
+                arena.set_hyperparameters(hyperparameters.0);
+                arena.set_constraints(constraints.0.clone());
+
+                commands.insert_resource(OptimizerArenaRes(arena));
+                commands.add(|world: &mut World| {
+                    world.send_event(ResetEvent);
+                });
+            }
+            Err(err) => error!("Could not create the optimizer arena: {err:?}"),
+        }
     }
 
     if !reset_event.is_empty() {
         reset_event.clear();
         info!("Reset Optimizer");
 
-        optimizer
-            .0
-            .reset(arena_mode.point_count, score_settings.0.flatten());
+        optimizer.0.reset(
+            arena_mode.point_count,
+            score_settings.0.flatten(),
+            arena_mode.seed,
+        );
 
         motor_conf.0.score = FloatType::NEG_INFINITY;
     }
@@ -2,19 +2,51 @@ use std::collections::BTreeMap;
 
 use bevy::prelude::*;
 use bevy_egui::{
-    egui::{self, Slider},
+    egui::{self, DragValue, Slider},
     EguiContexts,
 };
 use bevy_panorbit_camera::PanOrbitCamera;
-use motor_math::solve::reverse;
-use thruster_sim::heuristic::MesType;
+use motor_math::FloatType;
+use thruster_sim::{
+    constraints::PositionConstraints,
+    format::FileFormat,
+    heuristic::{
+        presets::{self, BUILTIN_PRESETS},
+        MesType,
+    },
+    hull::{Hull, HullSource},
+    layout::{self, ThrusterLayout},
+    mass::MassModel,
+    motor_fit::{MotorFit, DEFAULT_FIT_DEGREE},
+    optimize::{
+        dynamic::{Plane, Symmetry},
+        mounted::MountingPlate,
+        optimizers::OptimizerType,
+        schedule::StepSchedule,
+        ArenaCheckpoint, ArenaType, Execution,
+    },
+    physics,
+};
 
-use crate::{motor_config::MotorConfigRes, MotorDataRes};
+use crate::{motor_config::MotorConfigRes, MotorDataRes, MotorLibraryRes};
 
 use super::{
-    ArenaMode, ArenaType, OptimizerStatus, ResetEvent, ScoreSettingsRes, ShownConfig, TopConfigs,
+    ArenaMode, ConstraintsRes, HyperparametersRes, LoadedConfig, MountingPlateRes,
+    OptimizerArenaRes, OptimizerStatus, ResetEvent, ScoreSettingsRes, ShownConfig, TopConfigs,
 };
 
+/// The contents of the gui's path fields
+#[derive(Default)]
+pub struct FilePaths {
+    preset: String,
+    layout: String,
+    checkpoint: String,
+    mounting_plate: String,
+    constraints: String,
+    hull: String,
+    mass_model: String,
+}
+
 pub fn render_gui(
     mut commands: Commands,
     mut contexts: EguiContexts,
@@ -26,6 +58,12 @@ pub fn render_gui(
     best: Res<TopConfigs>,
     mut status: ResMut<OptimizerStatus>,
     mut arena: ResMut<ArenaMode>,
+    mut paths: Local<FilePaths>,
+    mut loaded: ResMut<LoadedConfig>,
+    mut optimizer: ResMut<OptimizerArenaRes>,
+    mounting_plate: Res<MountingPlateRes>,
+    mut hyperparameters: ResMut<HyperparametersRes>,
+    mut motor_library: ResMut<MotorLibraryRes>,
 ) {
     let response = egui::Window::new("Motor Config").show(contexts.ctx_mut(), |ui| {
         ui.set_width(250.0);
@@ -54,12 +92,29 @@ pub fn render_gui(
                 }
             });
 
+            if optimizer.0.converged() {
+                ui.label("All points converged");
+            }
+
+            if loaded.0.is_some() {
+                ui.selectable_value(&mut shown, ShownConfig::Loaded, "Loaded Layout");
+            }
             ui.selectable_value(&mut shown, ShownConfig::Best, "Always Best");
             for config in &best.configs {
                 ui.selectable_value(
                     &mut shown,
                     ShownConfig::Index(config.idx),
-                    format!("{}, {:.02}", config.idx, config.score),
+                    format!(
+                        "{}, {:.02}{}{}",
+                        config.idx,
+                        config.score,
+                        if config.converged { " (converged)" } else { "" },
+                        if config.constraint_violations > 0 {
+                            " (outside constraints)"
+                        } else {
+                            ""
+                        }
+                    ),
                 );
             }
 
@@ -83,15 +138,441 @@ pub fn render_gui(
                     "Unconstrained",
                 );
                 ui.selectable_value(&mut arena_mode.arena_type, ArenaType::X3d, "X3d");
+                ui.selectable_value(
+                    &mut arena_mode.arena_type,
+                    ArenaType::X3dDynamic,
+                    "X3d (Orientation + Position)",
+                );
+
+                let thruster_count = arena_mode.arena_type.thruster_count();
+                for (symmetry, label) in [
+                    (Symmetry::Mirror(Plane::YZ), "Symmetrical (Custom Count)"),
+                    (Symmetry::None, "Unconstrained (Custom Count)"),
+                    (Symmetry::Mirror(Plane::XZ), "Mirror Fore/Aft (XZ)"),
+                    (Symmetry::Mirror(Plane::XY), "Mirror Top/Bottom (XY)"),
+                    (Symmetry::DoubleMirror, "Double Mirror"),
+                    (Symmetry::Rotational(3), "Rotational"),
+                ] {
+                    let selected = match arena_mode.arena_type {
+                        ArenaType::Symmetric {
+                            symmetry: Symmetry::Rotational(_),
+                            ..
+                        } => matches!(symmetry, Symmetry::Rotational(_)),
+                        ArenaType::Symmetric {
+                            symmetry: current,
+                            ..
+                        } => current == symmetry,
+                        _ => false,
+                    };
+
+                    if ui.selectable_label(selected, label).clicked() && !selected {
+                        let order = symmetry.order();
+                        arena_mode.arena_type = ArenaType::Symmetric {
+                            symmetry,
+                            thrusters: thruster_count.div_ceil(order).max(1) * order,
+                        };
+                    }
+                }
             });
 
+            if let ArenaType::Symmetric {
+                symmetry,
+                thrusters: thruster_count,
+            } = &mut arena_mode.arena_type
+            {
+                if let Symmetry::Rotational(fold) = symmetry {
+                    ui.add(
+                        DragValue::new(fold)
+                            .speed(0.1)
+                            .range(2..=8)
+                            .prefix("Fold: "),
+                    );
+                }
+                ui.add(
+                    DragValue::new(thruster_count)
+                        .speed(0.1)
+                        .range(1..=16)
+                        .prefix("Thrusters: "),
+                );
+
+                let order = symmetry.order();
+                *thruster_count = (*thruster_count / order).max(1) * order;
+            }
+
+            ui.menu_button("Optimizer", |ui| {
+                for optimizer in OptimizerType::all() {
+                    let selected = arena_mode.optimizer.name() == optimizer.name();
+                    if ui.selectable_label(selected, optimizer.name()).clicked() && !selected {
+                        arena_mode.optimizer = optimizer;
+                    }
+                }
+            });
+
+            match &mut arena_mode.optimizer {
+                OptimizerType::Adam(adam) => {
+                    ui.add(
+                        DragValue::new(&mut adam.beta_1)
+                            .speed(0.001)
+                            .range(0.0..=0.9999)
+                            .prefix("Beta 1: "),
+                    );
+                    ui.add(
+                        DragValue::new(&mut adam.beta_2)
+                            .speed(0.0001)
+                            .range(0.0..=0.99999)
+                            .prefix("Beta 2: "),
+                    );
+                    ui.add(
+                        DragValue::new(&mut adam.epsilon)
+                            .speed(1e-11)
+                            .range(0.0..=1.0)
+                            .prefix("Epsilon: "),
+                    );
+                }
+                OptimizerType::Momentum(momentum) => {
+                    ui.add(
+                        DragValue::new(&mut momentum.momentum)
+                            .speed(0.001)
+                            .range(0.0..=0.9999)
+                            .prefix("Momentum: "),
+                    );
+                }
+                OptimizerType::Lbfgs(lbfgs) => {
+                    ui.add(
+                        DragValue::new(&mut lbfgs.memory)
+                            .range(1..=50)
+                            .prefix("Memory: "),
+                    );
+                }
+                OptimizerType::NelderMead(nelder_mead) => {
+                    ui.add(
+                        DragValue::new(&mut nelder_mead.simplex_size)
+                            .speed(0.001)
+                            .range(0.0001..=1.0)
+                            .prefix("Simplex Size: "),
+                    );
+                }
+            }
+
             ui.add(Slider::new(&mut arena_mode.point_count, 0..=1000).text("Point Count"));
 
-            ui.checkbox(&mut arena_mode.is_async, "Async Optimizer");
+            ui.horizontal(|ui| {
+                ui.menu_button("Execution", |ui| {
+                    ui.selectable_value(&mut arena_mode.execution, Execution::Serial, "Serial");
+                    ui.selectable_value(&mut arena_mode.execution, Execution::Rayon, "Rayon");
+                    if ui
+                        .selectable_label(
+                            matches!(arena_mode.execution, Execution::Threads(_)),
+                            "Fixed Threads",
+                        )
+                        .clicked()
+                        && !matches!(arena_mode.execution, Execution::Threads(_))
+                    {
+                        arena_mode.execution = Execution::Threads(4);
+                    }
+                });
+
+                if let Execution::Threads(threads) = &mut arena_mode.execution {
+                    ui.add(DragValue::new(threads).range(1..=256).suffix(" threads"));
+                }
+            });
+
+            ui.horizontal(|ui| {
+                ui.add(DragValue::new(&mut arena_mode.seed).prefix("Seed: "));
+
+                if ui.button("Randomize").clicked() {
+                    arena_mode.seed = rand::random::<u32>() as u64;
+                }
+            });
+
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            {
+                ui.separator();
+
+                ui.add(
+                    egui::TextEdit::singleline(&mut paths.mounting_plate).hint_text("mounts.toml"),
+                );
+
+                if ui.button("Load Mounting Plate").clicked() {
+                    match MountingPlate::load(&paths.mounting_plate) {
+                        Ok(plate) => commands.insert_resource(MountingPlateRes(Some(plate))),
+                        Err(err) => error!("Could not load mounting plate: {err:?}"),
+                    }
+                }
+
+                ui.add(
+                    egui::TextEdit::singleline(&mut paths.constraints)
+                        .hint_text("constraints.toml"),
+                );
+
+                ui.horizontal(|ui| {
+                    if ui.button("Load Constraints").clicked() {
+                        match PositionConstraints::load(&paths.constraints) {
+                            Ok(constraints) => {
+                                commands.insert_resource(ConstraintsRes(constraints))
+                            }
+                            Err(err) => error!("Could not load constraints: {err:?}"),
+                        }
+                    }
+
+                    if ui.button("Clear Constraints").clicked() {
+                        commands.insert_resource(ConstraintsRes::default());
+                    }
+                });
+
+                let constraints = optimizer.0.constraints();
+                if !constraints.is_empty() {
+                    ui.label(format!(
+                        "{} keep out volumes{}",
+                        constraints.keep_out.len(),
+                        if constraints.envelope.is_some() {
+                            ", inside envelope"
+                        } else {
+                            ""
+                        }
+                    ));
+                }
+            }
+
+            if let Some(plate) = &mounting_plate.0 {
+                let mut mounted = arena_mode.mounted_thrusters.is_some();
+                ui.checkbox(&mut mounted, "Place on Mounting Holes");
+
+                match (mounted, &mut arena_mode.mounted_thrusters) {
+                    (true, Some(thruster_count)) => {
+                        ui.add(
+                            DragValue::new(thruster_count)
+                                .speed(0.1)
+                                .range(1..=plate.mounts.len())
+                                .prefix("Mounted Thrusters: "),
+                        );
+                    }
+                    (true, thruster_count) => {
+                        *thruster_count = Some(
+                            arena_mode
+                                .arena_type
+                                .thruster_count()
+                                .min(plate.mounts.len()),
+                        );
+                    }
+                    (false, thruster_count) => *thruster_count = None,
+                }
+            }
 
             if arena_mode != *arena {
                 *arena = arena_mode;
             }
+
+            ui.separator();
+
+            let mut params = hyperparameters.0;
+
+            ui.add(
+                DragValue::new(&mut params.step_size)
+                    .speed(0.0001)
+                    .range(0.0..=1.0)
+                    .prefix("Step Size: "),
+            );
+
+            ui.menu_button("Step Schedule", |ui| {
+                for schedule in StepSchedule::all() {
+                    let selected = params.schedule.name() == schedule.name();
+                    if ui.selectable_label(selected, schedule.name()).clicked() && !selected {
+                        params.schedule = schedule;
+                    }
+                }
+            });
+
+            match &mut params.schedule {
+                StepSchedule::Constant => {}
+                StepSchedule::Cosine { period, min_ratio } => {
+                    ui.add(DragValue::new(period).range(1..=100_000).prefix("Period: "));
+                    ui.add(
+                        DragValue::new(min_ratio)
+                            .speed(0.001)
+                            .range(0.0..=1.0)
+                            .prefix("Min Ratio: "),
+                    );
+                }
+                StepSchedule::StepDecay { interval, factor } => {
+                    ui.add(
+                        DragValue::new(interval)
+                            .range(1..=100_000)
+                            .prefix("Interval: "),
+                    );
+                    ui.add(
+                        DragValue::new(factor)
+                            .speed(0.01)
+                            .range(0.0..=1.0)
+                            .prefix("Factor: "),
+                    );
+                }
+                StepSchedule::WarmRestarts {
+                    period,
+                    period_multiplier,
+                    min_ratio,
+                } => {
+                    ui.add(DragValue::new(period).range(1..=100_000).prefix("Period: "));
+                    ui.add(
+                        DragValue::new(period_multiplier)
+                            .speed(0.01)
+                            .range(1.0..=10.0)
+                            .prefix("Period Multiplier: "),
+                    );
+                    ui.add(
+                        DragValue::new(min_ratio)
+                            .speed(0.001)
+                            .range(0.0..=1.0)
+                            .prefix("Min Ratio: "),
+                    );
+                }
+            }
+
+            let convergence = &mut params.convergence;
+            ui.add(
+                DragValue::new(&mut convergence.frontier_ratio_threshold)
+                    .speed(0.001)
+                    .range(1.0..=2.0)
+                    .prefix("Frontier Ratio: "),
+            );
+            ui.add(
+                DragValue::new(&mut convergence.frontier_time_limit)
+                    .range(1..=100_000)
+                    .prefix("Frontier Time Limit: "),
+            );
+            ui.add(
+                DragValue::new(&mut convergence.gradient_tolerance)
+                    .speed(1e-7)
+                    .range(0.0..=1.0)
+                    .prefix("Gradient Tolerance: "),
+            );
+            ui.add(
+                DragValue::new(&mut convergence.max_iterations)
+                    .range(1..=1_000_000)
+                    .prefix("Max Iterations: "),
+            );
+            ui.add(
+                DragValue::new(&mut params.spin_interval)
+                    .range(0..=100_000)
+                    .prefix("Spin Search Interval: "),
+            );
+            ui.add(
+                DragValue::new(&mut params.discrete_interval)
+                    .range(0..=100_000)
+                    .prefix("Mount Search Interval: "),
+            );
+
+            if params != hyperparameters.0 {
+                hyperparameters.0 = params;
+            }
+
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            {
+                ui.separator();
+
+                ui.add(
+                    egui::TextEdit::singleline(&mut paths.checkpoint).hint_text("checkpoint.json"),
+                );
+
+                ui.horizontal(|ui| {
+                    if ui.button("Save Checkpoint").clicked() {
+                        if let Err(err) = optimizer.0.checkpoint().save(&paths.checkpoint) {
+                            error!("Could not save checkpoint: {err:?}");
+                        }
+                    }
+
+                    if ui.button("Load Checkpoint").clicked() {
+                        let restored = ArenaCheckpoint::load(&paths.checkpoint)
+                            .and_then(|checkpoint| optimizer.0.restore(checkpoint));
+
+                        match restored {
+                            Ok(()) => {
+                                hyperparameters.0 = optimizer.0.hyperparameters();
+                                warn!(
+                                    "Using the checkpoint's heuristic until the goals are changed"
+                                );
+                                *shown_config = ShownConfig::Best;
+                            }
+                            Err(err) => error!("Could not load checkpoint: {err:?}"),
+                        }
+                    }
+                });
+            }
+        });
+
+        ui.collapsing("Layouts", |ui| {
+            ui.horizontal(|ui| {
+                for (name, motor_config) in layout::builtin_layouts() {
+                    if ui.button(name).clicked() {
+                        loaded.0 = Some(motor_config);
+                        *shown_config = ShownConfig::Loaded;
+                    }
+                }
+            });
+
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            {
+                ui.separator();
+
+                ui.add(egui::TextEdit::singleline(&mut paths.layout).hint_text("layout.toml"));
+
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        match layout::load_layout(&paths.layout) {
+                            Ok(layout) => {
+                                loaded.0 = Some(layout.motor_config());
+                                *shown_config = ShownConfig::Loaded;
+                            }
+                            Err(err) => error!("Could not load layout: {err:?}"),
+                        }
+                    }
+
+                    if ui.button("Export").clicked() {
+                        let layout =
+                            ThrusterLayout::from_output(&motor_conf.0, &solver.0.flatten());
+
+                        if let Err(err) = layout::save_layout(&paths.layout, &layout) {
+                            error!("Could not export layout: {err:?}");
+                        }
+                    }
+                });
+            }
+
+            ui.allocate_space((ui.available_width(), 0.0).into());
+        });
+
+        ui.collapsing("Heuristic Presets", |ui| {
+            for (name, contents) in BUILTIN_PRESETS {
+                if ui.button(*name).clicked() {
+                    let settings = presets::parse_preset(contents, FileFormat::Toml)
+                        .expect("Parse builtin preset");
+                    commands.insert_resource(ScoreSettingsRes(settings));
+                }
+            }
+
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            {
+                ui.separator();
+
+                ui.add(egui::TextEdit::singleline(&mut paths.preset).hint_text("heuristic.toml"));
+
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        match presets::load_preset(&paths.preset) {
+                            Ok(settings) => commands.insert_resource(ScoreSettingsRes(settings)),
+                            Err(err) => error!("Could not load preset: {err:?}"),
+                        }
+                    }
+
+                    if ui.button("Save").clicked() {
+                        if let Err(err) = presets::save_preset(&paths.preset, &solver.0) {
+                            error!("Could not save preset: {err:?}");
+                        }
+                    }
+                });
+            }
+
+            ui.allocate_space((ui.available_width(), 0.0).into());
         });
 
         ui.collapsing("Optimization Goals", |ui| {
@@ -440,20 +921,156 @@ pub fn render_gui(
                     .changed();
             });
 
+            let tube = &mut settings.geometry.tube;
+
             ui.horizontal(|ui| {
-                let check = ui.checkbox(&mut settings.tube_exclusion_radius.0, "Tube radius");
-                let width = check.rect.width();
+                let label = ui.label("Tube radius");
+                let width = label.rect.width();
+                ui.allocate_space((text_width - width, 0.0).into());
+
+                updated |= ui.add(Slider::new(&mut tube.radius, 0.0..=0.5)).changed();
+            });
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Tube length");
+                let width = label.rect.width();
+                ui.allocate_space((text_width - width, 0.0).into());
+
+                updated |= ui.add(Slider::new(&mut tube.length, 0.0..=1.5)).changed();
+            });
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Tube axis");
+                let width = label.rect.width();
+                ui.allocate_space((text_width - width, 0.0).into());
+
+                for (axis, name) in [
+                    ([1.0, 0.0, 0.0], "X"),
+                    ([0.0, 1.0, 0.0], "Y"),
+                    ([0.0, 0.0, 1.0], "Z"),
+                ] {
+                    updated |= ui.selectable_value(&mut tube.axis, axis, name).changed();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Tube offset");
+                let width = label.rect.width();
+                ui.allocate_space((text_width - width, 0.0).into());
+
+                for value in &mut tube.offset {
+                    updated |= ui
+                        .add(DragValue::new(value).speed(0.001).range(-0.5..=0.5))
+                        .changed();
+                }
+            });
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Prop torque ratio");
+                let width = label.rect.width();
                 ui.allocate_space((text_width - width, 0.0).into());
 
-                updated |= check.changed();
                 updated |= ui
-                    .add_enabled(
-                        settings.tube_exclusion_radius.0,
-                        Slider::new(&mut settings.tube_exclusion_radius.1, -1.0..=1.0),
+                    .add(
+                        DragValue::new(&mut settings.geometry.prop_torque_ratio)
+                            .speed(0.001)
+                            .range(0.0..=0.1),
                     )
                     .changed();
             });
 
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            {
+                ui.add(egui::TextEdit::singleline(&mut paths.hull).hint_text("hull.stl"));
+
+                ui.horizontal(|ui| {
+                    if ui.button("Load Hull").clicked() {
+                        match Hull::load(HullSource::new(&paths.hull), std::path::Path::new("")) {
+                            Ok(hull) => {
+                                settings.geometry.hull = Some(hull);
+                                updated = true;
+                            }
+                            Err(err) => error!("Could not load hull: {err:?}"),
+                        }
+                    }
+
+                    if ui.button("Clear Hull").clicked() {
+                        settings.geometry.hull = None;
+                        updated = true;
+                    }
+                });
+            }
+
+            if let Some(hull) = &settings.geometry.hull {
+                ui.label(format!(
+                    "Hull {} with {} triangles",
+                    hull.source().path.display(),
+                    hull.triangles().len()
+                ));
+            }
+
+            #[cfg(not(all(target_arch = "wasm32", target_os = "unknown")))]
+            {
+                ui.add(
+                    egui::TextEdit::singleline(&mut paths.mass_model).hint_text("mass_model.toml"),
+                );
+
+                ui.horizontal(|ui| {
+                    if ui.button("Load Mass Model").clicked() {
+                        match MassModel::load(&paths.mass_model) {
+                            Ok(mass_model) => {
+                                settings.geometry.mass = Some(mass_model);
+                                updated = true;
+                            }
+                            Err(err) => error!("Could not load mass model: {err:?}"),
+                        }
+                    }
+
+                    if ui.button("Clear Mass Model").clicked() {
+                        settings.geometry.mass = None;
+                        updated = true;
+                    }
+                });
+            }
+
+            if let Some(mass_model) = &settings.geometry.mass {
+                ui.label(format!(
+                    "Mass model with {} components",
+                    mass_model.components.len()
+                ));
+
+                ui.horizontal(|ui| {
+                    let check = ui.checkbox(&mut settings.metacentric_loss.0, "Metacentric loss");
+                    let width = check.rect.width();
+                    ui.allocate_space((text_width - width, 0.0).into());
+
+                    updated |= check.changed();
+                    updated |= ui
+                        .add_enabled(
+                            settings.metacentric_loss.0,
+                            Slider::new(&mut settings.metacentric_loss.1, -5000.0..=0.0),
+                        )
+                        .changed();
+                });
+
+                ui.horizontal(|ui| {
+                    let check = ui.checkbox(
+                        &mut settings.min_metacentric_height.0,
+                        "Min metacentric height",
+                    );
+                    let width = check.rect.width();
+                    ui.allocate_space((text_width - width, 0.0).into());
+
+                    updated |= check.changed();
+                    updated |= ui
+                        .add_enabled(
+                            settings.min_metacentric_height.0,
+                            Slider::new(&mut settings.min_metacentric_height.1, 0.0..=0.2),
+                        )
+                        .changed();
+                });
+            }
+
             // ui.horizontal(|ui| {
             //     let check = ui.checkbox(&mut settings.tube_exclusion_loss.0, "Tube exclusion loss");
             //     let width = check.rect.width();
@@ -500,6 +1117,48 @@ pub fn render_gui(
                     .changed();
             });
 
+            ui.horizontal(|ui| {
+                let check = ui.checkbox(&mut settings.hull_collision_loss.0, "Hull collision loss");
+                let width = check.rect.width();
+                ui.allocate_space((text_width - width, 0.0).into());
+
+                updated |= check.changed();
+                updated |= ui
+                    .add_enabled(
+                        settings.hull_collision_loss.0,
+                        Slider::new(&mut settings.hull_collision_loss.1, -1000.0..=0.0),
+                    )
+                    .changed();
+            });
+
+            ui.horizontal(|ui| {
+                let check = ui.checkbox(&mut settings.hull_jet_loss.0, "Hull jet loss");
+                let width = check.rect.width();
+                ui.allocate_space((text_width - width, 0.0).into());
+
+                updated |= check.changed();
+                updated |= ui
+                    .add_enabled(
+                        settings.hull_jet_loss.0,
+                        Slider::new(&mut settings.hull_jet_loss.1, -100.0..=0.0),
+                    )
+                    .changed();
+            });
+
+            ui.horizontal(|ui| {
+                let check = ui.checkbox(&mut settings.hull_jet_distance.0, "Hull jet distance");
+                let width = check.rect.width();
+                ui.allocate_space((text_width - width, 0.0).into());
+
+                updated |= check.changed();
+                updated |= ui
+                    .add_enabled(
+                        settings.hull_jet_distance.0,
+                        Slider::new(&mut settings.hull_jet_distance.1, 0.0..=1.0),
+                    )
+                    .changed();
+            });
+
             // ui.horizontal(|ui| {
             //     let check = ui.checkbox(
             //         &mut settings.thruster_exclusion_loss.0,
@@ -531,6 +1190,64 @@ pub fn render_gui(
                     .changed();
             });
 
+            ui.horizontal(|ui| {
+                let check = ui.checkbox(&mut settings.thruster_count_loss.0, "Thruster count loss");
+                let width = check.rect.width();
+                ui.allocate_space((text_width - width, 0.0).into());
+
+                updated |= check.changed();
+                updated |= ui
+                    .add_enabled(
+                        settings.thruster_count_loss.0,
+                        Slider::new(&mut settings.thruster_count_loss.1, -10.0..=0.0),
+                    )
+                    .changed();
+            });
+
+            let power = &mut settings.power;
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Total current (A)");
+                let width = label.rect.width();
+                ui.allocate_space((text_width - width, 0.0).into());
+
+                updated |= ui
+                    .add(Slider::new(&mut power.total_current, 1.0..=100.0))
+                    .changed();
+            });
+
+            let mut limited = power.thruster_current.is_finite();
+            if ui.checkbox(&mut limited, "Limit each thruster").changed() {
+                power.thruster_current = if limited {
+                    power.total_current
+                } else {
+                    FloatType::INFINITY
+                };
+                updated = true;
+            }
+
+            if limited {
+                ui.horizontal(|ui| {
+                    let label = ui.label("Thruster current (A)");
+                    let width = label.rect.width();
+                    ui.allocate_space((text_width - width, 0.0).into());
+
+                    updated |= ui
+                        .add(Slider::new(&mut power.thruster_current, 1.0..=40.0))
+                        .changed();
+                });
+            }
+
+            ui.horizontal(|ui| {
+                let label = ui.label("Bus voltage (V)");
+                let width = label.rect.width();
+                ui.allocate_space((text_width - width, 0.0).into());
+
+                updated |= ui
+                    .add(Slider::new(&mut power.bus_voltage, 6.0..=24.0))
+                    .changed();
+            });
+
             if updated {
                 commands.insert_resource(ScoreSettingsRes(settings));
             }
@@ -538,12 +1255,130 @@ pub fn render_gui(
             ui.allocate_space((ui.available_width(), 0.0).into());
         });
 
+        ui.collapsing("Thruster Model", |ui| {
+            let models = motor_library.library.models();
+
+            let mut selected = motor_library.model;
+            for (idx, model) in models.iter().enumerate() {
+                ui.selectable_value(&mut selected, idx, &model.name);
+            }
+
+            // Each slot can use its own model, thrusters copied from a slot share its model
+            let mut slots = motor_library.slots.clone();
+            if models.len() > 1 {
+                slots.resize(optimizer.0.slot_count(), None);
+                for (idx, slot) in slots.iter_mut().enumerate() {
+                    egui::ComboBox::from_label(format!("Slot {}", idx + 1))
+                        .selected_text(match slot {
+                            Some(model) => models[*model].name.as_str(),
+                            None => "Default",
+                        })
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(slot, None, "Default");
+                            for (model_idx, model) in models.iter().enumerate() {
+                                ui.selectable_value(slot, Some(model_idx), &model.name);
+                            }
+                        });
+                }
+            }
+
+            // Fitted curves give smooth gradients where the tables are piecewise linear
+            let mut fit = motor_library.fit_degree.is_some();
+            let mut degree = motor_library.fit_degree.unwrap_or(DEFAULT_FIT_DEGREE);
+            ui.horizontal(|ui| {
+                ui.checkbox(&mut fit, "Fit polynomials of degree");
+                ui.add_enabled(fit, DragValue::new(&mut degree).range(1..=8));
+            });
+            let fit_degree = fit.then_some(degree);
+
+            if selected != motor_library.model
+                || slots != motor_library.slots
+                || fit_degree != motor_library.fit_degree
+            {
+                motor_library.model = selected;
+                motor_library.slots = slots;
+                motor_library.fit_degree = fit_degree;
+            }
+
+            let model = &motor_library.library.models()[motor_library.model];
+            let bus_voltage = solver.0.power.bus_voltage;
+            let (min, max) = model.voltage_range();
+            if bus_voltage < min || bus_voltage > max {
+                ui.label(format!(
+                    "Bus voltage is outside the {min:.1} to {max:.1} V curves, scaling the closest curve"
+                ));
+            }
+
+            // The measured curves next to the one in use show how the thrust shifts with voltage
+            let in_use = model.curve_at(bus_voltage);
+            for (name, curve) in model
+                .curves()
+                .iter()
+                .map(|it| ("Measured", it))
+                .chain([("In use", &in_use)])
+            {
+                let (reverse, forward) = curve.force_range();
+                ui.label(format!(
+                    "{name} {:.1} V: {forward:.1} N forward, {reverse:.1} N reverse, {:.1} A peak",
+                    curve.voltage,
+                    curve.max_current()
+                ));
+            }
+            if let Some(degree) = motor_library.fit_degree {
+                match MotorFit::fit(&in_use, degree) {
+                    Ok(fit) => ui.label(fit.to_string()),
+                    Err(err) => ui.label(format!("Could not fit the curve: {err}")),
+                };
+            }
+
+            ui.allocate_space((ui.available_width(), 0.0).into());
+        });
+
         ui.collapsing("Physics Result", |ui| {
-            let physics_result =
-                reverse::axis_maximums(&motor_conf.0.motor_config, &motor_data.0, 25.0, 0.001);
+            let geometry = &solver.0.geometry;
+            let motor_config = physics::with_vehicle(motor_conf.0.motor_config.clone(), geometry);
+            let power = &solver.0.power;
+            let motor_data = motor_data
+                .0
+                .resolved(motor_conf.0.thruster_models.clone());
+            let physics_result = power.axis_maximums(&motor_config, &motor_data);
             let physics_result: BTreeMap<_, _> = physics_result.into_iter().collect();
+            if power.thruster_current.is_finite() {
+                ui.label(format!(
+                    "Budget: {:.1} A at {:.1} V, {:.1} A per thruster",
+                    power.total_current, power.bus_voltage, power.thruster_current
+                ));
+            } else {
+                ui.label(format!(
+                    "Budget: {:.1} A at {:.1} V",
+                    power.total_current, power.bus_voltage
+                ));
+            }
             ui.label(format!("{physics_result:#.2?}"));
 
+            if let Some(mass_model) = &geometry.mass {
+                let properties = mass_model.properties(&motor_conf.0.motor_config);
+                let (height, offset) = properties.metacentric_height(mass_model.up());
+
+                ui.label(format!(
+                    "Mass: {:.2} kg, displacement: {:.2} L",
+                    properties.mass,
+                    properties.displacement * 1000.0
+                ));
+                ui.label(format!(
+                    "Center of mass: {:.3?}",
+                    properties.center_of_mass.as_slice()
+                ));
+                ui.label(format!(
+                    "Center of buoyancy: {:.3?}",
+                    properties.center_of_buoyancy.as_slice()
+                ));
+                ui.label(format!(
+                    "Metacentric height: {height:.3} m, offset: {:.3} m",
+                    offset.norm()
+                ));
+            }
+
             ui.allocate_space((ui.available_width(), 0.0).into());
         });
 
@@ -34,7 +34,7 @@ fn main() {
     println!("inverse: {:.04}", config.pseudo_inverse);
     let maximums = reverse::axis_maximums(&config, &motor_data, 25.0, 0.00001);
     println!("maximums: {maximums:#.04?}");
-    let score = optimize::evaluate(&config, &Default::default(), &motor_data);
+    let score = optimize::evaluate(&config, &Default::default(), &motor_data.into());
     println!("score: {score:#.04?}");
 }
 
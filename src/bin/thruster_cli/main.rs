@@ -0,0 +1,35 @@
+pub mod motor_report;
+pub mod motors;
+pub mod optimize;
+pub mod thruster_count;
+
+use clap::{Parser, Subcommand};
+
+/// Headless frontend to the thruster configuration optimizer
+#[derive(Parser)]
+#[command(version, about)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+#[allow(clippy::large_enum_variant)]
+enum Command {
+    /// Run an optimization arena and write the best configurations to disk
+    Optimize(optimize::OptimizeArgs),
+    /// Optimize several thruster counts under one heuristic and compare the best configurations
+    ThrusterCounts(thruster_count::ThrusterCountArgs),
+    /// Check motor data or the curves of a motor library and print a report of each
+    MotorReport(motor_report::MotorReportArgs),
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+
+    match cli.command {
+        Command::Optimize(args) => optimize::run(args),
+        Command::ThrusterCounts(args) => thruster_count::run(args),
+        Command::MotorReport(args) => motor_report::run(args),
+    }
+}
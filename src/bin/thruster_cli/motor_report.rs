@@ -0,0 +1,32 @@
+use anyhow::bail;
+use clap::Args;
+
+use crate::motors::MotorArgs;
+
+#[derive(Args)]
+pub struct MotorReportArgs {
+    #[command(flatten)]
+    motors: MotorArgs,
+}
+
+pub fn run(args: MotorReportArgs) -> anyhow::Result<()> {
+    let reports = args.motors.reports()?;
+
+    for (label, report) in &reports {
+        println!("{label}");
+        println!("{report}");
+    }
+
+    let invalid = reports.iter().filter(|(_, it)| it.has_errors()).count();
+    if invalid > 0 {
+        bail!("{invalid} of {} curves have errors", reports.len());
+    }
+
+    // Only valid curves can be fitted
+    for (label, fit) in args.motors.fits()? {
+        println!("{label}");
+        println!("{fit}");
+    }
+
+    Ok(())
+}
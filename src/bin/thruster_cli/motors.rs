@@ -0,0 +1,180 @@
+use std::path::PathBuf;
+
+use anyhow::{ensure, Context};
+use clap::Args;
+use itertools::Itertools;
+use motor_math::FloatType;
+use thruster_sim::{
+    motor_fit::MotorFit,
+    motor_library::{MotorLibrary, MotorModel, ThrustCurve},
+    motor_validation::MotorDataReport,
+    thruster_models::ThrusterModels,
+};
+
+/// Where the motor performance data comes from
+#[derive(Args)]
+pub struct MotorArgs {
+    /// Motor performance data, used when no motor library is given
+    #[arg(long, default_value = "motor_data.csv")]
+    motor_data: PathBuf,
+    /// Library of thruster models with curves at several voltages (.toml or .json), the
+    /// curves are interpolated to the bus voltage of the power budget
+    #[arg(long)]
+    motor_library: Option<PathBuf>,
+    /// Thruster model of the motor library, the first model if not set
+    #[arg(long)]
+    motor_model: Option<String>,
+    /// Comma separated thruster models of the motor library for each slot of the config, the
+    /// thrusters with their own parameters. Slots past the end use the motor model
+    #[arg(long, value_delimiter = ',')]
+    thruster_models: Vec<String>,
+    /// Fit polynomials of this degree to the thrust curves and use them in place of the tables,
+    /// which smooths the gradients of the current budget
+    #[arg(long)]
+    fit_degree: Option<usize>,
+}
+
+impl MotorArgs {
+    /// The thruster models at `voltage` with the model of each slot assigned, and the name of
+    /// the default model if a motor library is used
+    pub fn load(&self, voltage: FloatType) -> anyhow::Result<(ThrusterModels, Option<String>)> {
+        let Some(path) = &self.motor_library else {
+            ensure!(
+                self.thruster_models.is_empty(),
+                "Thruster models need a motor library"
+            );
+
+            let contents = std::fs::read_to_string(&self.motor_data)
+                .with_context(|| format!("Read motor data {}", self.motor_data.display()))?;
+            let name = self.motor_data.display().to_string();
+            let report = MotorDataReport::validate(&name, &contents, 1.0);
+            warn(&name, &report);
+            report.ensure_valid()?;
+
+            // A library of the one curve, so it's scaled to the bus voltage like library curves
+            let measured = table_curve(&contents)?.voltage;
+            let library = MotorLibrary::from_csv(&name, measured, &contents)?;
+            let models = ThrusterModels::from_library(&library, voltage, &name, self.fit_degree)?;
+            describe(library.model(None)?, voltage, self.fit_degree)?;
+
+            return Ok((models, None));
+        };
+
+        for (label, report) in self.reports()? {
+            warn(&label, &report);
+        }
+        let library = MotorLibrary::load(path)?;
+        let default = library.model(self.motor_model.as_deref())?;
+        let models =
+            ThrusterModels::from_library(&library, voltage, &default.name, self.fit_degree)?;
+        let assignment = self
+            .thruster_models
+            .iter()
+            .map(|name| models.index_of(name))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let used = [default.name.as_str()]
+            .into_iter()
+            .chain(self.thruster_models.iter().map(String::as_str))
+            .unique();
+        for name in used {
+            describe(library.model(Some(name))?, voltage, self.fit_degree)?;
+        }
+
+        Ok((
+            models.with_assignment(assignment),
+            Some(default.name.clone()),
+        ))
+    }
+
+    /// The validation report of the motor data, or of each curve of the motor model or of
+    /// every model if none is set
+    pub fn reports(&self) -> anyhow::Result<Vec<(String, MotorDataReport)>> {
+        let Some(path) = &self.motor_library else {
+            let contents = std::fs::read_to_string(&self.motor_data)
+                .with_context(|| format!("Read motor data {}", self.motor_data.display()))?;
+            let name = self.motor_data.display().to_string();
+            let report = MotorDataReport::validate(&name, &contents, 1.0);
+            return Ok(vec![(name, report)]);
+        };
+
+        Ok(MotorLibrary::validate(path)?
+            .into_iter()
+            .filter(|(model, ..)| self.motor_model.as_ref().is_none_or(|it| it == model))
+            .map(|(model, voltage, report)| (format!("{model} at {voltage} V"), report))
+            .collect())
+    }
+
+    /// The fit of the motor data, or of each curve of the motor model or of every model if none
+    /// is set, empty without a fit degree
+    pub fn fits(&self) -> anyhow::Result<Vec<(String, MotorFit)>> {
+        let Some(degree) = self.fit_degree else {
+            return Ok(vec![]);
+        };
+        let Some(path) = &self.motor_library else {
+            let contents = std::fs::read_to_string(&self.motor_data)
+                .with_context(|| format!("Read motor data {}", self.motor_data.display()))?;
+            let fit = MotorFit::fit(&table_curve(&contents)?, degree)?;
+            return Ok(vec![(self.motor_data.display().to_string(), fit)]);
+        };
+
+        let library = MotorLibrary::load(path)?;
+        library
+            .models()
+            .iter()
+            .filter(|model| self.motor_model.as_ref().is_none_or(|it| *it == model.name))
+            .flat_map(|model| {
+                model.curves().iter().map(|curve| {
+                    let fit = MotorFit::fit(curve, degree)
+                        .with_context(|| format!("Fit {} at {} V", model.name, curve.voltage))?;
+                    Ok((format!("{} at {} V", model.name, curve.voltage), fit))
+                })
+            })
+            .collect()
+    }
+}
+
+/// The curve of a motor data CSV, at the mean of its voltage column
+fn table_curve(contents: &str) -> anyhow::Result<ThrustCurve> {
+    let mut curve = ThrustCurve::parse(0.0, contents, 1.0)?;
+    curve.voltage =
+        curve.rows.iter().map(|it| it.voltage).sum::<FloatType>() / curve.rows.len() as FloatType;
+
+    Ok(curve)
+}
+
+/// Points out the warnings of motor data that is about to be used
+fn warn(label: &str, report: &MotorDataReport) {
+    let warnings = report.warnings().count();
+    if warnings > 0 {
+        println!("{label} has {warnings} warnings, see `thruster_cli motor-report`");
+    }
+}
+
+/// Prints the thrust of `model` at `voltage`, and how well it's fitted with `fit_degree`
+fn describe(
+    model: &MotorModel,
+    voltage: FloatType,
+    fit_degree: Option<usize>,
+) -> anyhow::Result<()> {
+    let (min, max) = model.voltage_range();
+    if voltage < min || voltage > max {
+        println!(
+            "{voltage} V is outside the {min} to {max} V curves of {}, scaling the closest curve",
+            model.name
+        );
+    }
+
+    let curve = model.curve_at(voltage);
+    let (reverse, forward) = curve.force_range();
+    println!(
+        "Using {} at {voltage} V: {forward:.1} N forward, {reverse:.1} N reverse, {:.1} A peak",
+        model.name,
+        curve.max_current()
+    );
+    if let Some(degree) = fit_degree {
+        print!("{}", MotorFit::fit(&curve, degree)?);
+    }
+
+    Ok(())
+}
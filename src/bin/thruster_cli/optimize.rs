@@ -0,0 +1,472 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{ensure, Context};
+use clap::Args;
+use motor_math::FloatType;
+use serde::Serialize;
+use thruster_sim::{
+    constraints::PositionConstraints,
+    format::{self, FilePaths},
+    heuristic::{presets, ScoreSettings},
+    hull::{Hull, HullSource},
+    layout::{self, ThrusterLayout},
+    mass::MassModel,
+    optimize::{
+        mounted::{self, MountingPlate},
+        optimizers::OptimizerType,
+        schedule::{Hyperparameters, StepSchedule},
+        ArenaCheckpoint, ArenaType, Execution, OptimizationOutput,
+    },
+};
+
+use crate::motors::MotorArgs;
+
+#[derive(Args)]
+pub struct OptimizeArgs {
+    /// Heuristic preset (.toml or .json), uses the default heuristic if not set
+    #[arg(long)]
+    heuristic: Option<PathBuf>,
+    #[command(flatten)]
+    motors: MotorArgs,
+    /// Optimization type (x3d, x3d-dynamic, symmetrical3, unconstrained6, or
+    /// symmetrical-N/unconstrained-N for N thrusters). N thrusters can also be arranged with
+    /// mirror-xy-N, mirror-xz-N, mirror-yz-N, double-mirror-N or rotational-FOLD-N symmetry
+    #[arg(long, default_value = "symmetrical3")]
+    arena: ArenaType,
+    /// Place the thrusters on the mounts of this mounting plate (.json or .toml) instead of
+    /// optimizing `arena`
+    #[arg(long)]
+    mounts: Option<PathBuf>,
+    /// Number of thrusters to place on the mounting plate
+    #[arg(long, default_value_t = 8)]
+    mounted_thrusters: usize,
+    /// Envelope and keep out volumes thrusters must respect (.toml or .json), overrides the
+    /// constraints of a resumed checkpoint
+    #[arg(long)]
+    constraints: Option<PathBuf>,
+    /// Hull or frame mesh (.stl or .obj) thrusters and their jets are kept clear of, overrides
+    /// the heuristic's hull
+    #[arg(long)]
+    hull: Option<PathBuf>,
+    /// Converts the hull's units to meters, 0.001 for a mesh modelled in millimeters
+    #[arg(long, default_value_t = 1.0)]
+    hull_scale: FloatType,
+    /// Masses and volumes of the vehicle (.toml or .json), moves the rotation center to the
+    /// center of mass and enables the metacentric loss. Overrides the heuristic's mass model
+    #[arg(long)]
+    mass_model: Option<PathBuf>,
+    /// Amps all thrusters may draw together, overrides the heuristic's power budget
+    #[arg(long)]
+    total_current: Option<FloatType>,
+    /// Amps each thruster may draw, overrides the heuristic's power budget. Unlimited by default
+    #[arg(long)]
+    thruster_current: Option<FloatType>,
+    /// Volts of the power bus, overrides the heuristic's power budget
+    #[arg(long)]
+    bus_voltage: Option<FloatType>,
+    /// How points are stepped (serial, rayon or a number of threads)
+    #[arg(long, default_value = "rayon")]
+    execution: Execution,
+    /// Optimization algorithm (adam, momentum, lbfgs, nelder-mead)
+    #[arg(long, default_value = "adam")]
+    optimizer: OptimizerType,
+    /// Adam's first moment decay rate
+    #[arg(long)]
+    beta1: Option<FloatType>,
+    /// Adam's second moment decay rate
+    #[arg(long)]
+    beta2: Option<FloatType>,
+    /// Adam's numerical stability term
+    #[arg(long)]
+    epsilon: Option<FloatType>,
+    /// Momentum coefficient of the momentum optimizer
+    #[arg(long)]
+    momentum: Option<FloatType>,
+    /// Number of past updates L-BFGS uses to approximate the hessian
+    #[arg(long)]
+    memory: Option<usize>,
+    /// Size of the initial Nelder-Mead simplex
+    #[arg(long)]
+    simplex_size: Option<FloatType>,
+    /// Initial step size/learn rate
+    #[arg(long)]
+    step_size: Option<FloatType>,
+    /// Step size schedule (constant, cosine, step-decay, warm-restarts)
+    #[arg(long)]
+    schedule: Option<StepSchedule>,
+    /// Length of the cosine schedule or first warm restart period, or the interval of step decay
+    #[arg(long)]
+    schedule_period: Option<i32>,
+    /// Decay factor of step decay, or the period multiplier of warm restarts
+    #[arg(long)]
+    schedule_factor: Option<FloatType>,
+    /// Smallest step size of the cosine and warm restart schedules relative to the step size
+    #[arg(long)]
+    min_step_ratio: Option<FloatType>,
+    /// Ratio by which a point's score must improve to count as an improvement
+    #[arg(long)]
+    frontier_ratio_threshold: Option<FloatType>,
+    /// Number of steps a point may go without improving before it is retired
+    #[arg(long)]
+    frontier_time_limit: Option<i32>,
+    /// Gradient norm below which a point is retired
+    #[arg(long)]
+    gradient_tolerance: Option<FloatType>,
+    /// Maximum number of steps of an individual point
+    #[arg(long)]
+    max_point_iterations: Option<i32>,
+    /// Number of steps between searches for the best spin direction of each thruster, 0 keeps
+    /// every thruster spinning clockwise
+    #[arg(long)]
+    spin_interval: Option<i32>,
+    /// Number of steps between searches for a better mount for each thruster, 0 keeps the
+    /// initial mounts
+    #[arg(long)]
+    discrete_interval: Option<i32>,
+    /// Number of points in the arena
+    #[arg(long, default_value_t = 100)]
+    points: usize,
+    /// Maximum number of optimizer steps
+    #[arg(long, default_value_t = 5000)]
+    iterations: usize,
+    /// Stop early once the best score improves by less than this ratio over `window` steps
+    #[arg(long, default_value_t = 1e-4)]
+    tolerance: FloatType,
+    /// Number of steps the improvement of the best score is measured over
+    #[arg(long, default_value_t = 100)]
+    window: usize,
+    /// Seed used to generate the initial points
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Number of configurations to write out
+    #[arg(long, default_value_t = 10)]
+    top: usize,
+    /// Output file (.json or .toml)
+    #[arg(long, short, default_value = "optimization_results.json")]
+    output: PathBuf,
+    /// Also write the best configuration as a standalone thruster layout (.json or .toml)
+    #[arg(long)]
+    export_best: Option<PathBuf>,
+    /// Periodically save the arena to this file (.json or .toml) so the run can be resumed
+    #[arg(long)]
+    checkpoint: Option<PathBuf>,
+    /// Number of steps between checkpoints
+    #[arg(long, default_value_t = 100)]
+    checkpoint_interval: usize,
+    /// Resume from a checkpoint instead of generating new points, `arena` (or `mounts` and
+    /// `mounted-thrusters`) must match the arena the checkpoint was created with. The heuristic
+    /// and seed of the checkpoint are used, its hull, mass model, power budget and
+    /// hyperparameters can be overridden
+    #[arg(long)]
+    resume: Option<PathBuf>,
+}
+
+#[derive(Clone, Serialize)]
+struct OptimizationRun {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    arena: Option<ArenaType>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mounting_plate: Option<MountingPlate>,
+    constraints: PositionConstraints,
+    /// The thruster model of the motor library
+    #[serde(skip_serializing_if = "Option::is_none")]
+    motor_model: Option<String>,
+    /// The thruster model of each slot, slots past the end use the motor model
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    thruster_models: Vec<String>,
+    optimizer: OptimizerType,
+    hyperparameters: Hyperparameters,
+    seed: u64,
+    points: usize,
+    iterations: usize,
+    heuristic: ScoreSettings,
+    results: Vec<OptimizationResult>,
+}
+
+#[derive(Clone, Serialize)]
+struct OptimizationResult {
+    idx: usize,
+    converged: bool,
+    /// Columns of the parameter matrix
+    parameters: Vec<Vec<FloatType>>,
+    /// The index of the mount of each thruster when placed on a mounting plate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mounts: Option<Vec<usize>>,
+    /// Number of thrusters outside of the position constraints
+    constraint_violations: usize,
+    layout: ThrusterLayout,
+}
+
+impl FilePaths for OptimizationRun {
+    fn load_paths(&mut self, directory: &Path) -> anyhow::Result<()> {
+        self.heuristic.load_paths(directory)?;
+        for result in &mut self.results {
+            result.layout.load_paths(directory)?;
+        }
+
+        Ok(())
+    }
+
+    fn relative_paths(&mut self, directory: &Path) {
+        self.heuristic.relative_paths(directory);
+        for result in &mut self.results {
+            result.layout.relative_paths(directory);
+        }
+    }
+}
+
+impl OptimizationResult {
+    fn new(output: &OptimizationOutput, heuristic: &ScoreSettings, mounted: bool) -> Self {
+        Self {
+            idx: output.idx,
+            converged: output.converged,
+            parameters: output
+                .parameters
+                .column_iter()
+                .map(|column| column.iter().copied().collect())
+                .collect(),
+            mounts: mounted.then(|| mounted::assigned_mounts(&output.parameters)),
+            constraint_violations: output.constraint_violations,
+            layout: ThrusterLayout::from_output(output, heuristic),
+        }
+    }
+}
+
+impl OptimizeArgs {
+    /// The selected optimizer with any overridden settings applied
+    fn optimizer_settings(&self) -> OptimizerType {
+        let mut optimizer = self.optimizer;
+
+        match &mut optimizer {
+            OptimizerType::Adam(adam) => {
+                adam.beta_1 = self.beta1.unwrap_or(adam.beta_1);
+                adam.beta_2 = self.beta2.unwrap_or(adam.beta_2);
+                adam.epsilon = self.epsilon.unwrap_or(adam.epsilon);
+            }
+            OptimizerType::Momentum(momentum) => {
+                momentum.momentum = self.momentum.unwrap_or(momentum.momentum);
+            }
+            OptimizerType::Lbfgs(lbfgs) => {
+                lbfgs.memory = self.memory.unwrap_or(lbfgs.memory);
+            }
+            OptimizerType::NelderMead(nelder_mead) => {
+                nelder_mead.simplex_size = self.simplex_size.unwrap_or(nelder_mead.simplex_size);
+            }
+        }
+
+        optimizer
+    }
+
+    /// Applies the hull, mass model and power budget set on the command line, returns whether
+    /// any were set
+    fn apply_heuristic_overrides(&self, heuristic: &mut ScoreSettings) -> anyhow::Result<bool> {
+        if let Some(path) = &self.hull {
+            let source = HullSource {
+                scale: self.hull_scale,
+                ..HullSource::new(path)
+            };
+            heuristic.geometry.hull = Some(Hull::load(source, Path::new(""))?);
+        }
+        if let Some(path) = &self.mass_model {
+            heuristic.geometry.mass = Some(MassModel::load(path)?);
+        }
+        let power = &mut heuristic.power;
+        power.total_current = self.total_current.unwrap_or(power.total_current);
+        power.thruster_current = self.thruster_current.unwrap_or(power.thruster_current);
+        power.bus_voltage = self.bus_voltage.unwrap_or(power.bus_voltage);
+
+        Ok(self.hull.is_some()
+            || self.mass_model.is_some()
+            || self.total_current.is_some()
+            || self.thruster_current.is_some()
+            || self.bus_voltage.is_some())
+    }
+
+    /// Applies any hyperparameters set on the command line
+    fn apply_hyperparameters(&self, hyperparameters: &mut Hyperparameters) {
+        hyperparameters.step_size = self.step_size.unwrap_or(hyperparameters.step_size);
+        hyperparameters.schedule = self.schedule.unwrap_or(hyperparameters.schedule);
+
+        match &mut hyperparameters.schedule {
+            StepSchedule::Constant => {}
+            StepSchedule::Cosine { period, min_ratio } => {
+                *period = self.schedule_period.unwrap_or(*period);
+                *min_ratio = self.min_step_ratio.unwrap_or(*min_ratio);
+            }
+            StepSchedule::StepDecay { interval, factor } => {
+                *interval = self.schedule_period.unwrap_or(*interval);
+                *factor = self.schedule_factor.unwrap_or(*factor);
+            }
+            StepSchedule::WarmRestarts {
+                period,
+                period_multiplier,
+                min_ratio,
+            } => {
+                *period = self.schedule_period.unwrap_or(*period);
+                *period_multiplier = self.schedule_factor.unwrap_or(*period_multiplier);
+                *min_ratio = self.min_step_ratio.unwrap_or(*min_ratio);
+            }
+        }
+
+        let convergence = &mut hyperparameters.convergence;
+        convergence.frontier_ratio_threshold = self
+            .frontier_ratio_threshold
+            .unwrap_or(convergence.frontier_ratio_threshold);
+        convergence.frontier_time_limit = self
+            .frontier_time_limit
+            .unwrap_or(convergence.frontier_time_limit);
+        convergence.gradient_tolerance = self
+            .gradient_tolerance
+            .unwrap_or(convergence.gradient_tolerance);
+        convergence.max_iterations = self
+            .max_point_iterations
+            .unwrap_or(convergence.max_iterations);
+
+        hyperparameters.spin_interval = self.spin_interval.unwrap_or(hyperparameters.spin_interval);
+        hyperparameters.discrete_interval = self
+            .discrete_interval
+            .unwrap_or(hyperparameters.discrete_interval);
+    }
+}
+
+pub fn run(args: OptimizeArgs) -> anyhow::Result<()> {
+    let optimizer = args.optimizer_settings();
+    let mounting_plate = args.mounts.as_ref().map(MountingPlate::load).transpose()?;
+    let mut arena = match &mounting_plate {
+        Some(plate) => {
+            mounted::new_arena(plate, args.mounted_thrusters, args.execution, optimizer)?
+        }
+        None => args.arena.new_arena(args.execution, optimizer)?,
+    };
+    let (heuristic, seed, points) = match &args.resume {
+        Some(path) => {
+            ensure!(
+                args.heuristic.is_none(),
+                "A resumed run uses the heuristic of its checkpoint, --heuristic can't be set"
+            );
+            let checkpoint = ArenaCheckpoint::load(path)?;
+            let mut heuristic = checkpoint.heuristic.clone();
+            let (seed, points) = (checkpoint.seed, checkpoint.points.len());
+            arena
+                .restore(checkpoint)
+                .with_context(|| format!("Resume from {}", path.display()))?;
+
+            // Setting the heuristic reactivates converged points, only done if it changed
+            if args.apply_heuristic_overrides(&mut heuristic)? {
+                arena.set_heuristic(heuristic.clone());
+            }
+
+            println!("Resumed from {}", path.display());
+            (heuristic, seed, points)
+        }
+        None => {
+            let mut heuristic = match &args.heuristic {
+                Some(path) => presets::load_preset(path)?.flatten(),
+                None => ScoreSettings::default(),
+            };
+            args.apply_heuristic_overrides(&mut heuristic)?;
+
+            arena.reset(args.points, heuristic.clone(), args.seed);
+            (heuristic, args.seed, args.points)
+        }
+    };
+
+    // Loaded after resuming so the curves match the bus voltage of the checkpoint
+    let (motor_data, motor_model) = args.motors.load(heuristic.power.bus_voltage)?;
+
+    let mut hyperparameters = arena.hyperparameters();
+    args.apply_hyperparameters(&mut hyperparameters);
+    arena.set_hyperparameters(hyperparameters);
+
+    if let Some(path) = &args.constraints {
+        arena.set_constraints(PositionConstraints::load(path)?);
+    }
+
+    let mut best_scores = Vec::with_capacity(args.iterations);
+    let mut top = Vec::new();
+
+    for iteration in 0..args.iterations {
+        top = arena.step(&motor_data).take(args.top).collect();
+
+        let Some(best) = top.first() else {
+            break;
+        };
+        best_scores.push(best.score);
+
+        if iteration % 100 == 0 {
+            println!("Step {iteration}: best score {:.04}", best.score);
+        }
+
+        if let Some(path) = &args.checkpoint {
+            if (iteration + 1) % args.checkpoint_interval.max(1) == 0 {
+                arena.checkpoint().save(path)?;
+            }
+        }
+
+        if arena.converged() {
+            println!("All points converged after {} steps", iteration + 1);
+            break;
+        }
+
+        if iteration >= args.window {
+            let previous = best_scores[iteration - args.window];
+            if (best.score - previous).abs() <= args.tolerance * previous.abs() {
+                println!("Converged after {} steps", iteration + 1);
+                break;
+            }
+        }
+    }
+
+    if let Some(best) = top.first() {
+        println!("Best score {:.04}", best.score);
+    }
+    for output in top.iter().filter(|it| it.constraint_violations > 0) {
+        eprintln!(
+            "Warning: config {} has {} thrusters outside of the position constraints, they \
+             can't all be satisfied",
+            output.idx, output.constraint_violations
+        );
+    }
+
+    if let Some(path) = &args.checkpoint {
+        arena.checkpoint().save(path)?;
+        println!("Saved checkpoint to {}", path.display());
+    }
+
+    let mounted = mounting_plate.is_some();
+    let run = OptimizationRun {
+        arena: (!mounted).then_some(args.arena),
+        mounting_plate,
+        constraints: arena.constraints().clone(),
+        motor_model,
+        thruster_models: motor_data
+            .assignment()
+            .iter()
+            .map(|&model| motor_data.names()[model].clone())
+            .collect(),
+        optimizer,
+        hyperparameters,
+        seed,
+        points,
+        iterations: best_scores.len(),
+        results: top
+            .iter()
+            .map(|output| OptimizationResult::new(output, &heuristic, mounted))
+            .collect(),
+        heuristic,
+    };
+    format::save_with_paths(&args.output, &run)?;
+
+    if let (Some(path), Some(best)) = (&args.export_best, run.results.first()) {
+        layout::save_layout(path, &best.layout)?;
+        println!("Wrote best layout to {}", path.display());
+    }
+
+    println!(
+        "Wrote {} configurations to {}",
+        run.results.len(),
+        args.output.display()
+    );
+
+    Ok(())
+}
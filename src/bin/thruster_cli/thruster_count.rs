@@ -0,0 +1,225 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use motor_math::FloatType;
+use serde::Serialize;
+use thruster_sim::{
+    constraints::PositionConstraints,
+    format::{self, FilePaths},
+    heuristic::{presets, ScoreSettings},
+    hull::{Hull, HullSource},
+    mass::MassModel,
+    optimize::{
+        optimizers::OptimizerType,
+        schedule::Hyperparameters,
+        thruster_count::{ThrusterCountResult, ThrusterCountSearch},
+        Execution,
+    },
+};
+
+use crate::motors::MotorArgs;
+
+#[derive(Args)]
+pub struct ThrusterCountArgs {
+    /// Heuristic preset (.toml or .json), uses the default heuristic if not set
+    #[arg(long)]
+    heuristic: Option<PathBuf>,
+    #[command(flatten)]
+    motors: MotorArgs,
+    /// Thruster counts to compare
+    #[arg(long, value_delimiter = ',', default_value = "4,6,8")]
+    thruster_counts: Vec<usize>,
+    /// Mirror the thrusters across the YZ plane, all counts must be even
+    #[arg(long)]
+    symmetrical: bool,
+    /// Score added per thruster, overrides the heuristic's thruster count loss
+    #[arg(long)]
+    thruster_cost: Option<FloatType>,
+    /// Envelope and keep out volumes thrusters must respect (.toml or .json)
+    #[arg(long)]
+    constraints: Option<PathBuf>,
+    /// Hull or frame mesh (.stl or .obj) thrusters and their jets are kept clear of, overrides
+    /// the heuristic's hull
+    #[arg(long)]
+    hull: Option<PathBuf>,
+    /// Converts the hull's units to meters, 0.001 for a mesh modelled in millimeters
+    #[arg(long, default_value_t = 1.0)]
+    hull_scale: FloatType,
+    /// Masses and volumes of the vehicle (.toml or .json), moves the rotation center to the
+    /// center of mass and enables the metacentric loss. Overrides the heuristic's mass model
+    #[arg(long)]
+    mass_model: Option<PathBuf>,
+    /// Amps all thrusters may draw together, overrides the heuristic's power budget
+    #[arg(long)]
+    total_current: Option<FloatType>,
+    /// Amps each thruster may draw, overrides the heuristic's power budget. Unlimited by default
+    #[arg(long)]
+    thruster_current: Option<FloatType>,
+    /// Volts of the power bus, overrides the heuristic's power budget
+    #[arg(long)]
+    bus_voltage: Option<FloatType>,
+    /// How points are stepped (serial, rayon or a number of threads)
+    #[arg(long, default_value = "rayon")]
+    execution: Execution,
+    /// Optimization algorithm (adam, momentum, lbfgs, nelder-mead)
+    #[arg(long, default_value = "adam")]
+    optimizer: OptimizerType,
+    /// Initial step size/learn rate
+    #[arg(long)]
+    step_size: Option<FloatType>,
+    /// Number of points in each arena
+    #[arg(long, default_value_t = 100)]
+    points: usize,
+    /// Maximum number of optimizer steps
+    #[arg(long, default_value_t = 5000)]
+    iterations: usize,
+    /// Seed used to generate the initial points
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+    /// Output file (.json or .toml)
+    #[arg(long, short, default_value = "thruster_counts.json")]
+    output: PathBuf,
+}
+
+#[derive(Clone, Serialize)]
+struct ThrusterCountRun {
+    optimizer: OptimizerType,
+    hyperparameters: Hyperparameters,
+    seed: u64,
+    points: usize,
+    iterations: usize,
+    heuristic: ScoreSettings,
+    results: Vec<ThrusterCountResult>,
+}
+
+impl FilePaths for ThrusterCountRun {
+    fn load_paths(&mut self, directory: &Path) -> anyhow::Result<()> {
+        self.heuristic.load_paths(directory)?;
+        for result in &mut self.results {
+            result.layout.load_paths(directory)?;
+        }
+
+        Ok(())
+    }
+
+    fn relative_paths(&mut self, directory: &Path) {
+        self.heuristic.relative_paths(directory);
+        for result in &mut self.results {
+            result.layout.relative_paths(directory);
+        }
+    }
+}
+
+pub fn run(args: ThrusterCountArgs) -> anyhow::Result<()> {
+    let mut heuristic = match &args.heuristic {
+        Some(path) => presets::load_preset(path)?.flatten(),
+        None => ScoreSettings::default(),
+    };
+    heuristic.thruster_count_loss = args.thruster_cost.unwrap_or(heuristic.thruster_count_loss);
+    if let Some(path) = &args.hull {
+        let source = HullSource {
+            scale: args.hull_scale,
+            ..HullSource::new(path)
+        };
+        heuristic.geometry.hull = Some(Hull::load(source, Path::new(""))?);
+    }
+    if let Some(path) = &args.mass_model {
+        heuristic.geometry.mass = Some(MassModel::load(path)?);
+    }
+    let power = &mut heuristic.power;
+    power.total_current = args.total_current.unwrap_or(power.total_current);
+    power.thruster_current = args.thruster_current.unwrap_or(power.thruster_current);
+    power.bus_voltage = args.bus_voltage.unwrap_or(power.bus_voltage);
+
+    let (motor_data, _) = args.motors.load(heuristic.power.bus_voltage)?;
+
+    let mut search = ThrusterCountSearch::new(
+        &args.thruster_counts,
+        args.symmetrical,
+        args.execution,
+        args.optimizer,
+    )?;
+    search.reset(args.points, heuristic.clone(), args.seed);
+    if let Some(path) = &args.constraints {
+        search.set_constraints(&PositionConstraints::load(path)?);
+    }
+
+    let mut hyperparameters = Hyperparameters::default();
+    hyperparameters.step_size = args.step_size.unwrap_or(hyperparameters.step_size);
+    search.set_hyperparameters(hyperparameters);
+
+    let mut iterations = 0;
+    for iteration in 0..args.iterations {
+        search.step(&motor_data);
+        iterations = iteration + 1;
+
+        if iteration % 100 == 0 {
+            let scores = search
+                .best_scores()
+                .map(|(thruster_count, score)| format!("{thruster_count}: {score:.04}"))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("Step {iteration}: best scores {scores}");
+        }
+
+        if search.converged() {
+            println!("All points converged after {iterations} steps");
+            break;
+        }
+    }
+
+    let results = search.comparison(&motor_data);
+
+    println!(
+        "{:>9} {:>10} {:>8} {:>8} {:>8} {:>8} {:>8} {:>8} {:>10}",
+        "Thrusters", "Score", "X", "Y", "Z", "X Rot", "Y Rot", "Z Rot", "Max Amps"
+    );
+    for result in &results {
+        let max = result.axis_maximums;
+        let current = result.total_current;
+        let max_current = [
+            current.x,
+            current.y,
+            current.z,
+            current.x_rot,
+            current.y_rot,
+            current.z_rot,
+        ]
+        .into_iter()
+        .fold(0.0, FloatType::max);
+
+        println!(
+            "{:>9} {:>10.04} {:>8.02} {:>8.02} {:>8.02} {:>8.02} {:>8.02} {:>8.02} {:>10.02}",
+            result.thruster_count,
+            result.score,
+            max.x,
+            max.y,
+            max.z,
+            max.x_rot,
+            max.y_rot,
+            max.z_rot,
+            max_current
+        );
+    }
+
+    if let Some(best) = results
+        .iter()
+        .max_by(|a, b| FloatType::total_cmp(&a.score, &b.score))
+    {
+        println!("Best thruster count {}", best.thruster_count);
+    }
+
+    let run = ThrusterCountRun {
+        optimizer: args.optimizer,
+        hyperparameters,
+        seed: args.seed,
+        points: args.points,
+        iterations,
+        heuristic,
+        results,
+    };
+    format::save_with_paths(&args.output, &run)?;
+    println!("Wrote comparison to {}", args.output.display());
+
+    Ok(())
+}
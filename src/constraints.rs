@@ -0,0 +1,366 @@
+use std::path::Path;
+
+use motor_math::{FloatType, Number};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::format;
+
+/// Number of times the keep out volumes and envelope are applied in turn, a position pushed out
+/// of one volume can land in another
+const MAX_PASSES: usize = 8;
+/// Distance in meters a position may be inside a keep out volume and still count as outside,
+/// so positions pushed exactly onto a surface aren't pushed again by rounding errors
+const TOLERANCE: FloatType = 1e-9;
+
+/// Hard limits on where thrusters may be placed, applied every time a point is normalised
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PositionConstraints {
+    /// Thrusters are kept inside this box
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub envelope: Option<Envelope>,
+    /// Volumes no thruster may be placed in, such as the battery or camera
+    pub keep_out: Vec<KeepOut>,
+    /// Distance in meters thrusters are kept from every keep out volume
+    pub clearance: FloatType,
+}
+
+/// An axis aligned box relative to the center of the vehicle
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Envelope {
+    pub min: [FloatType; 3],
+    pub max: [FloatType; 3],
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeepOut {
+    /// The component occupying the volume, only used for display
+    #[serde(default)]
+    pub name: String,
+    #[serde(flatten)]
+    pub volume: Volume,
+}
+
+/// A volume relative to the center of the vehicle, all lengths in meters
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "shape", rename_all = "snake_case")]
+pub enum Volume {
+    /// An axis aligned box
+    Box {
+        min: [FloatType; 3],
+        max: [FloatType; 3],
+    },
+    /// A cylinder between the centers of its end caps
+    Cylinder {
+        start: [FloatType; 3],
+        end: [FloatType; 3],
+        radius: FloatType,
+    },
+    Sphere {
+        center: [FloatType; 3],
+        radius: FloatType,
+    },
+}
+
+impl Envelope {
+    fn clamp(&self, position: Vector3<FloatType>) -> Vector3<FloatType> {
+        Vector3::from_fn(|idx, _| position[idx].clamp(self.min[idx], self.max[idx]))
+    }
+}
+
+impl Volume {
+    /// The shortest move that takes `position` out of the volume grown by `clearance`, or
+    /// `None` if it is already outside
+    pub fn push_out(
+        &self,
+        position: Vector3<FloatType>,
+        clearance: FloatType,
+    ) -> Option<Vector3<FloatType>> {
+        match *self {
+            Volume::Box { min, max } => {
+                let min = Vector3::from(min).add_scalar(-clearance);
+                let max = Vector3::from(max).add_scalar(clearance);
+                if (0..3).any(|idx| {
+                    position[idx] <= min[idx] + TOLERANCE || position[idx] >= max[idx] - TOLERANCE
+                }) {
+                    return None;
+                }
+
+                (0..3)
+                    .flat_map(|idx| {
+                        [min[idx] - position[idx], max[idx] - position[idx]]
+                            .map(|distance| Vector3::ith(idx, distance))
+                    })
+                    .min_by(|a, b| FloatType::total_cmp(&a.norm(), &b.norm()))
+            }
+            Volume::Cylinder { start, end, radius } => {
+                let start = Vector3::from(start);
+                let axis = Vector3::from(end) - start;
+                let length = axis.norm();
+                let axis = axis.try_normalize(1e-9)?;
+                let radius = radius + clearance;
+
+                let along = (position - start).dot(&axis);
+                let radial = position - start - axis * along;
+                let distance = radial.norm();
+                if along <= -clearance + TOLERANCE
+                    || along >= length + clearance - TOLERANCE
+                    || distance >= radius - TOLERANCE
+                {
+                    return None;
+                }
+
+                let outward = if distance > 1e-9 {
+                    radial / distance
+                } else {
+                    perpendicular(axis)
+                };
+
+                [
+                    outward * (radius - distance),
+                    axis * (-clearance - along),
+                    axis * (length + clearance - along),
+                ]
+                .into_iter()
+                .min_by(|a, b| FloatType::total_cmp(&a.norm(), &b.norm()))
+            }
+            Volume::Sphere { center, radius } => {
+                let offset = position - Vector3::from(center);
+                let distance = offset.norm();
+                let radius = radius + clearance;
+                if distance >= radius - TOLERANCE {
+                    return None;
+                }
+
+                let outward = if distance > 1e-9 {
+                    offset / distance
+                } else {
+                    Vector3::z()
+                };
+
+                Some(outward * (radius - distance))
+            }
+        }
+    }
+}
+
+/// Any unit vector perpendicular to `axis`
+fn perpendicular(axis: Vector3<FloatType>) -> Vector3<FloatType> {
+    let candidate = if axis.x.abs() < 0.9 {
+        Vector3::x()
+    } else {
+        Vector3::y()
+    };
+
+    axis.cross(&candidate).normalize()
+}
+
+impl PositionConstraints {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        format::load(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        format::save(path, self)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.envelope.is_none() && self.keep_out.is_empty()
+    }
+
+    /// These constraints with positions also kept inside `envelope`, so both are satisfied
+    /// together rather than one undoing the other
+    pub fn within(&self, envelope: Envelope) -> Self {
+        let envelope = match self.envelope {
+            Some(existing) => Envelope {
+                min: [0, 1, 2].map(|idx| existing.min[idx].max(envelope.min[idx])),
+                max: [0, 1, 2].map(|idx| existing.max[idx].min(envelope.max[idx])),
+            },
+            None => envelope,
+        };
+
+        Self {
+            envelope: Some(envelope),
+            ..self.clone()
+        }
+    }
+
+    /// True if a thruster may be placed at `position`
+    pub fn allows(&self, position: Vector3<FloatType>) -> bool {
+        self.envelope
+            .is_none_or(|envelope| envelope.clamp(position) == position)
+            && self
+                .keep_out
+                .iter()
+                .all(|it| it.volume.push_out(position, self.clearance).is_none())
+    }
+
+    /// Moves `position` into the envelope and out of every keep out volume
+    pub fn constrain<D: Number>(&self, position: Vector3<D>) -> Vector3<D> {
+        self.constrain_images(position, 1, |_, it| it, |_, it| it)
+    }
+
+    /// Like `constrain` for a thruster that is copied to `image_count` positions, for example
+    /// by a symmetry. `transform` maps the position to its `image`th copy and `inverse` maps a
+    /// copy back, every copy ends up satisfying the constraints
+    ///
+    /// When the volumes and envelope can't all be satisfied, for example a keep out volume
+    /// filling the envelope, it gives up after `MAX_PASSES` with the position still outside
+    /// of them. `allows` detects that
+    pub fn constrain_images<D: Number>(
+        &self,
+        mut position: Vector3<D>,
+        image_count: usize,
+        transform: impl Fn(usize, Vector3<D>) -> Vector3<D>,
+        inverse: impl Fn(usize, Vector3<D>) -> Vector3<D>,
+    ) -> Vector3<D> {
+        if self.is_empty() {
+            return position;
+        }
+
+        for _ in 0..MAX_PASSES {
+            let mut moved = false;
+
+            for image in 0..image_count {
+                let image_position = transform(image, position);
+                if let Some(displacement) = self.displacement(image_position.map(|it| it.re())) {
+                    position = inverse(image, image_position + displacement.map(D::from));
+                    moved = true;
+                }
+            }
+
+            if !moved {
+                break;
+            }
+        }
+
+        position
+    }
+
+    /// The move that takes `position` out of the keep out volumes and into the envelope, if
+    /// it needs to move at all
+    fn displacement(&self, position: Vector3<FloatType>) -> Option<Vector3<FloatType>> {
+        let mut constrained = position;
+        for keep_out in &self.keep_out {
+            if let Some(displacement) = keep_out.volume.push_out(constrained, self.clearance) {
+                constrained += displacement;
+            }
+        }
+        if let Some(envelope) = &self.envelope {
+            constrained = envelope.clamp(constrained);
+        }
+
+        (constrained != position).then(|| constrained - position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use motor_math::FloatType;
+    use nalgebra::{vector, Vector3};
+
+    use super::{Envelope, KeepOut, PositionConstraints, Volume};
+
+    fn assert_close(a: Vector3<FloatType>, b: Vector3<FloatType>) {
+        assert!((a - b).norm() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn box_push_out_takes_the_nearest_face() {
+        let volume = Volume::Box {
+            min: [-1.0, -1.0, -1.0],
+            max: [1.0, 1.0, 1.0],
+        };
+
+        let displacement = volume.push_out(vector![0.8, 0.1, -0.2], 0.0).unwrap();
+        assert_close(displacement, vector![0.2, 0.0, 0.0]);
+        let displacement = volume.push_out(vector![0.0, 0.0, -0.9], 0.5).unwrap();
+        assert_close(displacement, vector![0.0, 0.0, -0.6]);
+
+        assert_eq!(volume.push_out(vector![1.2, 0.0, 0.0], 0.0), None);
+        assert!(volume.push_out(vector![1.2, 0.0, 0.0], 0.5).is_some());
+    }
+
+    #[test]
+    fn cylinder_push_out_leaves_radially_or_past_the_caps() {
+        let volume = Volume::Cylinder {
+            start: [0.0, 0.0, 0.0],
+            end: [0.0, 0.0, 2.0],
+            radius: 0.5,
+        };
+
+        let displacement = volume.push_out(vector![0.4, 0.0, 1.0], 0.0).unwrap();
+        assert_close(displacement, vector![0.1, 0.0, 0.0]);
+        let displacement = volume.push_out(vector![0.0, 0.1, 1.9], 0.0).unwrap();
+        assert_close(displacement, vector![0.0, 0.0, 0.1]);
+        let displacement = volume.push_out(vector![0.0, 0.0, 0.05], 0.1).unwrap();
+        assert_close(displacement, vector![0.0, 0.0, -0.15]);
+
+        // Exactly on the axis it still leaves in some direction
+        let displacement = volume.push_out(vector![0.0, 0.0, 1.0], 0.0).unwrap();
+        assert!((displacement.norm() - 0.5).abs() < 1e-9);
+        assert!(displacement.z.abs() < 1e-9);
+
+        assert_eq!(volume.push_out(vector![0.6, 0.0, 1.0], 0.0), None);
+        assert_eq!(volume.push_out(vector![0.0, 0.0, 2.5], 0.0), None);
+    }
+
+    #[test]
+    fn sphere_push_out_leaves_radially() {
+        let volume = Volume::Sphere {
+            center: [1.0, 0.0, 0.0],
+            radius: 0.5,
+        };
+
+        let displacement = volume.push_out(vector![1.0, 0.3, 0.0], 0.1).unwrap();
+        assert_close(displacement, vector![0.0, 0.3, 0.0]);
+        let displacement = volume.push_out(vector![1.0, 0.0, 0.0], 0.0).unwrap();
+        assert!((displacement.norm() - 0.5).abs() < 1e-9);
+
+        assert_eq!(volume.push_out(vector![1.6, 0.0, 0.0], 0.0), None);
+    }
+
+    #[test]
+    fn pushed_positions_are_allowed() {
+        let constraints = PositionConstraints {
+            keep_out: vec![KeepOut {
+                name: "Battery".to_owned(),
+                volume: Volume::Sphere {
+                    center: [0.0; 3],
+                    radius: 0.3,
+                },
+            }],
+            clearance: 0.05,
+            ..Default::default()
+        };
+
+        for position in [vector![0.1, 0.2, 0.0], vector![-0.01, 0.0, 0.02]] {
+            let constrained = constraints.constrain(position);
+            assert!(constraints.allows(constrained), "{constrained}");
+            assert!((constrained.norm() - 0.35).abs() < 1e-9, "{constrained}");
+        }
+    }
+
+    #[test]
+    fn unsatisfiable_constraints_are_detected() {
+        // The keep out volume covers the whole envelope
+        let constraints = PositionConstraints {
+            envelope: Some(Envelope {
+                min: [-0.2; 3],
+                max: [0.2; 3],
+            }),
+            keep_out: vec![KeepOut {
+                name: "Hull".to_owned(),
+                volume: Volume::Box {
+                    min: [-1.0; 3],
+                    max: [1.0; 3],
+                },
+            }],
+            ..Default::default()
+        };
+
+        let constrained = constraints.constrain(vector![0.1, 0.0, 0.0]);
+        assert!(!constraints.allows(constrained), "{constrained}");
+    }
+}
@@ -0,0 +1,241 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// The on disk formats supported for presets, layouts and results
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Toml,
+    Json,
+}
+
+impl FileFormat {
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path.extension().and_then(|it| it.to_str()) {
+            Some("toml") => Ok(FileFormat::Toml),
+            Some("json") => Ok(FileFormat::Json),
+            _ => bail!(
+                "Unknown file format for {}, expected a .toml or .json file",
+                path.display()
+            ),
+        }
+    }
+
+    pub fn parse<T: DeserializeOwned>(&self, contents: &str) -> anyhow::Result<T> {
+        match self {
+            FileFormat::Toml => toml::from_str(contents).context("Parse toml"),
+            FileFormat::Json => serde_json::from_str(contents).context("Parse json"),
+        }
+    }
+
+    pub fn write<T: Serialize>(&self, value: &T) -> anyhow::Result<String> {
+        match self {
+            FileFormat::Toml => toml::to_string_pretty(value).context("Serialize toml"),
+            FileFormat::Json => serde_json::to_string_pretty(value).context("Serialize json"),
+        }
+    }
+}
+
+/// A file that references other files by paths relative to its own directory
+pub trait FilePaths {
+    /// Makes the paths absolute by joining them to `directory`, and loads what they reference
+    fn load_paths(&mut self, directory: &Path) -> anyhow::Result<()>;
+
+    /// Makes the paths relative to `directory` where they are inside it
+    fn relative_paths(&mut self, directory: &Path);
+}
+
+/// Loads a file, any paths inside it are left as written, see `load_with_paths`
+pub fn load<T: DeserializeOwned>(path: impl AsRef<Path>) -> anyhow::Result<T> {
+    let path = path.as_ref();
+    let contents = fs::read_to_string(path).with_context(|| format!("Read {}", path.display()))?;
+    let format = FileFormat::from_path(path)?;
+
+    format
+        .parse(&contents)
+        .with_context(|| format!("Load {}", path.display()))
+}
+
+/// Saves a file, any paths inside it are written as they are, see `save_with_paths`
+pub fn save<T: Serialize>(path: impl AsRef<Path>, value: &T) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let format = FileFormat::from_path(path)?;
+    let contents = format.write(value)?;
+
+    fs::write(path, contents).with_context(|| format!("Write {}", path.display()))
+}
+
+/// Loads a file, paths inside it are relative to its directory
+pub fn load_with_paths<T: DeserializeOwned + FilePaths>(
+    path: impl AsRef<Path>,
+) -> anyhow::Result<T> {
+    let path = path.as_ref();
+    let mut value: T = load(path)?;
+    value
+        .load_paths(&directory(path))
+        .with_context(|| format!("Load {}", path.display()))?;
+
+    Ok(value)
+}
+
+/// Saves a file, paths inside it are written relative to its directory where possible
+pub fn save_with_paths<T: Serialize + FilePaths + Clone>(
+    path: impl AsRef<Path>,
+    value: &T,
+) -> anyhow::Result<()> {
+    let path = path.as_ref();
+    let mut value = value.clone();
+    value.relative_paths(&directory(path));
+
+    save(path, &value)
+}
+
+/// The absolute directory of `file`, which paths inside it are relative to
+pub fn directory(file: &Path) -> PathBuf {
+    let directory = file.parent().unwrap_or(Path::new(""));
+    std::path::absolute(directory).unwrap_or(directory.to_owned())
+}
+
+/// A fresh directory for a test's files
+#[cfg(test)]
+pub(crate) fn test_directory(name: &str) -> PathBuf {
+    let directory =
+        std::env::temp_dir().join(format!("thruster-sim-{name}-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+    directory
+}
+
+/// Serde helpers for floats that may not be finite, which json can not represent
+pub mod non_finite {
+    use motor_math::FloatType;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Repr {
+        Float(FloatType),
+        String(String),
+    }
+
+    pub fn serialize<S: Serializer>(value: &FloatType, serializer: S) -> Result<S::Ok, S::Error> {
+        if value.is_finite() {
+            serializer.serialize_f64(*value)
+        } else {
+            serializer.collect_str(value)
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<FloatType, D::Error> {
+        match Repr::deserialize(deserializer)? {
+            Repr::Float(value) => Ok(value),
+            Repr::String(value) => value.parse().map_err(D::Error::custom),
+        }
+    }
+
+    /// Like the parent module but for a `(FloatType, T)` tuple
+    pub mod pair {
+        use motor_math::FloatType;
+        use serde::{ser::SerializeTuple, Deserialize, Deserializer, Serialize, Serializer};
+
+        #[derive(Deserialize)]
+        struct Pair<T>(#[serde(with = "super")] FloatType, T);
+
+        pub fn serialize<S: Serializer, T: Serialize>(
+            value: &(FloatType, T),
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            struct Float<'a>(&'a FloatType);
+
+            impl Serialize for Float<'_> {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    super::serialize(self.0, serializer)
+                }
+            }
+
+            let mut tuple = serializer.serialize_tuple(2)?;
+            tuple.serialize_element(&Float(&value.0))?;
+            tuple.serialize_element(&value.1)?;
+            tuple.end()
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: Deserialize<'de>>(
+            deserializer: D,
+        ) -> Result<(FloatType, T), D::Error> {
+            let Pair(float, other) = Pair::deserialize(deserializer)?;
+            Ok((float, other))
+        }
+    }
+}
+
+/// Serde helpers that store a vector as a plain list, toml can not represent nalgebra's
+/// serialization of the unit column dimension
+pub mod dvector {
+    use motor_math::FloatType;
+    use nalgebra::DVector;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &DVector<FloatType>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(value.iter())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<DVector<FloatType>, D::Error> {
+        Vec::deserialize(deserializer).map(DVector::from_vec)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use motor_math::FloatType;
+    use nalgebra::{dvector, DVector};
+    use serde::{Deserialize, Serialize};
+
+    use super::{load, save, test_directory, FileFormat};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Saved {
+        name: String,
+        #[serde(with = "super::non_finite")]
+        best: FloatType,
+        #[serde(with = "super::non_finite::pair")]
+        worst: (FloatType, u64),
+        #[serde(with = "super::dvector")]
+        point: DVector<FloatType>,
+    }
+
+    #[test]
+    fn saved_files_load_back() {
+        let directory = test_directory("format-round-trip");
+        let saved = Saved {
+            name: "run".to_owned(),
+            best: 1.5,
+            worst: (FloatType::NEG_INFINITY, 7),
+            point: dvector![0.25, -1.0, 3.0],
+        };
+
+        for name in ["saved.toml", "saved.json"] {
+            let path = directory.join(name);
+            save(&path, &saved).unwrap();
+            assert_eq!(load::<Saved>(&path).unwrap(), saved, "{name}");
+        }
+    }
+
+    #[test]
+    fn unknown_extensions_are_rejected() {
+        let directory = test_directory("format-extension");
+        let path = directory.join("saved.yaml");
+
+        assert!(FileFormat::from_path(&path).is_err());
+        assert!(save(&path, &1.0).is_err());
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,124 @@
+use std::path::Path;
+
+use motor_math::{FloatType, Number};
+use nalgebra::{vector, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::{format::FilePaths, hull::Hull, mass::MassModel};
+
+/// The parts of the vehicle the thrusters are placed around
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct VehicleGeometry {
+    pub tube: Tube,
+    /// A mesh of the hull or frame, thrusters are scored on how close they and their jets come
+    /// to its surfaces
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hull: Option<Hull>,
+    /// Masses and volumes of the vehicle, without one the vehicle rotates about the origin and
+    /// its center of mass is the mean thruster position
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mass: Option<MassModel>,
+    /// Reaction torque of a propeller per unit of thrust, in meters, 0 ignores it
+    pub prop_torque_ratio: FloatType,
+}
+
+impl FilePaths for VehicleGeometry {
+    fn load_paths(&mut self, directory: &Path) -> anyhow::Result<()> {
+        if let Some(hull) = &mut self.hull {
+            *hull = hull.reload(directory)?;
+        }
+
+        Ok(())
+    }
+
+    fn relative_paths(&mut self, directory: &Path) {
+        if let Some(hull) = &mut self.hull {
+            *hull = hull.relative_to(directory);
+        }
+    }
+}
+
+impl Default for VehicleGeometry {
+    fn default() -> Self {
+        Self {
+            tube: Tube::default(),
+            hull: None,
+            mass: None,
+            prop_torque_ratio: 0.01,
+        }
+    }
+}
+
+/// The electronics tube, a cylinder relative to the center of the vehicle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tube {
+    /// Length in meters between the end caps
+    pub length: FloatType,
+    /// Radius in meters
+    pub radius: FloatType,
+    /// Direction the tube runs in, does not need to be normalized
+    pub axis: [FloatType; 3],
+    /// Position of the tube's center relative to the center of the vehicle
+    pub offset: [FloatType; 3],
+}
+
+impl Default for Tube {
+    fn default() -> Self {
+        Self {
+            length: 0.34,
+            radius: 0.08,
+            axis: [0.0, 1.0, 0.0],
+            offset: [0.0, 0.0, 0.0],
+        }
+    }
+}
+
+impl Tube {
+    /// The unit vector the tube runs along, the Y axis if `axis` is zero
+    pub fn axis(&self) -> Vector3<FloatType> {
+        Vector3::from(self.axis)
+            .try_normalize(1e-9)
+            .unwrap_or_else(Vector3::y)
+    }
+
+    pub fn offset(&self) -> Vector3<FloatType> {
+        Vector3::from(self.offset)
+    }
+
+    /// The corners of the smallest axis aligned box containing the tube
+    pub fn bounds(&self) -> (Vector3<FloatType>, Vector3<FloatType>) {
+        let axis = self.axis();
+        let half_extent = vector![
+            self.extent_along(axis.x),
+            self.extent_along(axis.y),
+            self.extent_along(axis.z)
+        ];
+
+        (self.offset() - half_extent, self.offset() + half_extent)
+    }
+
+    /// Half the extent of the tube along a world axis making `cos` with the tube's axis
+    fn extent_along(&self, cos: FloatType) -> FloatType {
+        cos.abs() * self.length / 2.0 + self.radius * (1.0 - cos * cos).max(0.0).sqrt()
+    }
+
+    /// How far `position` is inside the tube grown by `margin` on every side, the distance to
+    /// the closest surface of the grown tube, or `None` if it is outside
+    pub fn penetration<D: Number>(&self, position: Vector3<D>, margin: FloatType) -> Option<D> {
+        let axis = self.axis().map(D::from);
+        let relative = position - self.offset().map(D::from);
+
+        let along = relative.dot(&axis);
+        let radial = (relative - axis * along).norm();
+
+        let radial_depth = D::from(self.radius + margin) - radial;
+        let axial_depth = D::from(self.length / 2.0 + margin) - along.abs();
+        if radial_depth.re() <= 0.0 || axial_depth.re() <= 0.0 {
+            return None;
+        }
+
+        Some(radial_depth.min(axial_depth))
+    }
+}
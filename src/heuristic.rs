@@ -1,17 +1,25 @@
 use motor_math::{solve::reverse::Axis, FloatType, MotorConfig, Number};
 use nalgebra::{vector, SVector};
+use serde::{Deserialize, Serialize};
 use stable_hashmap::StableHashMap;
 use std::fmt::Debug;
 use std::hash::Hash;
 use std::marker::PhantomData;
+use std::path::Path;
 
-#[derive(Clone, Copy)]
+use crate::{format::FilePaths, geometry::VehicleGeometry, power::PowerBudget};
+
+pub mod presets;
+pub mod settings;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum MesType {
     Equal,
     AtLeast,
 }
 
-#[derive(Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ScoreSettings {
     pub mes_linear: FloatType,
     pub mes_linear_type: MesType,
@@ -39,18 +47,50 @@ pub struct ScoreSettings {
     pub y_rot: FloatType,
     pub z_rot: FloatType,
 
+    /// Keeps the mean thruster position at the origin, skipped with a mass model
     pub center_of_mass_loss: FloatType,
     pub center_loss: FloatType,
     pub surface_area_score: FloatType,
     pub dimension_loss: FloatType,
     pub cardinality_loss: FloatType,
 
-    pub tube_exclusion_radius: FloatType,
     pub tube_exclusion_loss: FloatType,
 
     pub thruster_exclusion_radius: FloatType,
     pub thruster_exclusion_loss: FloatType,
     pub thruster_flow_exclusion_loss: FloatType,
+
+    /// Cost of each thruster, lets configs with different thruster counts be compared
+    pub thruster_count_loss: FloatType,
+
+    /// Applied to how far the center of buoyancy is from being `min_metacentric_height` above
+    /// the center of mass, only used with a mass model
+    pub metacentric_loss: FloatType,
+    /// Height in meters the center of buoyancy should be above the center of mass
+    pub min_metacentric_height: FloatType,
+
+    /// Applied to how far a thruster's body reaches into the hull, thrusters inside a closed
+    /// hull are always penalised however far they are from its surface
+    pub hull_collision_loss: FloatType,
+    /// Applied to how close the hull is in front of and behind each thruster
+    pub hull_jet_loss: FloatType,
+    /// Distance in meters along a thruster's axis the hull interferes with its jet
+    pub hull_jet_distance: FloatType,
+
+    /// The tube and hull the thrusters are kept out of
+    pub geometry: VehicleGeometry,
+    /// Limits the current drawn for every thrust maximum
+    pub power: PowerBudget,
+}
+
+impl FilePaths for ScoreSettings {
+    fn load_paths(&mut self, directory: &Path) -> anyhow::Result<()> {
+        self.geometry.load_paths(directory)
+    }
+
+    fn relative_paths(&mut self, directory: &Path) {
+        self.geometry.relative_paths(directory);
+    }
 }
 
 impl Default for ScoreSettings {
@@ -80,12 +120,19 @@ impl Default for ScoreSettings {
             center_loss: 0.0,
             surface_area_score: 0.0,
             dimension_loss: -1000.0,
-            tube_exclusion_radius: 0.08,
             thruster_exclusion_radius: 0.08,
             tube_exclusion_loss: -500.0,
             thruster_exclusion_loss: -500.0,
             thruster_flow_exclusion_loss: -10.0,
             cardinality_loss: 0.0,
+            thruster_count_loss: 0.0,
+            metacentric_loss: -1000.0,
+            min_metacentric_height: 0.02,
+            hull_collision_loss: -500.0,
+            hull_jet_loss: -10.0,
+            hull_jet_distance: 0.3,
+            geometry: VehicleGeometry::default(),
+            power: PowerBudget::default(),
         }
     }
 }
@@ -95,7 +142,11 @@ pub enum Scaled {}
 #[derive(Debug, Clone)]
 pub enum Unscaled {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(bound(
+    serialize = "D: Serialize",
+    deserialize = "D: Deserialize<'de> + Default"
+))]
 pub struct ScoreResult<D, Type> {
     pub mes_linear: D,
     pub mes_torque: D,
@@ -122,7 +173,16 @@ pub struct ScoreResult<D, Type> {
     pub thruster_exclusion_loss: D,
     pub thruster_flow_exclusion_loss: D,
     pub cardinality_loss: D,
-
+    #[serde(default)]
+    pub thruster_count_loss: D,
+    #[serde(default)]
+    pub metacentric_loss: D,
+    #[serde(default)]
+    pub hull_collision_loss: D,
+    #[serde(default)]
+    pub hull_jet_loss: D,
+
+    #[serde(skip)]
     phantom: PhantomData<Type>,
 }
 
@@ -151,6 +211,10 @@ impl<D: Number> ScoreResult<D, Unscaled> {
             thruster_flow_exclusion_loss: D::from(settings.thruster_flow_exclusion_loss)
                 * self.thruster_flow_exclusion_loss,
             cardinality_loss: D::from(settings.cardinality_loss) * self.cardinality_loss,
+            thruster_count_loss: D::from(settings.thruster_count_loss) * self.thruster_count_loss,
+            metacentric_loss: D::from(settings.metacentric_loss) * self.metacentric_loss,
+            hull_collision_loss: D::from(settings.hull_collision_loss) * self.hull_collision_loss,
+            hull_jet_loss: D::from(settings.hull_jet_loss) * self.hull_jet_loss,
             phantom: PhantomData,
         }
     }
@@ -182,6 +246,10 @@ impl<D: Number> ScoreResult<D, Scaled> {
             + self.thruster_exclusion_loss
             + self.thruster_flow_exclusion_loss
             + self.cardinality_loss
+            + self.thruster_count_loss
+            + self.metacentric_loss
+            + self.hull_collision_loss
+            + self.hull_jet_loss
     }
 }
 
@@ -208,6 +276,10 @@ impl<D: Number, Type> ScoreResult<D, Type> {
             thruster_exclusion_loss: self.thruster_exclusion_loss.re(),
             thruster_flow_exclusion_loss: self.thruster_flow_exclusion_loss.re(),
             cardinality_loss: self.cardinality_loss.re(),
+            thruster_count_loss: self.thruster_count_loss.re(),
+            metacentric_loss: self.metacentric_loss.re(),
+            hull_collision_loss: self.hull_collision_loss.re(),
+            hull_jet_loss: self.hull_jet_loss.re(),
             phantom: PhantomData,
         }
     }
@@ -236,6 +308,10 @@ impl<D: Number + Default, Type> Default for ScoreResult<D, Type> {
             thruster_exclusion_loss: Default::default(),
             thruster_flow_exclusion_loss: Default::default(),
             cardinality_loss: Default::default(),
+            thruster_count_loss: Default::default(),
+            metacentric_loss: Default::default(),
+            hull_collision_loss: Default::default(),
+            hull_jet_loss: Default::default(),
             phantom: Default::default(),
         }
     }
@@ -304,20 +380,17 @@ pub fn score<MotorId: Debug + Ord + Hash + Clone, D: Number>(
     let thruster_count = motor_config.motors().count();
     let thruster_count = D::from(thruster_count as FloatType);
 
+    let tube = &settings.geometry.tube;
+    let (tube_min, tube_max) = tube.bounds();
+
     let mut position_sum = SVector::<D, 3>::zeros();
-    let mut min: SVector<D, 3> = vector![
-        D::from(-settings.tube_exclusion_radius),
-        D::from(-0.17),
-        D::from(-settings.tube_exclusion_radius)
-    ];
-    let mut max: SVector<D, 3> = vector![
-        D::from(settings.tube_exclusion_radius),
-        D::from(0.17),
-        D::from(settings.tube_exclusion_radius)
-    ];
+    let mut min: SVector<D, 3> = tube_min.map(D::from);
+    let mut max: SVector<D, 3> = tube_max.map(D::from);
     let mut tube_exclusion_loss = D::zero();
     let mut thruster_exclusion_loss = D::zero();
     let mut thruster_flow_exclusion_loss = D::zero();
+    let mut hull_collision_loss = D::zero();
+    let mut hull_jet_loss = D::zero();
 
     let mut average_direction = SVector::<D, 3>::zeros();
 
@@ -361,21 +434,53 @@ pub fn score<MotorId: Debug + Ord + Hash + Clone, D: Number>(
                     / D::from(2.0);
         }
 
-        let pos_2d = motor.position.xz();
-        let space_between = pos_2d.norm()
-            - D::from(settings.tube_exclusion_radius + settings.thruster_exclusion_radius);
-        if space_between < D::zero() {
-            tube_exclusion_loss += space_between * space_between;
+        if let Some(depth) = tube.penetration(motor.position, settings.thruster_exclusion_radius) {
+            tube_exclusion_loss += depth * depth;
+        }
+
+        if let Some(hull) = &settings.geometry.hull {
+            // The distance is negative inside the hull, so the depth is at least the radius
+            let depth =
+                D::from(settings.thruster_exclusion_radius) - hull.surface_distance(motor.position);
+            if depth.re() > 0.0 {
+                hull_collision_loss += depth * depth;
+            }
+
+            // Thrusters run in both directions, so check the hull on either side
+            let jet_distance = settings.hull_jet_distance;
+            if jet_distance > 0.0 {
+                let axis = motor.orientation.normalize();
+                for direction in [axis, -axis] {
+                    if let Some(distance) = hull.ray_hit(motor.position, direction, jet_distance) {
+                        let blocked = D::one() - distance / D::from(jet_distance);
+                        hull_jet_loss += blocked * blocked;
+                    }
+                }
+            }
         }
     }
 
     let center = (max + min) / D::from(2.0);
     let half_extent = (max - min) / D::from(2.0);
 
-    // let center_of_mass = position_sum.dot(&position_sum).powi(4);
-    let center_of_mass = position_sum
-        .dot(&position_sum)
-        .max(position_sum.norm() * 10.0);
+    // Torques are about the real center of mass when there is one, so it doesn't need to be at
+    // the origin and only the metacentric loss applies
+    let (center_of_mass, metacentric_loss) = match &settings.geometry.mass {
+        Some(mass_model) => {
+            let properties = mass_model.properties(motor_config);
+            let (height, offset) = properties.metacentric_height(mass_model.up());
+            let shortfall = (height - D::from(settings.min_metacentric_height)).min(D::zero());
+
+            (D::zero(), shortfall * shortfall + offset.norm_squared())
+        }
+        None => {
+            // let center_of_mass = position_sum.dot(&position_sum).powi(4);
+            let center_of_mass = position_sum
+                .dot(&position_sum)
+                .max(position_sum.norm() * 10.0);
+            (center_of_mass, D::zero())
+        }
+    };
     let center = center.dot(&center);
     // let surface_area = D::from(8.0)
     //     * (half_extent.x * (half_extent.y + half_extent.z) + half_extent.y * half_extent.z);
@@ -415,6 +520,10 @@ pub fn score<MotorId: Debug + Ord + Hash + Clone, D: Number>(
         thruster_exclusion_loss,
         thruster_flow_exclusion_loss,
         cardinality_loss,
+        thruster_count_loss: thruster_count,
+        metacentric_loss,
+        hull_collision_loss,
+        hull_jet_loss,
         phantom: Default::default(),
     };
 
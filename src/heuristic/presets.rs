@@ -0,0 +1,89 @@
+use std::path::Path;
+
+use crate::format::{self, FileFormat, FilePaths};
+
+use super::settings::ToggleableScoreSettings;
+
+/// Heuristic presets that are shipped with the program
+pub const BUILTIN_PRESETS: &[(&str, &str)] = &[
+    (
+        "Station Keeping",
+        include_str!("../../presets/station_keeping.toml"),
+    ),
+    (
+        "Fast Survey",
+        include_str!("../../presets/fast_survey.toml"),
+    ),
+    (
+        "Manipulator Work",
+        include_str!("../../presets/manipulator_work.toml"),
+    ),
+];
+
+pub fn builtin_presets() -> impl Iterator<Item = (&'static str, ToggleableScoreSettings)> {
+    BUILTIN_PRESETS.iter().map(|(name, contents)| {
+        let settings = parse_preset(contents, FileFormat::Toml).expect("Parse builtin preset");
+        (*name, settings)
+    })
+}
+
+/// Parses a preset that isn't from a file, paths in it are relative to the working directory
+pub fn parse_preset(contents: &str, format: FileFormat) -> anyhow::Result<ToggleableScoreSettings> {
+    let mut settings: ToggleableScoreSettings = format.parse(contents)?;
+    settings.load_paths(Path::new(""))?;
+
+    Ok(settings.upgraded())
+}
+
+pub fn load_preset(path: impl AsRef<Path>) -> anyhow::Result<ToggleableScoreSettings> {
+    format::load_with_paths(path).map(ToggleableScoreSettings::upgraded)
+}
+
+pub fn save_preset(
+    path: impl AsRef<Path>,
+    settings: &ToggleableScoreSettings,
+) -> anyhow::Result<()> {
+    format::save_with_paths(path, settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{builtin_presets, load_preset, parse_preset, save_preset};
+    use crate::format::{test_directory, FileFormat};
+
+    #[test]
+    fn builtin_presets_round_trip() {
+        let directory = test_directory("presets-round-trip");
+
+        for (name, settings) in builtin_presets() {
+            let expected = FileFormat::Toml.write(&settings).unwrap();
+
+            for extension in ["toml", "json"] {
+                let path = directory.join(format!("{name}.{extension}"));
+                save_preset(&path, &settings).unwrap();
+                let loaded = load_preset(&path).unwrap();
+
+                assert_eq!(
+                    FileFormat::Toml.write(&loaded).unwrap(),
+                    expected,
+                    "{path:?}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn legacy_tube_radius_sets_the_tube() {
+        let settings =
+            parse_preset("tube_exclusion_radius = [true, 0.12]", FileFormat::Toml).unwrap();
+        assert_eq!(settings.geometry.tube.radius, 0.12);
+
+        let settings =
+            parse_preset("tube_exclusion_radius = [false, 0.12]", FileFormat::Toml).unwrap();
+        assert_eq!(settings.geometry.tube.radius, 0.0);
+
+        let settings = parse_preset("", FileFormat::Toml).unwrap();
+        let saved = toml::to_string(&settings).unwrap();
+        assert!(!saved.contains("tube_exclusion_radius"), "{saved}");
+    }
+}
@@ -1,7 +1,14 @@
+use std::path::Path;
+
 use motor_math::FloatType;
-use thruster_sim::heuristic::{MesType, ScoreSettings};
+use serde::{Deserialize, Serialize};
+
+use super::{MesType, ScoreSettings};
+use crate::{format::FilePaths, geometry::VehicleGeometry, power::PowerBudget};
 
-#[derive(Clone)]
+/// `ScoreSettings` where each term can be individually switched off
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ToggleableScoreSettings {
     pub mes_linear: (bool, FloatType),
     pub mes_linear_type: MesType,
@@ -33,15 +40,36 @@ pub struct ToggleableScoreSettings {
     pub center_loss: (bool, FloatType),
     pub surface_area_loss: (bool, FloatType),
     pub dimension_loss: (bool, FloatType),
-    pub tube_exclusion_radius: (bool, FloatType),
     pub tube_exclusion_loss: (bool, FloatType),
     pub thruster_exclusion_radius: (bool, FloatType),
     pub thruster_exclusion_loss: (bool, FloatType),
     pub cardinality_loss: (bool, FloatType),
     pub thruster_flow_exclusion_loss: (bool, FloatType),
+    pub thruster_count_loss: (bool, FloatType),
+    pub metacentric_loss: (bool, FloatType),
+    pub min_metacentric_height: (bool, FloatType),
+    pub hull_collision_loss: (bool, FloatType),
+    pub hull_jet_loss: (bool, FloatType),
+    pub hull_jet_distance: (bool, FloatType),
+
+    pub geometry: VehicleGeometry,
+    pub power: PowerBudget,
+
+    /// The radius of the tube before it moved to `geometry`, still read from older presets
+    #[serde(rename = "tube_exclusion_radius", skip_serializing)]
+    legacy_tube_radius: Option<(bool, FloatType)>,
 }
 
 impl ToggleableScoreSettings {
+    /// Moves settings of older presets to where they are now
+    pub(super) fn upgraded(mut self) -> Self {
+        if let Some((enabled, radius)) = self.legacy_tube_radius.take() {
+            self.geometry.tube.radius = if enabled { radius } else { 0.0 };
+        }
+
+        self
+    }
+
     pub fn flatten(&self) -> ScoreSettings {
         ScoreSettings {
             mes_linear: if self.mes_linear.0 {
@@ -133,11 +161,6 @@ impl ToggleableScoreSettings {
             } else {
                 0.0
             },
-            tube_exclusion_radius: if self.tube_exclusion_radius.0 {
-                self.tube_exclusion_radius.1
-            } else {
-                0.0
-            },
             tube_exclusion_loss: if self.tube_exclusion_loss.0 {
                 self.tube_exclusion_loss.1
             } else {
@@ -163,10 +186,52 @@ impl ToggleableScoreSettings {
             } else {
                 0.0
             },
+            thruster_count_loss: if self.thruster_count_loss.0 {
+                self.thruster_count_loss.1
+            } else {
+                0.0
+            },
+            metacentric_loss: if self.metacentric_loss.0 {
+                self.metacentric_loss.1
+            } else {
+                0.0
+            },
+            min_metacentric_height: if self.min_metacentric_height.0 {
+                self.min_metacentric_height.1
+            } else {
+                0.0
+            },
+            hull_collision_loss: if self.hull_collision_loss.0 {
+                self.hull_collision_loss.1
+            } else {
+                0.0
+            },
+            hull_jet_loss: if self.hull_jet_loss.0 {
+                self.hull_jet_loss.1
+            } else {
+                0.0
+            },
+            hull_jet_distance: if self.hull_jet_distance.0 {
+                self.hull_jet_distance.1
+            } else {
+                0.0
+            },
+            geometry: self.geometry.clone(),
+            power: self.power.clone(),
         }
     }
 }
 
+impl FilePaths for ToggleableScoreSettings {
+    fn load_paths(&mut self, directory: &Path) -> anyhow::Result<()> {
+        self.geometry.load_paths(directory)
+    }
+
+    fn relative_paths(&mut self, directory: &Path) {
+        self.geometry.relative_paths(directory);
+    }
+}
+
 impl Default for ToggleableScoreSettings {
     fn default() -> Self {
         let base = ScoreSettings::default();
@@ -196,12 +261,20 @@ impl Default for ToggleableScoreSettings {
             center_loss: (true, base.center_loss),
             surface_area_loss: (true, base.surface_area_score),
             dimension_loss: (true, base.dimension_loss),
-            tube_exclusion_radius: (true, base.tube_exclusion_radius),
             tube_exclusion_loss: (true, base.tube_exclusion_loss),
             thruster_exclusion_radius: (true, base.thruster_exclusion_radius),
             thruster_exclusion_loss: (true, base.thruster_exclusion_loss),
             cardinality_loss: (true, base.cardinality_loss),
             thruster_flow_exclusion_loss: (true, base.thruster_flow_exclusion_loss),
+            thruster_count_loss: (true, base.thruster_count_loss),
+            metacentric_loss: (true, base.metacentric_loss),
+            min_metacentric_height: (true, base.min_metacentric_height),
+            hull_collision_loss: (true, base.hull_collision_loss),
+            hull_jet_loss: (true, base.hull_jet_loss),
+            hull_jet_distance: (true, base.hull_jet_distance),
+            geometry: base.geometry,
+            power: base.power,
+            legacy_tube_radius: None,
         }
     }
 }
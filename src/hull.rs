@@ -0,0 +1,731 @@
+use std::{
+    fmt, fs,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use anyhow::{bail, ensure, Context};
+use itertools::Itertools;
+use motor_math::{FloatType, Number};
+use nalgebra::{vector, Vector3};
+use serde::{Deserialize, Serialize};
+
+/// Most triangles in a leaf of the bounding volume hierarchy
+const LEAF_SIZE: usize = 4;
+
+/// A triangle mesh of the vehicle's hull or frame, thrusters are kept clear of its surfaces
+///
+/// Only the source is serialized, a deserialized hull has no mesh until it is loaded by the
+/// `FilePaths` of the file it is in
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(from = "HullSource", into = "HullSource")]
+pub struct Hull {
+    source: HullSource,
+    /// In the order of the leaves of `bvh`
+    triangles: Arc<[Triangle]>,
+    bvh: Arc<[BvhNode]>,
+}
+
+/// Where a hull is loaded from and how it is placed on the vehicle
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HullSource {
+    /// An STL (ascii or binary) or OBJ file, relative to the file the source is saved in.
+    /// Absolute once loaded
+    pub path: PathBuf,
+    /// Converts the mesh's units to meters, 0.001 for a mesh modelled in millimeters
+    #[serde(default = "HullSource::default_scale")]
+    pub scale: FloatType,
+    /// Position of the mesh's origin relative to the center of the vehicle, after scaling
+    #[serde(default)]
+    pub offset: [FloatType; 3],
+}
+
+impl HullSource {
+    /// A mesh in meters centered on the vehicle
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        Self {
+            path: path.as_ref().to_owned(),
+            scale: Self::default_scale(),
+            offset: [0.0; 3],
+        }
+    }
+
+    fn default_scale() -> FloatType {
+        1.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Triangle {
+    pub vertices: [Vector3<FloatType>; 3],
+}
+
+/// A node of the hull's bounding volume hierarchy, the root is the first node
+#[derive(Debug, Clone, Copy)]
+struct BvhNode {
+    bounds: Aabb,
+    contents: BvhContents,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BvhContents {
+    /// A range of the hull's triangles
+    Leaf { start: usize, end: usize },
+    /// Indices of the two child nodes
+    Branch { left: usize, right: usize },
+}
+
+/// An axis aligned bounding box
+#[derive(Debug, Clone, Copy)]
+struct Aabb {
+    min: Vector3<FloatType>,
+    max: Vector3<FloatType>,
+}
+
+impl Hull {
+    /// Loads the mesh of `source`, its path is relative to `directory`
+    pub fn load(mut source: HullSource, directory: &Path) -> anyhow::Result<Self> {
+        let path = directory.join(&source.path);
+        source.path = std::path::absolute(&path).unwrap_or(path);
+        let path = &source.path;
+        let contents = fs::read(path).with_context(|| format!("Read {}", path.display()))?;
+
+        let extension = path
+            .extension()
+            .and_then(|it| it.to_str())
+            .map(str::to_ascii_lowercase);
+        let vertices = match extension.as_deref() {
+            Some("stl") => parse_stl(&contents),
+            Some("obj") => parse_obj(&contents),
+            _ => bail!(
+                "Unknown mesh format for {}, expected a .stl or .obj file",
+                path.display()
+            ),
+        }
+        .with_context(|| format!("Load {}", path.display()))?;
+
+        let offset = Vector3::from(source.offset);
+        let mut triangles = vertices
+            .into_iter()
+            .map(|vertices| Triangle {
+                vertices: vertices.map(|it| it * source.scale + offset),
+            })
+            .filter(|it| !it.is_degenerate())
+            .collect_vec();
+        ensure!(!triangles.is_empty(), "{} has no triangles", path.display());
+
+        let mut bvh = Vec::new();
+        build_bvh(&mut triangles, 0, &mut bvh);
+
+        Ok(Self {
+            source,
+            triangles: triangles.into(),
+            bvh: bvh.into(),
+        })
+    }
+
+    pub fn source(&self) -> &HullSource {
+        &self.source
+    }
+
+    pub fn triangles(&self) -> &[Triangle] {
+        &self.triangles
+    }
+
+    /// Loads the mesh again, if the hull was deserialized its path is relative to `directory`
+    pub fn reload(&self, directory: &Path) -> anyhow::Result<Self> {
+        Self::load(self.source.clone(), directory)
+    }
+
+    /// The hull with its path relative to `directory` if it is inside it
+    pub fn relative_to(&self, directory: &Path) -> Self {
+        let path = self.source.path.strip_prefix(directory);
+        Self {
+            source: HullSource {
+                path: path.unwrap_or(&self.source.path).to_owned(),
+                ..self.source.clone()
+            },
+            ..self.clone()
+        }
+    }
+
+    /// Distance from `position` to the closest point on the hull's surface, negative when
+    /// `position` is inside the hull
+    pub fn surface_distance<D: Number>(&self, position: Vector3<D>) -> D {
+        let float_position = position.map(|it| it.re());
+
+        // Find the closest triangle with floats, skipping any node further away than the
+        // closest triangle so far, and only repeat it with `D`
+        let first = self.triangles.first().expect("Hull mesh is loaded");
+        let mut closest = (first, FloatType::INFINITY);
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let BvhNode { bounds, contents } = self.bvh[node];
+            if bounds.distance_squared(float_position) >= closest.1 {
+                continue;
+            }
+
+            match contents {
+                BvhContents::Leaf { start, end } => {
+                    for triangle in &self.triangles[start..end] {
+                        let distance = (triangle.closest_point(float_position) - float_position)
+                            .norm_squared();
+                        if distance < closest.1 {
+                            closest = (triangle, distance);
+                        }
+                    }
+                }
+                BvhContents::Branch { left, right } => {
+                    // Visit the closer child first so the other can more often be skipped
+                    let distance =
+                        |node: usize| self.bvh[node].bounds.distance_squared(float_position);
+                    if distance(left) < distance(right) {
+                        stack.extend([right, left]);
+                    } else {
+                        stack.extend([left, right]);
+                    }
+                }
+            }
+        }
+
+        let distance = (position - closest.0.closest_point(position)).norm();
+        if self.contains(float_position) {
+            -distance
+        } else {
+            distance
+        }
+    }
+
+    /// If `position` is enclosed by the hull, from the parity of the surfaces a ray from it
+    /// crosses. Only meaningful for closed meshes
+    pub fn contains(&self, position: Vector3<FloatType>) -> bool {
+        // Not along an axis, so rays are unlikely to graze the edges of modelled meshes
+        let direction = vector![0.6, 0.48, 0.64];
+
+        let mut origin = position;
+        let mut crossings = 0;
+        while let Some(distance) = self.ray_hit(origin, direction, FloatType::INFINITY) {
+            crossings += 1;
+            origin += direction * (distance + 1e-9);
+        }
+
+        crossings % 2 == 1
+    }
+
+    /// Distance along the unit `direction` from `origin` to the first surface of the hull, or
+    /// `None` if no surface is hit within `max_distance`
+    pub fn ray_hit<D: Number>(
+        &self,
+        origin: Vector3<D>,
+        direction: Vector3<D>,
+        max_distance: FloatType,
+    ) -> Option<D> {
+        let float_origin = origin.map(|it| it.re());
+        let float_direction = direction.map(|it| it.re());
+
+        // Find the hit with floats and only repeat the closest one with `D`
+        let (mut closest, mut limit) = (None, max_distance);
+        let mut stack = vec![0];
+        while let Some(node) = stack.pop() {
+            let BvhNode { bounds, contents } = self.bvh[node];
+            if !bounds.hit_by(float_origin, float_direction, limit) {
+                continue;
+            }
+
+            match contents {
+                BvhContents::Leaf { start, end } => {
+                    for triangle in &self.triangles[start..end] {
+                        let Some(distance) = triangle.intersect(float_origin, float_direction)
+                        else {
+                            continue;
+                        };
+                        if distance > 0.0 && distance <= limit {
+                            (closest, limit) = (Some(triangle), distance);
+                        }
+                    }
+                }
+                BvhContents::Branch { left, right } => stack.extend([left, right]),
+            }
+        }
+
+        closest?.intersect(origin, direction)
+    }
+}
+
+impl Triangle {
+    fn is_degenerate(&self) -> bool {
+        let [a, b, c] = self.vertices;
+        (b - a).cross(&(c - a)).norm() < 1e-12
+    }
+
+    /// The point on the triangle closest to `position`, from Real-Time Collision Detection 5.1.5
+    pub fn closest_point<D: Number>(&self, position: Vector3<D>) -> Vector3<D> {
+        let [a, b, c] = self.vertices.map(|it| it.map(D::from));
+        let ab = b - a;
+        let ac = c - a;
+
+        let ap = position - a;
+        let d1 = ab.dot(&ap);
+        let d2 = ac.dot(&ap);
+        if d1.re() <= 0.0 && d2.re() <= 0.0 {
+            return a;
+        }
+
+        let bp = position - b;
+        let d3 = ab.dot(&bp);
+        let d4 = ac.dot(&bp);
+        if d3.re() >= 0.0 && d4.re() <= d3.re() {
+            return b;
+        }
+
+        let vc = d1 * d4 - d3 * d2;
+        if vc.re() <= 0.0 && d1.re() >= 0.0 && d3.re() <= 0.0 {
+            return a + ab * (d1 / (d1 - d3));
+        }
+
+        let cp = position - c;
+        let d5 = ab.dot(&cp);
+        let d6 = ac.dot(&cp);
+        if d6.re() >= 0.0 && d5.re() <= d6.re() {
+            return c;
+        }
+
+        let vb = d5 * d2 - d1 * d6;
+        if vb.re() <= 0.0 && d2.re() >= 0.0 && d6.re() <= 0.0 {
+            return a + ac * (d2 / (d2 - d6));
+        }
+
+        let va = d3 * d6 - d5 * d4;
+        if va.re() <= 0.0 && (d4 - d3).re() >= 0.0 && (d5 - d6).re() >= 0.0 {
+            return b + (c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+        }
+
+        let denominator = va + vb + vc;
+        a + ab * (vb / denominator) + ac * (vc / denominator)
+    }
+
+    /// Distance along the unit `direction` from `origin` to the triangle's plane if the ray
+    /// passes through the triangle, negative when it is behind the origin
+    pub fn intersect<D: Number>(&self, origin: Vector3<D>, direction: Vector3<D>) -> Option<D> {
+        let [a, b, c] = self.vertices.map(|it| it.map(D::from));
+        let edge_1 = b - a;
+        let edge_2 = c - a;
+
+        let h = direction.cross(&edge_2);
+        let determinant = edge_1.dot(&h);
+        if determinant.re().abs() < 1e-12 {
+            return None;
+        }
+
+        let s = origin - a;
+        let u = s.dot(&h) / determinant;
+        if u.re() < 0.0 || u.re() > 1.0 {
+            return None;
+        }
+
+        let q = s.cross(&edge_1);
+        let v = direction.dot(&q) / determinant;
+        if v.re() < 0.0 || (u + v).re() > 1.0 {
+            return None;
+        }
+
+        Some(edge_2.dot(&q) / determinant)
+    }
+}
+
+impl Aabb {
+    fn of(triangles: &[Triangle]) -> Self {
+        let mut vertices = triangles.iter().flat_map(|it| it.vertices);
+        let first = vertices.next().expect("Bounds of at least one triangle");
+
+        vertices.fold(
+            Self {
+                min: first,
+                max: first,
+            },
+            |bounds, vertex| Self {
+                min: bounds.min.inf(&vertex),
+                max: bounds.max.sup(&vertex),
+            },
+        )
+    }
+
+    /// Squared distance from `position` to the box, 0 inside it
+    fn distance_squared(&self, position: Vector3<FloatType>) -> FloatType {
+        let outside = (self.min - position)
+            .sup(&(position - self.max))
+            .sup(&Vector3::zeros());
+        outside.norm_squared()
+    }
+
+    /// If the ray along the unit `direction` from `origin` passes through the box within
+    /// `max_distance`, from the slab test
+    fn hit_by(
+        &self,
+        origin: Vector3<FloatType>,
+        direction: Vector3<FloatType>,
+        max_distance: FloatType,
+    ) -> bool {
+        let (mut near, mut far) = (0.0, max_distance);
+        for axis in 0..3 {
+            // Division by zero gives infinities, which clip correctly unless the origin is on a
+            // face of the box
+            let inverse = 1.0 / direction[axis];
+            let a = (self.min[axis] - origin[axis]) * inverse;
+            let b = (self.max[axis] - origin[axis]) * inverse;
+            if a.is_nan() || b.is_nan() {
+                continue;
+            }
+
+            near = FloatType::max(near, a.min(b));
+            far = FloatType::min(far, a.max(b));
+            if near > far {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// Builds the nodes for `triangles`, starting at `offset` in the hull's triangles, by splitting
+/// them at the median along the longest axis of their centers. Returns the index of the node
+fn build_bvh(triangles: &mut [Triangle], offset: usize, nodes: &mut Vec<BvhNode>) -> usize {
+    let bounds = Aabb::of(triangles);
+    let idx = nodes.len();
+    nodes.push(BvhNode {
+        bounds,
+        contents: BvhContents::Leaf {
+            start: offset,
+            end: offset + triangles.len(),
+        },
+    });
+    if triangles.len() <= LEAF_SIZE {
+        return idx;
+    }
+
+    let center = |triangle: &Triangle| triangle.vertices.iter().sum::<Vector3<FloatType>>() / 3.0;
+    let axis = (bounds.max - bounds.min).imax();
+    let middle = triangles.len() / 2;
+    triangles.select_nth_unstable_by(middle, |a, b| {
+        FloatType::total_cmp(&center(a)[axis], &center(b)[axis])
+    });
+
+    let (left, right) = triangles.split_at_mut(middle);
+    let left = build_bvh(left, offset, nodes);
+    let right = build_bvh(right, offset + middle, nodes);
+    nodes[idx].contents = BvhContents::Branch { left, right };
+
+    idx
+}
+
+impl From<HullSource> for Hull {
+    fn from(source: HullSource) -> Self {
+        Hull {
+            source,
+            triangles: Arc::new([]),
+            bvh: Arc::new([]),
+        }
+    }
+}
+
+impl From<Hull> for HullSource {
+    fn from(hull: Hull) -> Self {
+        hull.source
+    }
+}
+
+impl PartialEq for Hull {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl fmt::Debug for Hull {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Hull")
+            .field("source", &self.source)
+            .field("triangles", &self.triangles.len())
+            .finish()
+    }
+}
+
+type Vertices = [Vector3<FloatType>; 3];
+
+/// Parses a binary or ascii STL file
+fn parse_stl(contents: &[u8]) -> anyhow::Result<Vec<Vertices>> {
+    // Binary files may also start with "solid", so check the size matches the facet count first
+    if contents.len() >= 84 {
+        let count = u32::from_le_bytes(contents[80..84].try_into().unwrap()) as usize;
+        if contents.len() == 84 + count * 50 {
+            return Ok(contents[84..]
+                .chunks_exact(50)
+                .map(|facet| {
+                    // Skip the normal, the winding is not used
+                    [1, 2, 3].map(|vertex| {
+                        Vector3::from_fn(|idx, _| {
+                            let start = vertex * 12 + idx * 4;
+                            f32::from_le_bytes(facet[start..start + 4].try_into().unwrap()) as _
+                        })
+                    })
+                })
+                .collect());
+        }
+    }
+
+    let contents = std::str::from_utf8(contents).context("Not a binary or ascii STL")?;
+    ensure!(
+        contents.trim_start().starts_with("solid"),
+        "Not a binary or ascii STL"
+    );
+
+    let mut vertices = Vec::new();
+    for (line_idx, line) in contents.lines().enumerate() {
+        let mut tokens = line.split_whitespace();
+        if tokens.next() == Some("vertex") {
+            vertices.push(parse_vector(tokens).with_context(|| format!("Line {}", line_idx + 1))?);
+        }
+    }
+    ensure!(
+        vertices.len() % 3 == 0,
+        "Facets must have exactly 3 vertices"
+    );
+
+    Ok(vertices
+        .into_iter()
+        .tuples()
+        .map(|(a, b, c)| [a, b, c])
+        .collect())
+}
+
+/// Parses the vertices and faces of an OBJ file, faces with more than 3 vertices are split
+/// into a fan of triangles
+fn parse_obj(contents: &[u8]) -> anyhow::Result<Vec<Vertices>> {
+    let contents = std::str::from_utf8(contents).context("OBJ files must be utf-8")?;
+
+    let mut vertices = Vec::new();
+    let mut triangles = Vec::new();
+    for (line_idx, line) in contents.lines().enumerate() {
+        let context = || format!("Line {}", line_idx + 1);
+
+        let mut tokens = line.split_whitespace();
+        match tokens.next() {
+            Some("v") => vertices.push(parse_vector(tokens).with_context(context)?),
+            Some("f") => {
+                let face = tokens
+                    .map(|it| obj_index(it, vertices.len()))
+                    .collect::<anyhow::Result<Vec<_>>>()
+                    .with_context(context)?;
+                ensure!(
+                    face.len() >= 3,
+                    "{}: Face has less than 3 vertices",
+                    context()
+                );
+
+                for (b, c) in face[1..].iter().tuple_windows() {
+                    triangles.push([vertices[face[0]], vertices[*b], vertices[*c]]);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(triangles)
+}
+
+/// Resolves a face's reference to a vertex, which can be relative to the end of the vertices
+/// read so far and may be followed by texture and normal indices
+fn obj_index(token: &str, vertex_count: usize) -> anyhow::Result<usize> {
+    let index: i64 = token
+        .split('/')
+        .next()
+        .unwrap_or_default()
+        .parse()
+        .with_context(|| format!("Invalid vertex reference {token}"))?;
+
+    ensure!(index != 0, "Vertex references start at 1");
+    let resolved = if index > 0 {
+        index - 1
+    } else {
+        vertex_count as i64 + index
+    };
+    ensure!(
+        (0..vertex_count as i64).contains(&resolved),
+        "Vertex {index} does not exist"
+    );
+
+    Ok(resolved as usize)
+}
+
+fn parse_vector<'a>(
+    mut tokens: impl Iterator<Item = &'a str>,
+) -> anyhow::Result<Vector3<FloatType>> {
+    let mut vector = Vector3::zeros();
+    for value in vector.iter_mut() {
+        let token = tokens.next().context("Expected 3 coordinates")?;
+        *value = token
+            .parse()
+            .with_context(|| format!("Invalid coordinate {token}"))?;
+    }
+
+    Ok(vector)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fmt::Write, fs};
+
+    use motor_math::FloatType;
+    use nalgebra::{vector, Vector3};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+
+    use super::{Hull, HullSource};
+    use crate::{
+        format::{self, test_directory},
+        geometry::VehicleGeometry,
+        heuristic::ScoreSettings,
+        motor_library::MotorLibrary,
+    };
+
+    /// An OBJ of `count` random triangles within a meter of the origin
+    fn random_mesh(rng: &mut ChaCha8Rng, count: usize) -> String {
+        let mut obj = String::new();
+        for idx in 0..count {
+            let center = Vector3::from_fn(|_, _| rng.gen_range(-1.0..1.0));
+            for _ in 0..3 {
+                let vertex: Vector3<FloatType> =
+                    center + Vector3::from_fn(|_, _| rng.gen_range(-0.1..0.1));
+                writeln!(obj, "v {} {} {}", vertex.x, vertex.y, vertex.z).unwrap();
+            }
+            writeln!(obj, "f {} {} {}", idx * 3 + 1, idx * 3 + 2, idx * 3 + 3).unwrap();
+        }
+        obj
+    }
+
+    fn load_random_hull(name: &str, rng: &mut ChaCha8Rng) -> Hull {
+        let directory = test_directory(name);
+        fs::write(directory.join("hull.obj"), random_mesh(rng, 500)).unwrap();
+        Hull::load(HullSource::new("hull.obj"), &directory).unwrap()
+    }
+
+    #[test]
+    fn surface_distance_matches_every_triangle() {
+        let mut rng = ChaCha8Rng::seed_from_u64(1);
+        let hull = load_random_hull("surface-distance", &mut rng);
+
+        for _ in 0..200 {
+            let position: Vector3<FloatType> = Vector3::from_fn(|_, _| rng.gen_range(-1.5..1.5));
+            let expected = hull
+                .triangles()
+                .iter()
+                .map(|it| (it.closest_point(position) - position).norm())
+                .fold(FloatType::INFINITY, FloatType::min);
+
+            assert!((hull.surface_distance(position).abs() - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn thrusters_inside_closed_hulls_are_penalised() {
+        let directory = test_directory("closed-hull");
+        fs::write(
+            directory.join("cube.obj"),
+            "v -1 -1 -1\nv 1 -1 -1\nv 1 1 -1\nv -1 1 -1\n\
+             v -1 -1 1\nv 1 -1 1\nv 1 1 1\nv -1 1 1\n\
+             f 1 4 3 2\nf 5 6 7 8\nf 1 2 6 5\nf 2 3 7 6\nf 3 4 8 7\nf 4 1 5 8\n",
+        )
+        .unwrap();
+        let hull = Hull::load(HullSource::new("cube.obj"), &directory).unwrap();
+
+        let centroid = Vector3::zeros();
+        assert!(hull.contains(centroid));
+        assert!((hull.surface_distance(centroid) + 1.0).abs() < 1e-12);
+        assert!((hull.surface_distance(vector![0.0, 0.0, 1.5]) - 0.5).abs() < 1e-12);
+
+        // The centroid is further from the surface than a thruster's radius, but is still inside
+        let radius = ScoreSettings::default().thruster_exclusion_radius;
+        assert!(radius - hull.surface_distance(centroid) > radius);
+    }
+
+    #[test]
+    fn ray_hits_match_every_triangle() {
+        let mut rng = ChaCha8Rng::seed_from_u64(2);
+        let hull = load_random_hull("ray-hit", &mut rng);
+
+        for _ in 0..200 {
+            let origin: Vector3<FloatType> = Vector3::from_fn(|_, _| rng.gen_range(-1.5..1.5));
+            let direction = Vector3::from_fn(|_, _| rng.gen_range(-1.0..1.0)).normalize();
+            let expected = hull
+                .triangles()
+                .iter()
+                .filter_map(|it| it.intersect(origin, direction))
+                .filter(|it| *it > 0.0 && *it <= 1.0)
+                .min_by(FloatType::total_cmp);
+
+            assert_eq!(hull.ray_hit(origin, direction, 1.0), expected);
+        }
+    }
+
+    #[test]
+    fn axis_aligned_rays_hit() {
+        let directory = test_directory("axis-aligned");
+        fs::write(
+            directory.join("wall.obj"),
+            "v 1 -1 -1\nv 1 1 -1\nv 1 0 1\nf 1 2 3\n",
+        )
+        .unwrap();
+        let hull = Hull::load(HullSource::new("wall.obj"), &directory).unwrap();
+
+        let hit = hull.ray_hit(Vector3::zeros(), vector![1.0, 0.0, 0.0], 2.0);
+        assert_eq!(hit, Some(1.0));
+        assert_eq!(
+            hull.ray_hit(Vector3::zeros(), vector![-1.0, 0.0, 0.0], 2.0),
+            None
+        );
+    }
+
+    #[test]
+    fn paths_are_relative_to_the_referencing_file() {
+        let directory = test_directory("relative-paths");
+        fs::create_dir(directory.join("meshes")).unwrap();
+        fs::write(
+            directory.join("meshes/wall.obj"),
+            "v 1 -1 -1\nv 1 1 -1\nv 1 0 1\nf 1 2 3\n",
+        )
+        .unwrap();
+        fs::write(
+            directory.join("geometry.toml"),
+            "[hull]\npath = \"meshes/wall.obj\"\n",
+        )
+        .unwrap();
+
+        let geometry: VehicleGeometry =
+            format::load_with_paths(directory.join("geometry.toml")).unwrap();
+        let hull = geometry.hull.as_ref().unwrap();
+        assert!(hull.source().path.is_absolute());
+        assert!(hull.source().path.ends_with("meshes/wall.obj"));
+
+        format::save_with_paths(directory.join("copy.toml"), &geometry).unwrap();
+        let saved = fs::read_to_string(directory.join("copy.toml")).unwrap();
+        assert!(saved.contains("path = \"meshes/wall.obj\""), "{saved}");
+        let reloaded: VehicleGeometry =
+            format::load_with_paths(directory.join("copy.toml")).unwrap();
+        assert_eq!(reloaded, geometry);
+
+        // A motor library resolves its curves the same way
+        fs::create_dir(directory.join("curves")).unwrap();
+        fs::write(
+            directory.join("curves/t200.csv"),
+            include_str!("../motor_data.csv"),
+        )
+        .unwrap();
+        fs::write(
+            directory.join("library.toml"),
+            "[[models]]\nname = \"T200\"\n\n[[models.curves]]\nvoltage = 14.0\n\
+             path = \"curves/t200.csv\"\n",
+        )
+        .unwrap();
+        assert!(MotorLibrary::load(directory.join("library.toml")).is_ok());
+    }
+}
@@ -0,0 +1,233 @@
+use std::path::Path;
+
+use motor_math::{x3d::X3dMotorId, Direction, ErasedMotorId, FloatType, Motor, MotorConfig};
+use nalgebra::{vector, Vector3};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    format::{self, FilePaths},
+    heuristic::{Scaled, ScoreResult, ScoreSettings, Unscaled},
+    optimize::OptimizationOutput,
+    HEIGHT, LENGTH, WIDTH,
+};
+
+/// A thruster configuration in a form that can be shared with other tools
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThrusterLayout {
+    pub thrusters: Vec<ThrusterEntry>,
+
+    /// The score of the layout when it was exported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<LayoutScore>,
+    /// The heuristic the layout was scored with
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub heuristic: Option<ScoreSettings>,
+    /// The seed of the optimization run that produced the layout
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThrusterEntry {
+    pub id: ErasedMotorId,
+    /// Position in meters relative to the center of the vehicle
+    pub position: [FloatType; 3],
+    /// Unit vector in the direction of thrust
+    pub orientation: [FloatType; 3],
+    pub direction: SpinDirection,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpinDirection {
+    Clockwise,
+    CounterClockwise,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LayoutScore {
+    pub score: FloatType,
+    pub unscaled: ScoreResult<FloatType, Unscaled>,
+    pub scaled: ScoreResult<FloatType, Scaled>,
+}
+
+impl From<Direction> for SpinDirection {
+    fn from(value: Direction) -> Self {
+        match value {
+            Direction::Clockwise => SpinDirection::Clockwise,
+            Direction::CounterClockwise => SpinDirection::CounterClockwise,
+        }
+    }
+}
+
+impl From<SpinDirection> for Direction {
+    fn from(value: SpinDirection) -> Self {
+        match value {
+            SpinDirection::Clockwise => Direction::Clockwise,
+            SpinDirection::CounterClockwise => Direction::CounterClockwise,
+        }
+    }
+}
+
+impl FilePaths for ThrusterLayout {
+    fn load_paths(&mut self, directory: &Path) -> anyhow::Result<()> {
+        match &mut self.heuristic {
+            Some(heuristic) => heuristic.load_paths(directory),
+            None => Ok(()),
+        }
+    }
+
+    fn relative_paths(&mut self, directory: &Path) {
+        if let Some(heuristic) = &mut self.heuristic {
+            heuristic.relative_paths(directory);
+        }
+    }
+}
+
+impl ThrusterLayout {
+    pub fn from_motor_config(motor_config: &MotorConfig<ErasedMotorId, FloatType>) -> Self {
+        let mut thrusters = motor_config
+            .motors()
+            .map(|(id, motor)| ThrusterEntry {
+                id: *id,
+                position: motor.position.into(),
+                orientation: motor.orientation.into(),
+                direction: motor.direction.into(),
+            })
+            .collect::<Vec<_>>();
+        thrusters.sort_by_key(|it| it.id);
+
+        Self {
+            thrusters,
+            score: None,
+            heuristic: None,
+            seed: None,
+        }
+    }
+
+    pub fn from_output(output: &OptimizationOutput, heuristic: &ScoreSettings) -> Self {
+        Self {
+            score: Some(LayoutScore {
+                score: output.score,
+                unscaled: output.score_result_unscaled.clone(),
+                scaled: output.score_result_scaled.clone(),
+            }),
+            heuristic: Some(heuristic.clone()),
+            seed: output.seed,
+            ..Self::from_motor_config(&output.motor_config)
+        }
+    }
+
+    /// Rebuilds the motor config described by this layout
+    pub fn motor_config(&self) -> MotorConfig<ErasedMotorId, FloatType> {
+        MotorConfig::new_raw(
+            self.thrusters.iter().map(|thruster| {
+                (
+                    thruster.id,
+                    Motor {
+                        position: Vector3::from(thruster.position),
+                        orientation: Vector3::from(thruster.orientation).normalize(),
+                        direction: thruster.direction.into(),
+                    },
+                )
+            }),
+            vector![0.0, 0.0, 0.0],
+        )
+    }
+}
+
+/// The X3d configuration of our current vehicle
+pub fn x3d_motor_config() -> MotorConfig<ErasedMotorId, FloatType> {
+    MotorConfig::<X3dMotorId, FloatType>::new(
+        Motor {
+            position: vector![WIDTH, LENGTH, HEIGHT] / 2.0,
+            orientation: vector![-0.254, 0.571, -0.781].normalize(),
+            direction: Direction::Clockwise,
+        },
+        vector![0.0, 0.0, 0.0],
+    )
+    .erase()
+}
+
+/// Layouts that can be evaluated without loading a file
+pub fn builtin_layouts() -> Vec<(&'static str, MotorConfig<ErasedMotorId, FloatType>)> {
+    vec![("X3d", x3d_motor_config())]
+}
+
+pub fn load_layout(path: impl AsRef<Path>) -> anyhow::Result<ThrusterLayout> {
+    format::load_with_paths(path)
+}
+
+pub fn save_layout(path: impl AsRef<Path>, layout: &ThrusterLayout) -> anyhow::Result<()> {
+    format::save_with_paths(path, layout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        load_layout, save_layout, LayoutScore, SpinDirection, ThrusterEntry, ThrusterLayout,
+    };
+    use crate::{
+        format::{test_directory, FileFormat},
+        heuristic::ScoreSettings,
+    };
+
+    fn layout() -> ThrusterLayout {
+        ThrusterLayout {
+            thrusters: vec![
+                ThrusterEntry {
+                    id: 0,
+                    position: [0.1, 0.2, -0.05],
+                    orientation: [0.0, 0.6, 0.8],
+                    direction: SpinDirection::Clockwise,
+                },
+                ThrusterEntry {
+                    id: 1,
+                    position: [-0.1, 0.2, -0.05],
+                    orientation: [0.0, -0.6, 0.8],
+                    direction: SpinDirection::CounterClockwise,
+                },
+            ],
+            score: Some(LayoutScore {
+                score: 12.5,
+                unscaled: Default::default(),
+                scaled: Default::default(),
+            }),
+            heuristic: Some(ScoreSettings::default()),
+            seed: Some(42),
+        }
+    }
+
+    #[test]
+    fn saved_layouts_load_back() {
+        let directory = test_directory("layout-round-trip");
+        let layout = layout();
+        let expected = serde_json::to_value(&layout).unwrap();
+
+        for name in ["layout.toml", "layout.json"] {
+            let path = directory.join(name);
+            save_layout(&path, &layout).unwrap();
+            let loaded = load_layout(&path).unwrap();
+
+            assert_eq!(serde_json::to_value(&loaded).unwrap(), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn layouts_only_need_thrusters() {
+        let contents = r#"
+            [[thrusters]]
+            id = 3
+            position = [0.0, 0.1, 0.2]
+            orientation = [1.0, 0.0, 0.0]
+            direction = "CounterClockwise"
+        "#;
+
+        let layout: ThrusterLayout = FileFormat::Toml.parse(contents).unwrap();
+        assert_eq!(layout.thrusters.len(), 1);
+        assert_eq!(
+            layout.thrusters[0].direction,
+            SpinDirection::CounterClockwise
+        );
+        assert!(layout.score.is_none() && layout.heuristic.is_none() && layout.seed.is_none());
+    }
+}
@@ -3,8 +3,20 @@
 
 use motor_math::FloatType;
 
+pub mod constraints;
+pub mod format;
+pub mod geometry;
 pub mod heuristic;
+pub mod hull;
+pub mod layout;
+pub mod mass;
+pub mod motor_fit;
+pub mod motor_library;
+pub mod motor_validation;
 pub mod optimize;
+pub mod physics;
+pub mod power;
+pub mod thruster_models;
 
 pub const WIDTH: FloatType = 0.19 * 2.0;
 pub const LENGTH: FloatType = 0.22 * 2.0;
@@ -0,0 +1,200 @@
+use std::{fmt::Debug, hash::Hash, path::Path};
+
+use motor_math::{FloatType, MotorConfig, Number};
+use nalgebra::Vector3;
+use serde::{Deserialize, Serialize};
+
+use crate::format;
+
+/// Masses and displaced volumes of the vehicle, used to find its centers of mass and buoyancy
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MassModel {
+    /// Everything on the vehicle other than the thrusters
+    pub components: Vec<Component>,
+    /// Mass of each thruster in kilograms
+    pub thruster_mass: FloatType,
+    /// Volume of water each thruster displaces in cubic meters
+    pub thruster_volume: FloatType,
+    /// Direction of the surface in the vehicle's frame while it is level
+    pub up: [FloatType; 3],
+}
+
+/// A part of the vehicle that does not move during optimization
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Component {
+    /// Only used for display
+    #[serde(default)]
+    pub name: String,
+    /// Mass in kilograms
+    pub mass: FloatType,
+    /// Volume of water displaced in cubic meters
+    #[serde(default)]
+    pub displacement: FloatType,
+    /// Position of the component's center relative to the center of the vehicle, in meters
+    pub position: [FloatType; 3],
+}
+
+impl Default for MassModel {
+    fn default() -> Self {
+        Self {
+            components: vec![],
+            thruster_mass: 0.344,
+            thruster_volume: 0.000188,
+            up: [0.0, 0.0, 1.0],
+        }
+    }
+}
+
+/// The mass distribution of one motor config
+#[derive(Debug, Clone)]
+pub struct MassProperties<D> {
+    /// Total mass in kilograms
+    pub mass: D,
+    /// Total displaced volume in cubic meters
+    pub displacement: D,
+    pub center_of_mass: Vector3<D>,
+    pub center_of_buoyancy: Vector3<D>,
+}
+
+impl MassModel {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        format::load(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        format::save(path, self)
+    }
+
+    /// The unit vector towards the surface, Z if `up` is zero
+    pub fn up(&self) -> Vector3<FloatType> {
+        Vector3::from(self.up)
+            .try_normalize(1e-9)
+            .unwrap_or_else(Vector3::z)
+    }
+
+    /// The mass distribution of the components with a thruster at each motor's position
+    pub fn properties<MotorId: Debug + Ord + Hash + Clone, D: Number>(
+        &self,
+        motor_config: &MotorConfig<MotorId, D>,
+    ) -> MassProperties<D> {
+        let mut mass = D::zero();
+        let mut displacement = D::zero();
+        let mut mass_moment = Vector3::<D>::zeros();
+        let mut displacement_moment = Vector3::<D>::zeros();
+
+        for component in &self.components {
+            let position = Vector3::from(component.position).map(D::from);
+
+            mass += D::from(component.mass);
+            displacement += D::from(component.displacement);
+            mass_moment += position * D::from(component.mass);
+            displacement_moment += position * D::from(component.displacement);
+        }
+
+        for (_, motor) in motor_config.motors() {
+            mass += D::from(self.thruster_mass);
+            displacement += D::from(self.thruster_volume);
+            mass_moment += motor.position * D::from(self.thruster_mass);
+            displacement_moment += motor.position * D::from(self.thruster_volume);
+        }
+
+        MassProperties {
+            center_of_mass: weighted_center(mass_moment, mass),
+            center_of_buoyancy: weighted_center(displacement_moment, displacement),
+            mass,
+            displacement,
+        }
+    }
+}
+
+impl<D: Number> MassProperties<D> {
+    /// Height of the center of buoyancy above the center of mass and its horizontal offset.
+    /// A fully submerged vehicle rights itself when the height is positive and floats level
+    /// when the offset is zero
+    pub fn metacentric_height(&self, up: Vector3<FloatType>) -> (D, Vector3<D>) {
+        let up = up.map(D::from);
+        let separation = self.center_of_buoyancy - self.center_of_mass;
+        let height = separation.dot(&up);
+
+        (height, separation - up * height)
+    }
+}
+
+fn weighted_center<D: Number>(moment: Vector3<D>, total: D) -> Vector3<D> {
+    if total.re() > 0.0 {
+        moment / total
+    } else {
+        Vector3::zeros()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use motor_math::{Direction, FloatType, Motor, MotorConfig};
+    use nalgebra::vector;
+
+    use super::{Component, MassModel};
+
+    fn component(mass: FloatType, displacement: FloatType, position: [FloatType; 3]) -> Component {
+        Component {
+            name: String::new(),
+            mass,
+            displacement,
+            position,
+        }
+    }
+
+    #[test]
+    fn centers_combine_components_and_thrusters() {
+        let model = MassModel {
+            components: vec![
+                component(2.0, 0.0, [1.0, 0.0, 0.0]),
+                component(1.0, 0.0, [-2.0, 0.0, 0.3]),
+                // A float, displaces water without adding mass
+                component(0.0, 0.003, [0.4, 0.0, 0.5]),
+            ],
+            thruster_mass: 1.0,
+            thruster_volume: 0.001,
+            up: [0.0, 0.0, 2.0],
+        };
+        let motor_config = MotorConfig::new_raw(
+            [(
+                0u8,
+                Motor {
+                    position: vector![0.0, 1.0, 0.0],
+                    orientation: vector![1.0, 0.0, 0.0],
+                    direction: Direction::Clockwise,
+                },
+            )],
+            vector![0.0, 0.0, 0.0],
+        );
+
+        let properties = model.properties(&motor_config);
+        assert!((properties.mass - 4.0).abs() < 1e-12);
+        assert!((properties.displacement - 0.004).abs() < 1e-12);
+        // (2 * 1 + 1 * -2, 1 * 1, 1 * 0.3) / 4
+        assert!((properties.center_of_mass - vector![0.0, 0.25, 0.075]).norm() < 1e-12);
+        // (0.003 * 0.4, 0.001 * 1, 0.003 * 0.5) / 0.004
+        assert!((properties.center_of_buoyancy - vector![0.3, 0.25, 0.375]).norm() < 1e-12);
+
+        let (height, offset) = properties.metacentric_height(model.up());
+        assert!((height - 0.3).abs() < 1e-12);
+        assert!((offset - vector![0.3, 0.0, 0.0]).norm() < 1e-12);
+
+        // Rolled onto its side, the horizontal part of the separation is now along z
+        let (height, offset) = properties.metacentric_height(vector![1.0, 0.0, 0.0]);
+        assert!((height - 0.3).abs() < 1e-12);
+        assert!((offset - vector![0.0, 0.0, 0.3]).norm() < 1e-12);
+    }
+
+    #[test]
+    fn zero_up_is_z() {
+        let model = MassModel {
+            up: [0.0; 3],
+            ..MassModel::default()
+        };
+
+        assert_eq!(model.up(), vector![0.0, 0.0, 1.0]);
+    }
+}
@@ -0,0 +1,339 @@
+use std::{
+    fmt::{self, Display},
+    hash::Hash,
+};
+
+use anyhow::{ensure, Context};
+use motor_math::{motor_preformance::MotorRecord, FloatType, Number};
+use nalgebra::{DMatrix, DVector};
+use stable_hashmap::StableHashMap;
+
+use crate::{
+    motor_library::{CurveRow, ThrustCurve},
+    motor_validation::NEUTRAL_PWM,
+};
+
+/// Degree of the fitted polynomials when none is given
+pub const DEFAULT_FIT_DEGREE: usize = 4;
+
+/// Newtons per kilogram force, efficiency is given in grams per watt
+const STANDARD_GRAVITY: FloatType = 9.81;
+const SINGULAR_EPSILON: FloatType = 1e-12;
+
+/// Polynomials fitted to a thrust curve, usable in place of its motor data
+///
+/// The tables are piecewise linear, so gradients of anything looked up in them jump from row to
+/// row. The fits are smooth, so derivatives through the current budget are too
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotorFit {
+    pub voltage: FloatType,
+    pub degree: usize,
+    pub forward: DirectionFit,
+    pub reverse: DirectionFit,
+}
+
+/// Current, PWM and RPM as polynomials of the force in one direction
+///
+/// The polynomials take the magnitude of the force over `max_force`, past which the thruster
+/// saturates like the last row of its table
+#[derive(Debug, Clone, PartialEq)]
+pub struct DirectionFit {
+    /// The strongest force measured, positive for both directions
+    pub max_force: FloatType,
+    /// Without a constant term, so no force draws no current
+    pub current: Polynomial,
+    /// How far the PWM is from neutral, the constant term is the edge of the dead band
+    pub pwm: Polynomial,
+    /// Without a constant term
+    pub rpm: Polynomial,
+    pub current_residuals: Residuals,
+    pub pwm_residuals: Residuals,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Polynomial {
+    /// From the constant term up
+    pub coefficients: Vec<FloatType>,
+}
+
+/// How far the measured rows are from a fit
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Residuals {
+    pub rms: FloatType,
+    pub max: FloatType,
+}
+
+impl MotorFit {
+    /// Fits polynomials of `degree` to the forward and reverse rows of `curve`
+    pub fn fit(curve: &ThrustCurve, degree: usize) -> anyhow::Result<Self> {
+        ensure!(degree >= 1, "Fits need a degree of at least 1");
+
+        let forward = curve
+            .rows
+            .iter()
+            .filter(|it| it.force > 0.0)
+            .collect::<Vec<_>>();
+        let reverse = curve
+            .rows
+            .iter()
+            .filter(|it| it.force < 0.0)
+            .collect::<Vec<_>>();
+
+        Ok(Self {
+            voltage: curve.voltage,
+            degree,
+            forward: DirectionFit::fit(&forward, degree).context("Fit forward thrust")?,
+            reverse: DirectionFit::fit(&reverse, degree).context("Fit reverse thrust")?,
+        })
+    }
+
+    /// The command producing `force`, like a lookup in the motor data
+    ///
+    /// Forces past the strongest measured one are clamped to it
+    pub fn record<D: Number>(&self, force: D) -> MotorRecord<D> {
+        let (fit, side) = if force.re() >= 0.0 {
+            (&self.forward, 1.0)
+        } else {
+            (&self.reverse, -1.0)
+        };
+        let magnitude = if force.re().abs() > fit.max_force {
+            D::from(fit.max_force)
+        } else {
+            force * side
+        };
+        let force = magnitude * side;
+
+        let current = fit.eval(&fit.current, magnitude);
+        let power = current * self.voltage;
+        let pwm = if force.re() == 0.0 {
+            D::from(NEUTRAL_PWM)
+        } else {
+            fit.eval(&fit.pwm, magnitude) * side + NEUTRAL_PWM
+        };
+        let efficiency = if power.re() > 0.0 {
+            magnitude * (1000.0 / STANDARD_GRAVITY) / power
+        } else {
+            D::zero()
+        };
+
+        MotorRecord {
+            pwm,
+            rpm: fit.eval(&fit.rpm, magnitude),
+            current,
+            voltage: D::from(self.voltage),
+            power,
+            force,
+            efficiency,
+        }
+    }
+
+    /// Like `reverse::forces_to_cmds` with the commands from the fits
+    pub fn forces_to_cmds<MotorId: Ord + Hash + Clone, D: Number>(
+        &self,
+        forces: StableHashMap<MotorId, D>,
+    ) -> StableHashMap<MotorId, MotorRecord<D>> {
+        forces
+            .into_iter()
+            .map(|(id, force)| (id, self.record(force)))
+            .collect()
+    }
+
+    /// The strongest reverse and forward force, like `ThrustCurve::force_range`
+    pub fn force_range(&self) -> (FloatType, FloatType) {
+        (-self.reverse.max_force, self.forward.max_force)
+    }
+
+    /// The strongest force in the direction of `force`
+    pub fn max_force(&self, force: FloatType) -> FloatType {
+        if force >= 0.0 {
+            self.forward.max_force
+        } else {
+            self.reverse.max_force
+        }
+    }
+}
+
+impl DirectionFit {
+    fn fit(rows: &[&CurveRow], degree: usize) -> anyhow::Result<Self> {
+        ensure!(
+            rows.len() > degree,
+            "{} rows with thrust can't fit a degree {degree} polynomial",
+            rows.len()
+        );
+
+        let max_force = rows
+            .iter()
+            .map(|it| it.force.abs())
+            .fold(0.0, FloatType::max);
+        let scaled = rows
+            .iter()
+            .map(|it| it.force.abs() / max_force)
+            .collect::<Vec<_>>();
+        let column =
+            |value: fn(&CurveRow) -> FloatType| rows.iter().map(|it| value(it)).collect::<Vec<_>>();
+
+        let currents = column(|it| it.current);
+        let pwms = column(|it| (it.pwm - NEUTRAL_PWM).abs());
+        let current = Polynomial::fit(&scaled, &currents, degree, true)?;
+        let pwm = Polynomial::fit(&scaled, &pwms, degree, false)?;
+        let rpm = Polynomial::fit(&scaled, &column(|it| it.rpm), degree, true)?;
+
+        Ok(Self {
+            max_force,
+            current_residuals: current.residuals(&scaled, &currents),
+            pwm_residuals: pwm.residuals(&scaled, &pwms),
+            current,
+            pwm,
+            rpm,
+        })
+    }
+
+    /// `polynomial` at the force `magnitude`, which is at most `max_force`
+    fn eval<D: Number>(&self, polynomial: &Polynomial, magnitude: D) -> D {
+        polynomial.eval(magnitude / self.max_force)
+    }
+}
+
+impl Polynomial {
+    /// The least squares polynomial of `degree` through `(xs, ys)`, without a constant term if
+    /// `through_origin`
+    pub fn fit(
+        xs: &[FloatType],
+        ys: &[FloatType],
+        degree: usize,
+        through_origin: bool,
+    ) -> anyhow::Result<Self> {
+        let lowest = usize::from(through_origin);
+        let design = DMatrix::from_fn(xs.len(), degree + 1 - lowest, |row, col| {
+            xs[row].powi((col + lowest) as i32)
+        });
+        let solution = design
+            .svd(true, true)
+            .solve(&DVector::from_column_slice(ys), SINGULAR_EPSILON)
+            .map_err(anyhow::Error::msg)?;
+
+        let mut coefficients = vec![0.0; lowest];
+        coefficients.extend(solution.iter());
+        Ok(Self { coefficients })
+    }
+
+    pub fn eval<D: Number>(&self, x: D) -> D {
+        self.coefficients
+            .iter()
+            .rev()
+            .fold(D::zero(), |total, &coefficient| total * x + coefficient)
+    }
+
+    fn residuals(&self, xs: &[FloatType], ys: &[FloatType]) -> Residuals {
+        let errors = xs
+            .iter()
+            .zip(ys)
+            .map(|(&x, y)| self.eval(x) - y)
+            .collect::<Vec<_>>();
+
+        Residuals {
+            rms: (errors.iter().map(|it| it * it).sum::<FloatType>() / errors.len() as FloatType)
+                .sqrt(),
+            max: errors.iter().map(|it| it.abs()).fold(0.0, FloatType::max),
+        }
+    }
+}
+
+impl Display for MotorFit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "Degree {} fit at {} V, residuals as rms (max)",
+            self.degree, self.voltage
+        )?;
+        for (name, fit) in [("Forward", &self.forward), ("Reverse", &self.reverse)] {
+            writeln!(
+                f,
+                "{name} to {:.2} N: current {:.3} A ({:.3} A), PWM {:.1} us ({:.1} us)",
+                fit.max_force,
+                fit.current_residuals.rms,
+                fit.current_residuals.max,
+                fit.pwm_residuals.rms,
+                fit.pwm_residuals.max
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use motor_math::FloatType;
+
+    use super::{MotorFit, Polynomial, DEFAULT_FIT_DEGREE};
+    use crate::motor_library::{CurveRow, ThrustCurve};
+
+    fn shipped_curve() -> ThrustCurve {
+        ThrustCurve::parse(12.0, include_str!("../motor_data.csv"), 1.0).unwrap()
+    }
+
+    #[test]
+    fn exact_polynomials_have_no_residuals() {
+        let xs = (0..=10)
+            .map(|it| it as FloatType / 10.0)
+            .collect::<Vec<_>>();
+        let expected = Polynomial {
+            coefficients: vec![0.0, 2.0, -1.0, 3.0],
+        };
+        let ys = xs.iter().map(|&x| expected.eval(x)).collect::<Vec<_>>();
+
+        let fit = Polynomial::fit(&xs, &ys, 3, true).unwrap();
+        let residuals = fit.residuals(&xs, &ys);
+
+        assert!(residuals.max < 1e-9, "{residuals:?}");
+        for (fitted, expected) in fit.coefficients.iter().zip(&expected.coefficients) {
+            assert!((fitted - expected).abs() < 1e-9, "{fit:?}");
+        }
+    }
+
+    #[test]
+    fn shipped_curve_fits_closely() {
+        let fit = MotorFit::fit(&shipped_curve(), DEFAULT_FIT_DEGREE).unwrap();
+
+        for direction in [&fit.forward, &fit.reverse] {
+            assert!(direction.current_residuals.rms < 0.1, "{fit}");
+            assert!(direction.current_residuals.max < 0.5, "{fit}");
+            assert!(direction.pwm_residuals.rms < 5.0, "{fit}");
+        }
+    }
+
+    #[test]
+    fn fits_agree_with_the_table() {
+        let curve = shipped_curve();
+        let fit = MotorFit::fit(&curve, DEFAULT_FIT_DEGREE).unwrap();
+
+        for row in curve.rows.iter().filter(|it| it.force != 0.0) {
+            let record = fit.record(row.force);
+            let CurveRow { current, .. } = curve.row_at(record.pwm);
+
+            assert!(
+                (record.current - row.current).abs() < 0.5,
+                "{record:?} {row:?}"
+            );
+            assert!((current - row.current).abs() < 0.5, "{record:?} {row:?}");
+            assert_eq!(record.pwm > 1500.0, row.force > 0.0, "{record:?} {row:?}");
+        }
+        assert_eq!(fit.force_range(), curve.force_range());
+    }
+
+    #[test]
+    fn forces_past_the_maximum_saturate() {
+        let fit = MotorFit::fit(&shipped_curve(), DEFAULT_FIT_DEGREE).unwrap();
+        let (min, max) = fit.force_range();
+
+        for force in [min, max] {
+            let record = fit.record(force);
+            let past = fit.record(force * 2.0);
+
+            assert_eq!(past.force, force);
+            assert_eq!(past.current, record.current);
+            assert_eq!(past.pwm, record.pwm);
+        }
+    }
+}
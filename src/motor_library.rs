@@ -0,0 +1,408 @@
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, ensure, Context};
+use itertools::Itertools;
+use motor_math::{
+    motor_preformance::{self, MotorData},
+    FloatType,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{format, motor_validation::MotorDataReport};
+
+/// Named thruster models, each with thrust curves measured at several voltages
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotorLibrary {
+    models: Vec<MotorModel>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotorModel {
+    pub name: String,
+    /// Sorted by voltage
+    curves: Vec<ThrustCurve>,
+}
+
+/// The performance of a thruster over its PWM range at one voltage
+#[derive(Debug, Clone, PartialEq)]
+pub struct ThrustCurve {
+    pub voltage: FloatType,
+    /// Sorted by PWM
+    pub rows: Vec<CurveRow>,
+}
+
+/// One row of a motor data CSV
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CurveRow {
+    pub pwm: FloatType,
+    pub rpm: FloatType,
+    pub current: FloatType,
+    pub voltage: FloatType,
+    pub power: FloatType,
+    pub force: FloatType,
+    pub efficiency: FloatType,
+}
+
+/// The on disk format of a motor library, curve paths are relative to the library
+#[derive(Deserialize)]
+struct LibraryFile {
+    models: Vec<ModelFile>,
+}
+
+#[derive(Deserialize)]
+struct ModelFile {
+    name: String,
+    curves: Vec<CurveFile>,
+}
+
+#[derive(Deserialize)]
+struct CurveFile {
+    voltage: FloatType,
+    #[serde(flatten)]
+    table: TableFile,
+    /// Multiplies the force column, 9.81 for tables in kilograms force
+    #[serde(default = "CurveFile::default_force_scale")]
+    force_scale: FloatType,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum TableFile {
+    /// One table covering both directions
+    Combined { path: PathBuf },
+    /// Separate tables for forward and reverse thrust
+    Split { forward: PathBuf, reverse: PathBuf },
+}
+
+impl MotorLibrary {
+    pub fn new(models: Vec<MotorModel>) -> anyhow::Result<Self> {
+        ensure!(!models.is_empty(), "Motor library has no models");
+        Ok(Self { models })
+    }
+
+    /// Loads a library file, failing if any of its tables has errors, see `validate`
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let file: LibraryFile = format::load(path)?;
+        let directory = format::directory(path);
+
+        let models = file
+            .models
+            .into_iter()
+            .map(|model| {
+                let curves = model
+                    .curves
+                    .into_iter()
+                    .map(|curve| curve.load(&directory))
+                    .collect::<anyhow::Result<_>>()
+                    .with_context(|| format!("Load model {}", model.name))?;
+
+                MotorModel::new(model.name, curves)
+            })
+            .collect::<anyhow::Result<_>>()
+            .with_context(|| format!("Load {}", path.display()))?;
+
+        Self::new(models)
+    }
+
+    /// Checks the tables of every curve in a library file, returning the model, voltage and
+    /// report of each curve
+    pub fn validate(
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<Vec<(String, FloatType, MotorDataReport)>> {
+        let path = path.as_ref();
+        let file: LibraryFile = format::load(path)?;
+        let directory = format::directory(path);
+
+        let mut reports = vec![];
+        for model in file.models {
+            for curve in model.curves {
+                let tables = curve.tables(&directory)?;
+                let report = MotorDataReport::validate_tables(&tables, curve.force_scale);
+                reports.push((model.name.clone(), curve.voltage, report));
+            }
+        }
+
+        Ok(reports)
+    }
+
+    /// A library of the single model in a motor data CSV measured at `voltage`
+    pub fn from_csv(name: &str, voltage: FloatType, contents: &str) -> anyhow::Result<Self> {
+        MotorDataReport::validate(name, contents, 1.0).ensure_valid()?;
+        let curve = ThrustCurve::parse(voltage, contents, 1.0)?;
+        Self::new(vec![MotorModel::new(name.to_owned(), vec![curve])?])
+    }
+
+    pub fn models(&self) -> &[MotorModel] {
+        &self.models
+    }
+
+    /// The model called `name`, or the first model if `name` is not set
+    pub fn model(&self, name: Option<&str>) -> anyhow::Result<&MotorModel> {
+        match name {
+            Some(name) => match self.models.iter().find(|it| it.name == name) {
+                Some(model) => Ok(model),
+                None => bail!(
+                    "No motor model {name}, expected one of {}",
+                    self.models.iter().map(|it| &it.name).join(", ")
+                ),
+            },
+            None => Ok(&self.models[0]),
+        }
+    }
+}
+
+impl MotorModel {
+    pub fn new(name: String, mut curves: Vec<ThrustCurve>) -> anyhow::Result<Self> {
+        ensure!(!curves.is_empty(), "Motor model {name} has no curves");
+        curves.sort_by(|a, b| FloatType::total_cmp(&a.voltage, &b.voltage));
+        ensure!(
+            curves
+                .iter()
+                .tuple_windows()
+                .all(|(a, b)| a.voltage != b.voltage),
+            "Motor model {name} has two curves at the same voltage"
+        );
+
+        Ok(Self { name, curves })
+    }
+
+    pub fn curves(&self) -> &[ThrustCurve] {
+        &self.curves
+    }
+
+    /// The lowest and highest voltage curves were measured at
+    pub fn voltage_range(&self) -> (FloatType, FloatType) {
+        (
+            self.curves[0].voltage,
+            self.curves[self.curves.len() - 1].voltage,
+        )
+    }
+
+    /// The curve at `voltage`, interpolated between the closest measured curves. Voltages
+    /// outside the measured range scale the closest curve, see `ThrustCurve::scaled`
+    pub fn curve_at(&self, voltage: FloatType) -> ThrustCurve {
+        let above = self.curves.partition_point(|it| it.voltage < voltage);
+        if above == 0 {
+            return self.curves[0].scaled(voltage);
+        }
+        if above == self.curves.len() {
+            return self.curves[above - 1].scaled(voltage);
+        }
+
+        let (low, high) = (&self.curves[above - 1], &self.curves[above]);
+        let t = (voltage - low.voltage) / (high.voltage - low.voltage);
+
+        let rows = low
+            .rows
+            .iter()
+            .chain(&high.rows)
+            .map(|it| it.pwm)
+            .sorted_by(FloatType::total_cmp)
+            .dedup()
+            .map(|pwm| CurveRow {
+                voltage,
+                ..low.row_at(pwm).lerp(&high.row_at(pwm), t)
+            })
+            .collect();
+
+        ThrustCurve { voltage, rows }
+    }
+
+    /// Motor data at `voltage`, see `curve_at`
+    pub fn motor_data(&self, voltage: FloatType) -> anyhow::Result<MotorData> {
+        self.curve_at(voltage).motor_data()
+    }
+}
+
+impl CurveFile {
+    fn default_force_scale() -> FloatType {
+        1.0
+    }
+
+    /// The path and contents of each table of the curve
+    fn tables(&self, directory: &Path) -> anyhow::Result<Vec<(String, String)>> {
+        let paths = match &self.table {
+            TableFile::Combined { path } => vec![path],
+            TableFile::Split { forward, reverse } => vec![forward, reverse],
+        };
+
+        paths
+            .into_iter()
+            .map(|path| {
+                let path = directory.join(path);
+                let contents = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Read {}", path.display()))?;
+                Ok((path.display().to_string(), contents))
+            })
+            .collect()
+    }
+
+    fn load(self, directory: &Path) -> anyhow::Result<ThrustCurve> {
+        let tables = self.tables(directory)?;
+        let context = tables.iter().map(|(path, _)| path).join(" and ");
+
+        // The headers of the later tables are skipped so they can be read as one
+        let contents = tables
+            .iter()
+            .enumerate()
+            .map(|(idx, (_, contents))| {
+                contents
+                    .trim_end()
+                    .lines()
+                    .skip(usize::from(idx > 0))
+                    .join("\n")
+            })
+            .join("\n");
+
+        MotorDataReport::validate_tables(&tables, self.force_scale)
+            .ensure_valid()
+            .and_then(|()| ThrustCurve::parse(self.voltage, &contents, self.force_scale))
+            .with_context(|| format!("Load {context} at {} V", self.voltage))
+    }
+}
+
+impl ThrustCurve {
+    /// Reads a motor data CSV, repeats of a row are dropped and rows that differ at the same PWM
+    /// are an error, like in `MotorDataReport`
+    pub fn parse(
+        voltage: FloatType,
+        contents: &str,
+        force_scale: FloatType,
+    ) -> anyhow::Result<Self> {
+        let mut rows = csv::Reader::from_reader(contents.as_bytes())
+            .deserialize()
+            .map(|row| {
+                let row: CurveRow = row.context("Parse motor data")?;
+                Ok(CurveRow {
+                    force: row.force * force_scale,
+                    ..row
+                })
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        ensure!(rows.len() >= 2, "Motor data needs at least 2 rows");
+
+        rows.sort_by(|a, b| FloatType::total_cmp(&a.pwm, &b.pwm));
+        if let Some((a, _)) = rows
+            .iter()
+            .tuple_windows()
+            .find(|(a, b)| a.pwm == b.pwm && a != b)
+        {
+            bail!("Motor data has different rows at PWM {}", a.pwm);
+        }
+        rows.dedup();
+
+        Ok(Self { voltage, rows })
+    }
+
+    /// An estimate of the curve at another voltage from the propeller affinity laws. Speed is
+    /// taken to be proportional to voltage, so force and current grow with its square
+    pub fn scaled(&self, voltage: FloatType) -> Self {
+        let ratio = voltage / self.voltage;
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| CurveRow {
+                rpm: row.rpm * ratio,
+                current: row.current * ratio.powi(2),
+                voltage,
+                power: row.power * ratio.powi(3),
+                force: row.force * ratio.powi(2),
+                efficiency: row.efficiency / ratio,
+                ..*row
+            })
+            .collect();
+
+        Self { voltage, rows }
+    }
+
+    /// The strongest reverse and forward force
+    pub fn force_range(&self) -> (FloatType, FloatType) {
+        self.rows
+            .iter()
+            .map(|it| it.force)
+            .fold((0.0, 0.0), |(min, max), force| {
+                (min.min(force), max.max(force))
+            })
+    }
+
+    /// The most current drawn anywhere on the curve
+    pub fn max_current(&self) -> FloatType {
+        self.rows
+            .iter()
+            .map(|it| it.current)
+            .fold(0.0, FloatType::max)
+    }
+
+    /// The row at `pwm`, interpolated between the closest rows and clamped to the PWM range
+    pub fn row_at(&self, pwm: FloatType) -> CurveRow {
+        let above = self.rows.partition_point(|it| it.pwm < pwm);
+        if above == 0 {
+            return self.rows[0];
+        }
+        if above == self.rows.len() {
+            return self.rows[above - 1];
+        }
+
+        let (low, high) = (&self.rows[above - 1], &self.rows[above]);
+        low.lerp(high, (pwm - low.pwm) / (high.pwm - low.pwm))
+    }
+
+    pub fn motor_data(&self) -> anyhow::Result<MotorData> {
+        let mut writer = csv::Writer::from_writer(vec![]);
+        for row in &self.rows {
+            writer.serialize(row)?;
+        }
+        let contents = String::from_utf8(writer.into_inner()?)?;
+
+        motor_preformance::read_motor_data_from_string(&contents).context("Read motor data")
+    }
+}
+
+impl CurveRow {
+    fn lerp(&self, other: &Self, t: FloatType) -> Self {
+        let lerp = |a: FloatType, b: FloatType| a + (b - a) * t;
+
+        Self {
+            pwm: lerp(self.pwm, other.pwm),
+            rpm: lerp(self.rpm, other.rpm),
+            current: lerp(self.current, other.current),
+            voltage: lerp(self.voltage, other.voltage),
+            power: lerp(self.power, other.power),
+            force: lerp(self.force, other.force),
+            efficiency: lerp(self.efficiency, other.efficiency),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ThrustCurve;
+    use crate::motor_validation::MotorDataReport;
+
+    const HEADER: &str = "pwm,rpm,current,voltage,power,force,efficiency\n";
+    const ROWS: &str = "1100,2976,17.03,12,204.4,-28.449,14.2\n\
+                        1500,0,0,12,0,0,0\n\
+                        1900,2976,17.03,12,204.4,36.4,17.1\n";
+
+    #[test]
+    fn repeated_rows_are_dropped() {
+        let contents = format!("{HEADER}{ROWS}1500,0,0,12,0,0,0\n");
+
+        let curve = ThrustCurve::parse(12.0, &contents, 1.0).unwrap();
+        assert_eq!(curve.rows.len(), 3);
+        assert!(MotorDataReport::validate("test", &contents, 1.0)
+            .ensure_valid()
+            .is_ok());
+    }
+
+    #[test]
+    fn differing_rows_at_a_pwm_are_rejected() {
+        let contents = format!("{HEADER}{ROWS}1500,10,0.1,12,1.2,0.01,8.1\n");
+
+        assert!(ThrustCurve::parse(12.0, &contents, 1.0).is_err());
+        assert!(MotorDataReport::validate("test", &contents, 1.0)
+            .ensure_valid()
+            .is_err());
+    }
+}
@@ -0,0 +1,789 @@
+use std::fmt::{self, Display};
+
+use itertools::Itertools;
+use motor_math::FloatType;
+
+use crate::motor_library::CurveRow;
+
+/// The PWM pulse width in microseconds that stops a thruster
+pub const NEUTRAL_PWM: FloatType = 1500.0;
+
+/// Columns every motor data CSV needs, in the order of `CurveRow`
+const COLUMNS: [&str; 7] = [
+    "pwm",
+    "rpm",
+    "current",
+    "voltage",
+    "power",
+    "force",
+    "efficiency",
+];
+
+/// How far force may fall between neighbouring rows, as a fraction of the strongest force,
+/// before it counts as non-monotonic. Measurements jitter a little at saturation
+const MONOTONIC_TOLERANCE: FloatType = 0.01;
+/// Rows drawing less than this fraction of the peak current aren't checked for efficiency
+/// outliers, their current is too coarsely measured for the efficiency to mean much
+const EFFICIENCY_MIN_CURRENT: FloatType = 0.05;
+/// How far a row's efficiency may stray from the median of its neighbours, as a fraction
+const EFFICIENCY_TOLERANCE: FloatType = 0.2;
+/// Rows either side of a row its efficiency is compared with
+const EFFICIENCY_NEIGHBOURS: usize = 2;
+/// How many times stronger one direction may be than the other before it looks like the
+/// tables use different units
+const ASYMMETRY_LIMIT: FloatType = 4.0;
+/// Forward forces below this fraction of the strongest aren't compared with their mirrored
+/// reverse force
+const ASYMMETRY_MIN_FORCE: FloatType = 0.05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    /// Loads, but likely isn't what was measured
+    Warning,
+    /// Can't be used to score configs
+    Error,
+}
+
+/// What an issue is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IssueKind {
+    /// The header or a row couldn't be read
+    Unreadable,
+    MissingColumns,
+    /// A value that isn't a finite number
+    InvalidValue,
+    /// PWM falls from one row to the next
+    Unsorted,
+    /// Two rows at the same PWM with different values
+    ConflictingRows,
+    RepeatedRow,
+    TooFewRows,
+    NegativeCurrent,
+    /// Thrust on the wrong side of neutral
+    WrongDirection,
+    /// No row has forward or no row has reverse thrust
+    MissingDirection,
+    /// Force falls as PWM rises
+    NonMonotonic,
+    /// No dead band around neutral, or one that doesn't contain it
+    DeadBand,
+    EfficiencyOutlier,
+    /// One direction is much stronger than the other
+    Asymmetric,
+}
+
+impl IssueKind {
+    pub fn severity(&self) -> Severity {
+        match self {
+            IssueKind::Unreadable
+            | IssueKind::MissingColumns
+            | IssueKind::InvalidValue
+            | IssueKind::ConflictingRows
+            | IssueKind::TooFewRows
+            | IssueKind::NegativeCurrent
+            | IssueKind::WrongDirection
+            | IssueKind::MissingDirection => Severity::Error,
+            IssueKind::Unsorted
+            | IssueKind::RepeatedRow
+            | IssueKind::NonMonotonic
+            | IssueKind::DeadBand
+            | IssueKind::EfficiencyOutlier
+            | IssueKind::Asymmetric => Severity::Warning,
+        }
+    }
+}
+
+/// A line of one of the checked tables
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Location {
+    pub table: String,
+    pub line: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Issue {
+    pub severity: Severity,
+    pub kind: IssueKind,
+    /// Where the issue is, `None` if it's about the data as a whole
+    pub location: Option<Location>,
+    pub message: String,
+}
+
+/// Summary of the thrust curve described by motor data
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotorDataStats {
+    /// Rows left after duplicate PWMs are dropped
+    pub rows: usize,
+    pub pwm_range: (FloatType, FloatType),
+    /// The PWMs of the last reverse and first forward thrust
+    pub thrust_gap: Option<(FloatType, FloatType)>,
+    /// The PWMs of the first and last row without thrust inside the thrust gap
+    pub dead_band: Option<(FloatType, FloatType)>,
+    /// The strongest forward and reverse force, both positive
+    pub peak_forward: FloatType,
+    pub peak_reverse: FloatType,
+    /// The mean of reverse over forward force at PWMs mirrored around neutral
+    pub mean_asymmetry: Option<FloatType>,
+    pub peak_current: FloatType,
+    pub efficiency_outliers: usize,
+}
+
+/// The issues found in motor data and a summary of its thrust curve
+#[derive(Debug, Clone, PartialEq)]
+pub struct MotorDataReport {
+    pub issues: Vec<Issue>,
+    /// `None` if there are no usable rows
+    pub stats: Option<MotorDataStats>,
+}
+
+/// A parsed row and where it came from
+struct Row {
+    location: Location,
+    table: usize,
+    record: CurveRow,
+}
+
+impl MotorDataReport {
+    /// Checks the motor data CSV `contents`, `name` is used as the location of the issues
+    pub fn validate(name: &str, contents: &str, force_scale: FloatType) -> Self {
+        Self::validate_tables(&[(name.to_owned(), contents.to_owned())], force_scale)
+    }
+
+    /// Checks `(name, contents)` motor data CSVs that are loaded together, like the forward and
+    /// reverse tables of a curve
+    pub fn validate_tables(tables: &[(String, String)], force_scale: FloatType) -> Self {
+        let mut report = Self {
+            issues: vec![],
+            stats: None,
+        };
+
+        let mut rows = vec![];
+        for (table, (name, contents)) in tables.iter().enumerate() {
+            report.parse_table(table, name, contents, force_scale, &mut rows);
+        }
+
+        // Rows are loaded sorted by PWM, keeping the first of each PWM
+        rows.sort_by(|a, b| FloatType::total_cmp(&a.record.pwm, &b.record.pwm));
+        let rows = report.dedup(rows);
+
+        if rows.len() < 2 {
+            report.push(
+                IssueKind::TooFewRows,
+                None,
+                "Motor data needs at least 2 rows",
+            );
+            return report;
+        }
+
+        report.check_values(&rows);
+        report.check_monotonic(&rows);
+        let (thrust_gap, dead_band) = report.check_neutral(&rows);
+        let efficiency_outliers = report.check_efficiency(&rows);
+
+        let records = rows.iter().map(|it| it.record).collect_vec();
+        let peak_forward = records.iter().map(|it| it.force).fold(0.0, FloatType::max);
+        let peak_reverse = -records.iter().map(|it| it.force).fold(0.0, FloatType::min);
+        if peak_forward > 0.0 && peak_reverse > 0.0 {
+            let ratio = peak_reverse / peak_forward;
+            if !(1.0 / ASYMMETRY_LIMIT..=ASYMMETRY_LIMIT).contains(&ratio) {
+                report.push(
+                    IssueKind::Asymmetric,
+                    None,
+                    format!(
+                        "The strongest reverse force is {:.0}% of the strongest forward force, \
+                         check both directions use the same units",
+                        ratio * 100.0
+                    ),
+                );
+            }
+        }
+
+        report.stats = Some(MotorDataStats {
+            rows: records.len(),
+            pwm_range: (records[0].pwm, records[records.len() - 1].pwm),
+            thrust_gap,
+            dead_band,
+            peak_forward,
+            peak_reverse,
+            mean_asymmetry: mean_asymmetry(&records),
+            peak_current: records
+                .iter()
+                .map(|it| it.current)
+                .fold(0.0, FloatType::max),
+            efficiency_outliers,
+        });
+        report
+            .issues
+            .sort_by_key(|it| (std::cmp::Reverse(it.severity), it.location.clone()));
+
+        report
+    }
+
+    pub fn has_errors(&self) -> bool {
+        self.errors().next().is_some()
+    }
+
+    pub fn errors(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|it| it.severity == Severity::Error)
+    }
+
+    pub fn warnings(&self) -> impl Iterator<Item = &Issue> {
+        self.issues
+            .iter()
+            .filter(|it| it.severity == Severity::Warning)
+    }
+
+    /// Fails with every error in the report
+    pub fn ensure_valid(&self) -> anyhow::Result<()> {
+        if self.has_errors() {
+            anyhow::bail!("Invalid motor data\n{}", self.errors().join("\n"));
+        }
+
+        Ok(())
+    }
+
+    fn push(&mut self, kind: IssueKind, location: Option<Location>, message: impl Into<String>) {
+        self.issues.push(Issue {
+            severity: kind.severity(),
+            kind,
+            location,
+            message: message.into(),
+        });
+    }
+
+    fn parse_table(
+        &mut self,
+        table: usize,
+        name: &str,
+        contents: &str,
+        force_scale: FloatType,
+        rows: &mut Vec<Row>,
+    ) {
+        let location = |line: u64| {
+            Some(Location {
+                table: name.to_owned(),
+                line,
+            })
+        };
+
+        let mut reader = csv::Reader::from_reader(contents.as_bytes());
+        let headers = match reader.headers() {
+            Ok(headers) => headers.clone(),
+            Err(err) => {
+                self.push(
+                    IssueKind::Unreadable,
+                    location(1),
+                    format!("Unreadable header: {err}"),
+                );
+                return;
+            }
+        };
+        let columns = COLUMNS.map(|column| headers.iter().position(|it| it.trim() == column));
+        let missing = COLUMNS
+            .iter()
+            .zip(&columns)
+            .filter(|(_, idx)| idx.is_none())
+            .map(|(column, _)| column)
+            .collect_vec();
+        if !missing.is_empty() {
+            self.push(
+                IssueKind::MissingColumns,
+                location(1),
+                format!("Missing columns {}", missing.iter().join(", ")),
+            );
+            return;
+        }
+
+        let mut previous_pwm = None;
+        for record in reader.records() {
+            let record = match record {
+                Ok(record) => record,
+                Err(err) => {
+                    let line = err.position().map_or(0, |it| it.line());
+                    self.push(
+                        IssueKind::Unreadable,
+                        location(line),
+                        format!("Unreadable row: {err}"),
+                    );
+                    continue;
+                }
+            };
+            let line = record.position().map_or(0, |it| it.line());
+
+            let mut values = [0.0; COLUMNS.len()];
+            let mut valid = true;
+            for ((value, column), idx) in values.iter_mut().zip(COLUMNS).zip(columns) {
+                let field = record.get(idx.unwrap_or_default()).unwrap_or("").trim();
+                match field.parse::<FloatType>() {
+                    Ok(parsed) if parsed.is_finite() => *value = parsed,
+                    _ => {
+                        self.push(
+                            IssueKind::InvalidValue,
+                            location(line),
+                            format!("{column} {field:?} is not a finite number"),
+                        );
+                        valid = false;
+                    }
+                }
+            }
+            if !valid {
+                continue;
+            }
+
+            let [pwm, rpm, current, voltage, power, force, efficiency] = values;
+            if let Some(previous) = previous_pwm.filter(|&it| pwm < it) {
+                self.push(
+                    IssueKind::Unsorted,
+                    location(line),
+                    format!("PWM {pwm} comes after {previous}, rows are sorted when loaded"),
+                );
+            }
+            previous_pwm = Some(pwm);
+
+            rows.push(Row {
+                location: Location {
+                    table: name.to_owned(),
+                    line,
+                },
+                table,
+                record: CurveRow {
+                    pwm,
+                    rpm,
+                    current,
+                    voltage,
+                    power,
+                    force: force * force_scale,
+                    efficiency,
+                },
+            });
+        }
+    }
+
+    /// Drops rows with the PWM of an earlier row. Repeats of a row within a table are warned
+    /// about, across tables they're the neutral row both directions share
+    fn dedup(&mut self, rows: Vec<Row>) -> Vec<Row> {
+        let mut kept: Vec<Row> = vec![];
+
+        for row in rows {
+            let Some(first) = kept.last().filter(|it| it.record.pwm == row.record.pwm) else {
+                kept.push(row);
+                continue;
+            };
+
+            if first.record != row.record {
+                let message = format!(
+                    "PWM {} differs from line {} of {}, only the first row is used",
+                    row.record.pwm, first.location.line, first.location.table
+                );
+                self.push(IssueKind::ConflictingRows, Some(row.location), message);
+            } else if first.table == row.table {
+                let message = format!("Repeats line {}", first.location.line);
+                self.push(IssueKind::RepeatedRow, Some(row.location), message);
+            }
+        }
+
+        kept
+    }
+
+    fn check_values(&mut self, rows: &[Row]) {
+        for row in rows {
+            let CurveRow {
+                pwm,
+                current,
+                power,
+                force,
+                ..
+            } = row.record;
+            let location = Some(row.location.clone());
+
+            if current < 0.0 || power < 0.0 {
+                self.push(
+                    IssueKind::NegativeCurrent,
+                    location.clone(),
+                    format!("Negative current {current} A or power {power} W"),
+                );
+            }
+            if pwm < NEUTRAL_PWM && force > 0.0 {
+                self.push(
+                    IssueKind::WrongDirection,
+                    location,
+                    format!("Forward force {force:.3} N below the {NEUTRAL_PWM} neutral"),
+                );
+            } else if pwm > NEUTRAL_PWM && force < 0.0 {
+                self.push(
+                    IssueKind::WrongDirection,
+                    location,
+                    format!("Reverse force {force:.3} N above the {NEUTRAL_PWM} neutral"),
+                );
+            }
+        }
+
+        if rows.iter().all(|it| it.record.force <= 0.0) {
+            self.push(
+                IssueKind::MissingDirection,
+                None,
+                "No row has forward thrust",
+            );
+        }
+        if rows.iter().all(|it| it.record.force >= 0.0) {
+            self.push(
+                IssueKind::MissingDirection,
+                None,
+                "No row has reverse thrust",
+            );
+        }
+    }
+
+    /// Warns where force falls as PWM rises, the thrust for a force is then ambiguous
+    fn check_monotonic(&mut self, rows: &[Row]) {
+        let tolerance = rows
+            .iter()
+            .map(|it| it.record.force.abs())
+            .fold(0.0, FloatType::max)
+            * MONOTONIC_TOLERANCE;
+
+        for (low, high) in rows.iter().tuple_windows() {
+            if high.record.force < low.record.force - tolerance {
+                let message = format!(
+                    "Force falls from {:.3} N at PWM {} (line {}) to {:.3} N at PWM {}",
+                    low.record.force,
+                    low.record.pwm,
+                    low.location.line,
+                    high.record.force,
+                    high.record.pwm
+                );
+                self.push(
+                    IssueKind::NonMonotonic,
+                    Some(high.location.clone()),
+                    message,
+                );
+            }
+        }
+    }
+
+    /// The thrust gap and dead band around neutral
+    #[allow(clippy::type_complexity)]
+    fn check_neutral(
+        &mut self,
+        rows: &[Row],
+    ) -> (
+        Option<(FloatType, FloatType)>,
+        Option<(FloatType, FloatType)>,
+    ) {
+        let last_reverse = rows
+            .iter()
+            .rev()
+            .find(|it| it.record.pwm <= NEUTRAL_PWM && it.record.force < 0.0)
+            .map(|it| it.record.pwm);
+        let first_forward = rows
+            .iter()
+            .find(|it| it.record.pwm >= NEUTRAL_PWM && it.record.force > 0.0)
+            .map(|it| it.record.pwm);
+        let (Some(low), Some(high)) = (last_reverse, first_forward) else {
+            return (None, None);
+        };
+
+        let still = rows
+            .iter()
+            .filter(|it| it.record.pwm > low && it.record.pwm < high && it.record.force == 0.0)
+            .map(|it| it.record.pwm)
+            .collect_vec();
+        let dead_band = still.first().zip(still.last()).map(|(a, b)| (*a, *b));
+
+        match dead_band {
+            None => self.push(
+                IssueKind::DeadBand,
+                None,
+                format!(
+                    "No row without thrust between PWM {low} and {high}, the thruster may \
+                     not stop at neutral"
+                ),
+            ),
+            Some((start, end)) if start > NEUTRAL_PWM || end < NEUTRAL_PWM => self.push(
+                IssueKind::DeadBand,
+                None,
+                format!("The dead band from PWM {start} to {end} doesn't contain {NEUTRAL_PWM}"),
+            ),
+            Some(_) => {}
+        }
+
+        (Some((low, high)), dead_band)
+    }
+
+    /// Warns about rows whose efficiency strays from their neighbours, returns how many
+    fn check_efficiency(&mut self, rows: &[Row]) -> usize {
+        let peak_current = rows
+            .iter()
+            .map(|it| it.record.current)
+            .fold(0.0, FloatType::max);
+        let mut outliers = 0;
+
+        for (idx, row) in rows.iter().enumerate() {
+            if row.record.current < peak_current * EFFICIENCY_MIN_CURRENT {
+                continue;
+            }
+
+            let neighbours = rows[idx.saturating_sub(EFFICIENCY_NEIGHBOURS)..]
+                .iter()
+                .take(EFFICIENCY_NEIGHBOURS * 2 + 1)
+                .filter(|it| !std::ptr::eq(*it, row))
+                .map(|it| it.record.efficiency)
+                .sorted_by(FloatType::total_cmp)
+                .collect_vec();
+            let median = match neighbours.len() {
+                0 => continue,
+                len if len % 2 == 0 => (neighbours[len / 2 - 1] + neighbours[len / 2]) / 2.0,
+                len => neighbours[len / 2],
+            };
+            if median <= 0.0 {
+                continue;
+            }
+
+            let deviation = (row.record.efficiency - median) / median;
+            if deviation.abs() > EFFICIENCY_TOLERANCE {
+                outliers += 1;
+                self.push(
+                    IssueKind::EfficiencyOutlier,
+                    Some(row.location.clone()),
+                    format!(
+                        "Efficiency {} is {:+.0}% off the {median:.1} of its neighbours",
+                        row.record.efficiency,
+                        deviation * 100.0
+                    ),
+                );
+            }
+        }
+
+        outliers
+    }
+}
+
+/// The mean of reverse over forward force at the PWMs of the forward rows mirrored around
+/// neutral, skipping forces too small to compare
+fn mean_asymmetry(records: &[CurveRow]) -> Option<FloatType> {
+    let peak_forward = records.iter().map(|it| it.force).fold(0.0, FloatType::max);
+    let (first, last) = (records[0].pwm, records[records.len() - 1].pwm);
+
+    let ratios = records
+        .iter()
+        .filter(|it| it.force > peak_forward * ASYMMETRY_MIN_FORCE)
+        .map(|it| (NEUTRAL_PWM * 2.0 - it.pwm, it.force))
+        .filter(|(mirrored, _)| *mirrored >= first && *mirrored <= last)
+        .map(|(mirrored, forward)| -force_at(records, mirrored) / forward)
+        .collect_vec();
+
+    (!ratios.is_empty()).then(|| ratios.iter().sum::<FloatType>() / ratios.len() as FloatType)
+}
+
+/// The force at `pwm`, interpolated between the closest of the sorted `records`
+fn force_at(records: &[CurveRow], pwm: FloatType) -> FloatType {
+    let above = records
+        .partition_point(|it| it.pwm < pwm)
+        .clamp(1, records.len() - 1);
+    let (low, high) = (&records[above - 1], &records[above]);
+
+    low.force + (high.force - low.force) * (pwm - low.pwm) / (high.pwm - low.pwm)
+}
+
+impl Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+impl Display for Issue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(Location { table, line }) => {
+                write!(f, "{table}:{line}: {}: {}", self.severity, self.message)
+            }
+            None => write!(f, "{}: {}", self.severity, self.message),
+        }
+    }
+}
+
+impl Display for MotorDataReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for issue in &self.issues {
+            writeln!(f, "{issue}")?;
+        }
+
+        let Some(stats) = &self.stats else {
+            return Ok(());
+        };
+        let (first, last) = stats.pwm_range;
+        writeln!(f, "{} rows from PWM {first} to {last}", stats.rows)?;
+        if let Some((low, high)) = stats.thrust_gap {
+            write!(f, "No thrust between PWM {low} and {high}")?;
+            match stats.dead_band {
+                Some((start, end)) => writeln!(f, ", dead band from {start} to {end}")?,
+                None => writeln!(f)?,
+            }
+        }
+        writeln!(
+            f,
+            "Peak force {:.2} N forward, {:.2} N reverse, {:.2} A peak current",
+            stats.peak_forward, stats.peak_reverse, stats.peak_current
+        )?;
+        if stats.peak_forward > 0.0 {
+            write!(
+                f,
+                "Reverse thrust is {:.0}% of forward at full throttle",
+                stats.peak_reverse / stats.peak_forward * 100.0
+            )?;
+            match stats.mean_asymmetry {
+                Some(mean) => writeln!(f, ", {:.0}% on average", mean * 100.0)?,
+                None => writeln!(f)?,
+            }
+        }
+        writeln!(
+            f,
+            "{} errors, {} warnings, {} efficiency outliers",
+            self.errors().count(),
+            self.warnings().count(),
+            stats.efficiency_outliers
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{IssueKind, Location, MotorDataReport};
+
+    /// A table with a dead band around neutral, even efficiency and similar peak forces
+    const CLEAN: &str = "\
+pwm,rpm,current,voltage,power,force,efficiency
+1100,3000,10,12,120,-20,10
+1200,2000,5,12,60,-12,10
+1300,1000,2,12,24,-5,10
+1460,0,0,12,0,0,10
+1500,0,0,12,0,0,10
+1540,0,0,12,0,0,10
+1700,1000,2,12,24,6,10
+1800,2000,5,12,60,14,10
+1900,3000,10,12,120,24,10
+";
+
+    fn validate(contents: &str) -> MotorDataReport {
+        MotorDataReport::validate("test.csv", contents, 1.0)
+    }
+
+    /// The kinds and lines of the report's issues, in the order they're reported
+    fn issues(report: &MotorDataReport) -> Vec<(IssueKind, Option<u64>)> {
+        report
+            .issues
+            .iter()
+            .map(|it| (it.kind, it.location.as_ref().map(|it| it.line)))
+            .collect()
+    }
+
+    #[test]
+    fn clean_tables_have_no_issues() {
+        let report = validate(CLEAN);
+
+        assert_eq!(report.issues, vec![], "{report}");
+        let stats = report.stats.unwrap();
+        assert_eq!(stats.rows, 9);
+        assert_eq!(stats.thrust_gap, Some((1300.0, 1700.0)));
+        assert_eq!(stats.dead_band, Some((1460.0, 1540.0)));
+        assert_eq!(stats.efficiency_outliers, 0);
+    }
+
+    #[test]
+    fn missing_columns_are_reported_on_the_header() {
+        let report = validate(&CLEAN.replace("pwm,rpm,", "pwm,"));
+
+        let issue = report
+            .issues
+            .iter()
+            .find(|it| it.kind == IssueKind::MissingColumns)
+            .unwrap();
+        assert_eq!(
+            issue.location,
+            Some(Location {
+                table: "test.csv".to_owned(),
+                line: 1,
+            })
+        );
+        assert!(issue.message.contains("rpm"), "{issue}");
+        assert!(report.has_errors());
+    }
+
+    #[test]
+    fn unsorted_pwms_are_reported_on_the_later_row() {
+        let contents = CLEAN.replace(
+            "1700,1000,2,12,24,6,10\n1800,2000,5,12,60,14,10",
+            "1800,2000,5,12,60,14,10\n1700,1000,2,12,24,6,10",
+        );
+        let report = validate(&contents);
+
+        // Rows are sorted when loaded, so nothing else changes
+        assert_eq!(
+            issues(&report),
+            [(IssueKind::Unsorted, Some(9))],
+            "{report}"
+        );
+        assert!(!report.has_errors());
+    }
+
+    #[test]
+    fn falling_force_is_reported_on_the_weaker_row() {
+        let report = validate(&CLEAN.replace("1800,2000,5,12,60,14,", "1800,2000,5,12,60,4,"));
+
+        assert_eq!(
+            issues(&report),
+            [(IssueKind::NonMonotonic, Some(9))],
+            "{report}"
+        );
+    }
+
+    #[test]
+    fn dead_bands_must_contain_neutral() {
+        let without = CLEAN
+            .lines()
+            .filter(|it| !it.contains(",0,10"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let report = validate(&without);
+        assert_eq!(issues(&report), [(IssueKind::DeadBand, None)], "{report}");
+        assert_eq!(report.stats.unwrap().dead_band, None);
+
+        let off_neutral = CLEAN
+            .replace("1460,0,0,12,0,0,", "1500,500,1,12,12,-1,")
+            .replace("1500,0,0,12,0,0,", "1560,0,0,12,0,0,")
+            .replace("1540,0,0,12,0,0,", "1600,0,0,12,0,0,");
+        let report = validate(&off_neutral);
+        assert_eq!(issues(&report), [(IssueKind::DeadBand, None)], "{report}");
+        assert_eq!(report.stats.unwrap().dead_band, Some((1560.0, 1600.0)));
+    }
+
+    #[test]
+    fn lopsided_directions_are_reported() {
+        let contents = CLEAN
+            .replace(",-20,", ",-200,")
+            .replace(",-12,", ",-120,")
+            .replace(",-5,", ",-50,");
+        let report = validate(&contents);
+
+        assert_eq!(issues(&report), [(IssueKind::Asymmetric, None)], "{report}");
+        assert!(report.issues[0].message.contains("833%"), "{report}");
+    }
+
+    #[test]
+    fn efficiency_outliers_are_reported_on_their_row() {
+        let report =
+            validate(&CLEAN.replace("1200,2000,5,12,60,-12,10", "1200,2000,5,12,60,-12,20"));
+
+        assert_eq!(
+            issues(&report),
+            [(IssueKind::EfficiencyOutlier, Some(3))],
+            "{report}"
+        );
+        assert_eq!(report.stats.unwrap().efficiency_outliers, 1);
+    }
+}
@@ -1,13 +1,41 @@
+use anyhow::{ensure, Context};
 use itertools::Itertools;
-use motor_math::{
-    motor_preformance::MotorData, solve::reverse, ErasedMotorId, FloatType, MotorConfig, Number,
+use motor_math::{ErasedMotorId, FloatType, MotorConfig, Number};
+use nalgebra::{
+    allocator::Allocator, vector, Const, DMatrix, DVector, DefaultAllocator, Dim, OMatrix, SMatrix,
+    SVector, Vector3,
 };
-use nalgebra::{vector, Const, DMatrix, SMatrix, Vector3};
 use num_dual::{gradient, DualVec};
-use rayon::iter::{IntoParallelRefMutIterator, ParallelIterator};
-use std::{fmt::Debug, hash::Hash, iter};
-
-use crate::heuristic::{score, Scaled, ScoreResult, ScoreSettings, Unscaled};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use rayon::{
+    iter::{IntoParallelRefMutIterator, ParallelIterator},
+    ThreadPool, ThreadPoolBuilder,
+};
+use serde::{Deserialize, Serialize};
+use std::{cmp::Ordering, fmt::Debug, hash::Hash, iter, path::Path, str::FromStr};
+
+use crate::{
+    constraints::PositionConstraints,
+    format::{self, non_finite, FilePaths},
+    heuristic::{score, Scaled, ScoreResult, ScoreSettings, Unscaled},
+    layout::SpinDirection,
+    physics,
+    thruster_models::ThrusterModels,
+    HEIGHT, LENGTH, WIDTH,
+};
+use dynamic::{DynamicOptimization, Plane, Symmetry};
+use full::FullOptimization;
+use mounted::MountingPlate;
+use optimizers::{Convergence, Objective, Optimizer, OptimizerState, OptimizerType};
+use schedule::Hyperparameters;
+use symetrical::SymerticalOptimization;
+use x3d_dyn::DynamicX3dOptimization;
+use x3d_fixed::FixedX3dOptimization;
+
+pub mod optimizers;
+pub mod schedule;
+pub mod thruster_count;
 
 pub fn fibonacci_sphere(samples: usize) -> impl Iterator<Item = Vector3<FloatType>> {
     iter::from_coroutine(
@@ -30,153 +58,255 @@ pub fn fibonacci_sphere(samples: usize) -> impl Iterator<Item = Vector3<FloatTyp
     )
 }
 
+/// Scores `motor_config`, with `motor_data` already resolved to its thrusters
 pub fn evaluate<MotorId: Debug + Ord + Hash + Clone, D: Number>(
     motor_config: &MotorConfig<MotorId, D>,
     settings: &ScoreSettings,
-    motor_data: &MotorData,
+    motor_data: &ThrusterModels,
 ) -> (D, ScoreResult<D, Unscaled>) {
-    // TODO: Use 25 amps / make consistant with other calls
-    let result = reverse::axis_maximums(motor_config, motor_data, 25.0, 0.001);
-    score(&result, motor_config, settings)
-}
-
-// Adam without weight decay
-pub fn adam_optimizer<const DIM1: usize, const DIM2: usize, Config>(
-    old_point: &OptimizationState<Config::Point<FloatType>>,
-    config: &Config,
-    heuristic: &ScoreSettings,
-    motor_data: &MotorData,
-    step_size: FloatType,
-    frontier_ratio_threshold: FloatType,
-) -> Ascent<DIM1, DIM2>
-where
-    // This feels wrong
-    Config: OptimizableConfig<Point<FloatType> = SMatrix<FloatType, DIM1, DIM2>>
-        + OptimizableConfig<
-            Point<DualVec<FloatType, FloatType, Const<DIM1>, Const<DIM2>>> = SMatrix<
-                DualVec<FloatType, FloatType, Const<DIM1>, Const<DIM2>>,
-                DIM1,
-                DIM2,
-            >,
-        > + 'static,
-{
-    let old_point = old_point.clone();
-
-    let mut result = None;
-
-    let (score, grad) = gradient(
-        |point| {
-            let motor_config = config.motor_config(point);
-            let (score, score_breakdown) = evaluate(&motor_config, heuristic, motor_data);
-            result = Some(score_breakdown);
-
-            score
-        },
-        old_point.point,
-    );
+    let motor_config = physics::with_vehicle(motor_config.clone(), &settings.geometry);
 
-    let grad = Config::Point::<FloatType>::from_column_slice(grad.as_slice());
-
-    let beta_1 = 0.9;
-    let beta_2 = 0.999;
-    let epsilon = 1e-10;
-
-    let new_time = old_point.time + 1;
-    let new_first_moment = beta_1 * old_point.first_moment + (1.0 - beta_1) * grad;
-    let new_second_moment =
-        beta_2 * old_point.second_moment + (1.0 - beta_2) * grad.component_mul(&grad);
-
-    let first_moment_hat = new_first_moment / (1.0 - beta_1.powi(new_time));
-    let second_moment_hat = new_second_moment / (1.0 - beta_2.powi(new_time));
+    let result = settings.power.axis_maximums(&motor_config, motor_data);
+    score(&result, &motor_config, settings)
+}
 
-    let new_point = old_point.point
-        + step_size
-            * first_moment_hat
-                .component_div(&second_moment_hat.map(|it| it.sqrt()).add_scalar(epsilon));
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OptimizationState<Point, State> {
+    pub point: Point,
+    /// The optimizer specific state of the point
+    pub optimizer: State,
+    /// Spin directions of the point's thrusters in `motors()` order, empty while they all spin
+    /// clockwise
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub directions: Vec<SpinDirection>,
+
+    #[serde(with = "non_finite::pair")]
+    pub frontier_threshold: (FloatType, i32),
+    pub time: i32,
+    pub done: bool,
+}
 
-    let mut frontier_threshold = old_point.frontier_threshold;
-    if (frontier_threshold.0 * frontier_ratio_threshold).abs() < score.abs() {
-        frontier_threshold = (score, new_time);
+impl<Point, State> OptimizationState<Point, State> {
+    pub fn new(point: Point, optimizer: State) -> Self {
+        Self {
+            point,
+            optimizer,
+            directions: vec![],
+            time: 0,
+            frontier_threshold: (FloatType::NEG_INFINITY, 0),
+            done: false,
+        }
     }
 
-    let new_point = OptimizationState {
-        point: config.normalise_point::<FloatType>(new_point),
-        first_moment: new_first_moment,
-        second_moment: new_second_moment,
-        time: new_time,
-        frontier_threshold,
-        ..old_point
-    };
-
-    let delta = new_point.point - old_point.point;
+    /// Moves to the next point of the optimization and checks if it has converged
+    pub fn advance(
+        &mut self,
+        point: Point,
+        score: FloatType,
+        convergence: Convergence,
+        criteria: &ConvergenceCriteria,
+    ) {
+        self.point = point;
+        self.time += 1;
+
+        let (frontier_score, _) = self.frontier_threshold;
+        if frontier_score == FloatType::NEG_INFINITY
+            || score - frontier_score
+                > (criteria.frontier_ratio_threshold - 1.0) * frontier_score.abs()
+        {
+            self.frontier_threshold = (score, self.time);
+        }
 
-    Ascent {
-        old_point,
-        new_point,
-        old_score: score,
-        est_new_score: score + grad.dot(&delta),
-        gradient: grad,
-        score_breakdown: result.unwrap(),
+        self.update_done(convergence, criteria);
     }
-}
 
-#[derive(Debug, Clone)]
-pub struct Ascent<const DIM1: usize, const DIM2: usize> {
-    pub old_point: OptimizationState<SMatrix<FloatType, DIM1, DIM2>>,
-    pub new_point: OptimizationState<SMatrix<FloatType, DIM1, DIM2>>,
+    /// Marks the point as done once any of the convergence criteria are met
+    pub fn update_done(&mut self, convergence: Convergence, criteria: &ConvergenceCriteria) {
+        self.done = self.time >= criteria.max_iterations
+            || convergence.is_converged(criteria.gradient_tolerance)
+            || self.time - self.frontier_threshold.1 > criteria.frontier_time_limit;
+    }
 
-    pub old_score: FloatType,
-    pub est_new_score: FloatType,
+    /// Makes a done point active again, used when the score it converged on is no longer valid
+    pub fn reactivate(&mut self) {
+        self.done = false;
+        self.frontier_threshold = (FloatType::NEG_INFINITY, self.time);
+    }
 
-    pub gradient: SMatrix<FloatType, DIM1, DIM2>,
-    pub score_breakdown:
-        ScoreResult<DualVec<FloatType, FloatType, Const<DIM1>, Const<DIM2>>, Unscaled>,
+    pub fn try_map<OtherPoint, OtherState>(
+        self,
+        map_point: impl FnOnce(Point) -> anyhow::Result<OtherPoint>,
+        map_state: impl FnOnce(State) -> anyhow::Result<OtherState>,
+    ) -> anyhow::Result<OptimizationState<OtherPoint, OtherState>> {
+        Ok(OptimizationState {
+            point: map_point(self.point)?,
+            optimizer: map_state(self.optimizer)?,
+            directions: self.directions,
+            frontier_threshold: self.frontier_threshold,
+            time: self.time,
+            done: self.done,
+        })
+    }
 }
 
-#[derive(Debug, Clone)]
-pub struct OptimizationState<Point> {
-    pub point: Point,
-
-    pub first_moment: Point,
-    pub second_moment: Point,
-
-    pub frontier_threshold: (FloatType, i32),
-    pub time: i32,
-    pub done: bool,
+/// When a point is considered converged and stops being stepped
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ConvergenceCriteria {
+    /// The ratio by which a points score must improve to be considered an improvement
+    pub frontier_ratio_threshold: FloatType,
+    /// The number of time steps a point must not improve for it to be considered done
+    pub frontier_time_limit: i32,
+    /// The gradient norm below which a point is considered done, gradient free optimizers
+    /// have their own tolerances
+    pub gradient_tolerance: FloatType,
+    /// The maximum number of time steps a point is optimized for
+    pub max_iterations: i32,
 }
 
-impl<const DIM1: usize, const DIM2: usize> OptimizationState<SMatrix<FloatType, DIM1, DIM2>> {
-    pub fn new(point: SMatrix<FloatType, DIM1, DIM2>) -> Self {
+impl Default for ConvergenceCriteria {
+    fn default() -> Self {
         Self {
-            point,
-            first_moment: SMatrix::zeros(),
-            second_moment: SMatrix::zeros(),
-            time: 0,
-            frontier_threshold: (FloatType::NEG_INFINITY, 0),
-            done: false,
+            frontier_ratio_threshold: 1.01,
+            frontier_time_limit: 25,
+            gradient_tolerance: 1e-6,
+            max_iterations: 10_000,
         }
     }
 }
 
 pub trait OptimizableConfig {
-    const DIMENSIONALITY: usize;
-
     type MotorId: Ord + Debug + Clone + Hash;
     type Point<D: Number>;
 
+    /// The number of rows and columns of a point's parameter matrix
+    fn parameter_shape(&self) -> (usize, usize);
+    /// What the parameters mean, checkpoints only restore into an arena of the same kind
+    fn arena_kind(&self) -> ArenaKind;
     fn initial_points(
         &self,
         count: usize,
-    ) -> impl Iterator<Item = OptimizationState<Self::Point<FloatType>>>;
+        rng: &mut impl Rng,
+    ) -> impl Iterator<Item = Self::Point<FloatType>>;
     fn motor_config<D: Number>(&self, point: Self::Point<D>) -> MotorConfig<Self::MotorId, D>;
-    fn normalise_point<D: Number>(&self, point: Self::Point<D>) -> Self::Point<D>;
+    /// The slot, a column of a point's parameters, the thruster with `id` is made from.
+    /// Thrusters made from the same slot use the same thruster model
+    fn thruster_slot(&self, id: &Self::MotorId) -> usize;
+    /// Projects `point` back onto valid parameters with every thruster's position satisfying
+    /// `constraints`
+    fn normalise_point<D: Number>(
+        &self,
+        point: Self::Point<D>,
+        constraints: &PositionConstraints,
+    ) -> Self::Point<D>;
+    /// The score of `point` with the thrusters spinning in `directions` along with its gradient
+    fn score_gradient(
+        &self,
+        point: &Self::Point<FloatType>,
+        directions: &[SpinDirection],
+        heuristic: &ScoreSettings,
+        motor_data: &ThrusterModels,
+    ) -> (FloatType, Self::Point<FloatType>);
+
+    /// The number of leading rows of each column of a point the optimizers change, the rest only
+    /// change through `discrete_neighbours`
+    fn optimized_rows(&self) -> usize {
+        self.parameter_shape().0
+    }
+
+    /// Points reachable from `point` by a discrete change the gradient can't make, such as
+    /// moving a thruster to another mount. Searched every `discrete_interval` steps
+    fn discrete_neighbours(
+        &self,
+        _point: &Self::Point<FloatType>,
+        _constraints: &PositionConstraints,
+    ) -> Vec<Self::Point<FloatType>> {
+        vec![]
+    }
+}
+
+/// Forward mode gradient for configs whose points are fixed size matrices
+pub fn fixed_gradient<const DIM1: usize, const DIM2: usize, Config>(
+    config: &Config,
+    point: &SMatrix<FloatType, DIM1, DIM2>,
+    directions: &[SpinDirection],
+    heuristic: &ScoreSettings,
+    motor_data: &ThrusterModels,
+) -> (FloatType, SMatrix<FloatType, DIM1, DIM2>)
+where
+    Config: OptimizableConfig<
+        Point<DualVec<FloatType, FloatType, Const<DIM1>, Const<DIM2>>> = SMatrix<
+            DualVec<FloatType, FloatType, Const<DIM1>, Const<DIM2>>,
+            DIM1,
+            DIM2,
+        >,
+    >,
+{
+    let (score, grad) = gradient(
+        |point| {
+            let motor_config = physics::with_directions(config.motor_config(point), directions);
+            evaluate(&motor_config, heuristic, motor_data).0
+        },
+        *point,
+    );
+
+    (score, SMatrix::from_column_slice(grad.as_slice()))
+}
+
+type ColumnDual<const ROWS: usize> = DualVec<FloatType, FloatType, Const<ROWS>, Const<1>>;
+
+/// Forward mode gradient for configs whose points are dynamically sized matrices
+///
+/// Dual numbers need to be `Copy`, so rather than one dynamically sized dual we take one fixed
+/// width gradient over the first `ROWS` rows of each column with the rest of the point held
+/// constant. Any remaining rows get a gradient of zero
+pub fn column_gradient<const ROWS: usize, Config>(
+    config: &Config,
+    point: &DMatrix<FloatType>,
+    directions: &[SpinDirection],
+    heuristic: &ScoreSettings,
+    motor_data: &ThrusterModels,
+) -> (FloatType, DMatrix<FloatType>)
+where
+    Config: OptimizableConfig<Point<ColumnDual<ROWS>> = DMatrix<ColumnDual<ROWS>>>,
+{
+    let mut score = FloatType::NEG_INFINITY;
+    let mut grad = DMatrix::zeros(point.nrows(), point.ncols());
+
+    for column in 0..point.ncols() {
+        let (column_score, column_grad) = gradient(
+            |free: SVector<ColumnDual<ROWS>, ROWS>| {
+                let point = DMatrix::from_fn(point.nrows(), point.ncols(), |row, col| {
+                    if col == column && row < ROWS {
+                        free[row]
+                    } else {
+                        ColumnDual::<ROWS>::from(point[(row, col)])
+                    }
+                });
+                let motor_config = physics::with_directions(config.motor_config(point), directions);
+                evaluate(&motor_config, heuristic, motor_data).0
+            },
+            point.fixed_view::<ROWS, 1>(0, column).into_owned(),
+        );
+
+        score = column_score;
+        grad.fixed_view_mut::<ROWS, 1>(0, column)
+            .copy_from(&column_grad);
+    }
+
+    (score, grad)
 }
 
 pub mod x3d_fixed {
     use motor_math::{x3d::X3dMotorId, Direction, FloatType, Motor, MotorConfig, Number};
     use nalgebra::{vector, SVector};
+    use rand::Rng;
 
-    use super::{OptimizableConfig, OptimizationState};
+    use super::{ArenaKind, ArenaType, OptimizableConfig};
+    use crate::{
+        constraints::PositionConstraints, heuristic::ScoreSettings, layout::SpinDirection,
+        thruster_models::ThrusterModels,
+    };
 
     pub struct FixedX3dOptimization {
         pub width: FloatType,
@@ -185,16 +315,23 @@ pub mod x3d_fixed {
     }
 
     impl OptimizableConfig for FixedX3dOptimization {
-        const DIMENSIONALITY: usize = 3;
-
         type MotorId = X3dMotorId;
-        type Point<D: Number> = SVector<D, { Self::DIMENSIONALITY }>;
+        type Point<D: Number> = SVector<D, 3>;
+
+        fn parameter_shape(&self) -> (usize, usize) {
+            (3, 1)
+        }
+
+        fn arena_kind(&self) -> ArenaKind {
+            ArenaKind::Type(ArenaType::X3d)
+        }
 
         fn initial_points(
             &self,
             count: usize,
-        ) -> impl Iterator<Item = OptimizationState<Self::Point<FloatType>>> {
-            super::fibonacci_sphere(count).map(OptimizationState::new)
+            _rng: &mut impl Rng,
+        ) -> impl Iterator<Item = Self::Point<FloatType>> {
+            super::fibonacci_sphere(count)
         }
 
         fn motor_config<D: Number>(&self, point: Self::Point<D>) -> MotorConfig<Self::MotorId, D> {
@@ -208,37 +345,82 @@ pub mod x3d_fixed {
             )
         }
 
-        fn normalise_point<D: Number>(&self, point: Self::Point<D>) -> Self::Point<D> {
+        // Every thruster is a copy of the seed thruster
+        fn thruster_slot(&self, _id: &Self::MotorId) -> usize {
+            0
+        }
+
+        // The position of the thrusters is fixed, so there is nothing to constrain
+        fn normalise_point<D: Number>(
+            &self,
+            point: Self::Point<D>,
+            _constraints: &PositionConstraints,
+        ) -> Self::Point<D> {
             point.normalize()
         }
+
+        fn score_gradient(
+            &self,
+            point: &Self::Point<FloatType>,
+            directions: &[SpinDirection],
+            heuristic: &ScoreSettings,
+            motor_data: &ThrusterModels,
+        ) -> (FloatType, Self::Point<FloatType>) {
+            super::fixed_gradient(self, point, directions, heuristic, motor_data)
+        }
     }
 }
 
 pub mod x3d_dyn {
     use motor_math::{x3d::X3dMotorId, Direction, FloatType, Motor, MotorConfig, Number};
-    use nalgebra::{vector, Const, Matrix3x2, SVector, U1};
+    use nalgebra::{vector, Const, Matrix3x2, SVector, Vector3, U1};
+    use rand::Rng;
+
+    use super::{ArenaKind, ArenaType, OptimizableConfig};
+    use crate::{
+        constraints::{Envelope, PositionConstraints},
+        heuristic::ScoreSettings,
+        layout::SpinDirection,
+        thruster_models::ThrusterModels,
+    };
 
-    use super::{OptimizableConfig, OptimizationState};
+    /// An X3d config where both the orientation and position of the seed thruster are optimized
+    pub struct DynamicX3dOptimization {
+        /// Positions are kept within plus or minus these half extents of the frame
+        pub width: FloatType,
+        pub length: FloatType,
+        pub height: FloatType,
+    }
 
-    pub struct DynamicX3dOptimization;
+    impl DynamicX3dOptimization {
+        fn bounds(&self) -> Vector3<FloatType> {
+            vector![self.width, self.length, self.height]
+        }
+    }
 
     impl OptimizableConfig for DynamicX3dOptimization {
-        const DIMENSIONALITY: usize = 6;
-
         type MotorId = X3dMotorId;
-        type Point<D: Number> = SVector<D, { Self::DIMENSIONALITY }>;
+        type Point<D: Number> = SVector<D, 6>;
+
+        fn parameter_shape(&self) -> (usize, usize) {
+            (6, 1)
+        }
+
+        fn arena_kind(&self) -> ArenaKind {
+            ArenaKind::Type(ArenaType::X3dDynamic)
+        }
 
         fn initial_points(
             &self,
             count: usize,
-        ) -> impl Iterator<Item = OptimizationState<Self::Point<FloatType>>> {
-            super::fibonacci_sphere(count)
-                .map(|dir| {
-                    let pos = SVector::<FloatType, 3>::from_fn(|_, _| rand::random());
-                    Matrix3x2::from_columns(&[pos, dir])
-                        .reshape_generic(Const::<{ Self::DIMENSIONALITY }>, U1)
-                })
-                .map(OptimizationState::new)
+            rng: &mut impl Rng,
+        ) -> impl Iterator<Item = Self::Point<FloatType>> {
+            let bounds = self.bounds();
+
+            super::fibonacci_sphere(count).map(move |dir| {
+                let pos = SVector::<FloatType, 3>::from_fn(|_, _| rng.gen()).component_mul(&bounds);
+                Matrix3x2::from_columns(&[pos, dir]).reshape_generic(Const::<6>, U1)
+            })
         }
 
         fn motor_config<D: Number>(&self, point: Self::Point<D>) -> MotorConfig<Self::MotorId, D> {
@@ -252,8 +434,53 @@ pub mod x3d_dyn {
             )
         }
 
-        fn normalise_point<D: Number>(&self, point: Self::Point<D>) -> Self::Point<D> {
-            point.normalize()
+        fn thruster_slot(&self, _id: &Self::MotorId) -> usize {
+            0
+        }
+
+        fn normalise_point<D: Number>(
+            &self,
+            mut point: Self::Point<D>,
+            constraints: &PositionConstraints,
+        ) -> Self::Point<D> {
+            // The frame is applied with the constraints, clamping to it afterwards could put
+            // the thrusters back inside a keep out volume
+            let bounds = self.bounds();
+            let constraints = constraints.within(Envelope {
+                min: (-bounds).into(),
+                max: bounds.into(),
+            });
+
+            // Each of the 8 thrusters flips the sign of some of the seed thruster's axes
+            let mirror = |image: usize, position: Vector3<D>| {
+                Vector3::from_fn(|axis, _| {
+                    if image & (1 << axis) == 0 {
+                        position[axis]
+                    } else {
+                        -position[axis]
+                    }
+                })
+            };
+            let position = constraints.constrain_images(
+                point.fixed_rows::<3>(0).into_owned(),
+                8,
+                mirror,
+                mirror,
+            );
+            point.fixed_rows_mut::<3>(0).copy_from(&position);
+            point.fixed_rows_mut::<3>(3).normalize_mut();
+
+            point
+        }
+
+        fn score_gradient(
+            &self,
+            point: &Self::Point<FloatType>,
+            directions: &[SpinDirection],
+            heuristic: &ScoreSettings,
+            motor_data: &ThrusterModels,
+        ) -> (FloatType, Self::Point<FloatType>) {
+            super::fixed_gradient(self, point, directions, heuristic, motor_data)
         }
     }
 }
@@ -263,26 +490,42 @@ pub mod symetrical {
         utils::VectorTransform, Direction, ErasedMotorId, FloatType, Motor, MotorConfig, Number,
     };
     use nalgebra::{vector, SMatrix};
+    use rand::Rng;
 
-    use super::{OptimizableConfig, OptimizationState};
+    use super::{
+        dynamic::{Plane, Symmetry},
+        ArenaKind, ArenaType, OptimizableConfig,
+    };
+    use crate::{
+        constraints::PositionConstraints, heuristic::ScoreSettings, layout::SpinDirection,
+        thruster_models::ThrusterModels,
+    };
 
     pub struct SymerticalOptimization<const HALF_THRUSTER_COUNT: usize>;
 
     impl<const HALF_THRUSTER_COUNT: usize> OptimizableConfig
         for SymerticalOptimization<{ HALF_THRUSTER_COUNT }>
     {
-        const DIMENSIONALITY: usize = HALF_THRUSTER_COUNT * 6;
-
         type MotorId = ErasedMotorId;
         type Point<D: Number> = SMatrix<D, 6, { HALF_THRUSTER_COUNT }>;
 
+        fn parameter_shape(&self) -> (usize, usize) {
+            (6, HALF_THRUSTER_COUNT)
+        }
+
+        fn arena_kind(&self) -> ArenaKind {
+            ArenaKind::Type(ArenaType::Symmetric {
+                symmetry: Symmetry::Mirror(Plane::YZ),
+                thrusters: HALF_THRUSTER_COUNT * 2,
+            })
+        }
+
         fn initial_points(
             &self,
             count: usize,
-        ) -> impl Iterator<Item = OptimizationState<Self::Point<FloatType>>> {
-            (0..count)
-                .map(|_| Self::Point::<FloatType>::from_fn(|_, _| rand::random()))
-                .map(OptimizationState::new)
+            rng: &mut impl Rng,
+        ) -> impl Iterator<Item = Self::Point<FloatType>> {
+            (0..count).map(|_| Self::Point::<FloatType>::from_fn(|_, _| rng.gen()))
         }
 
         fn motor_config<D: Number>(&self, point: Self::Point<D>) -> MotorConfig<Self::MotorId, D> {
@@ -315,13 +558,46 @@ pub mod symetrical {
             )
         }
 
-        fn normalise_point<D: Number>(&self, mut point: Self::Point<D>) -> Self::Point<D> {
+        fn thruster_slot(&self, id: &Self::MotorId) -> usize {
+            *id as usize % HALF_THRUSTER_COUNT
+        }
+
+        fn normalise_point<D: Number>(
+            &self,
+            mut point: Self::Point<D>,
+            constraints: &PositionConstraints,
+        ) -> Self::Point<D> {
+            let mirror = |image: usize, position| {
+                if image == 0 {
+                    position
+                } else {
+                    VectorTransform::ReflectYZ.transform(position)
+                }
+            };
+
             for idx in 0..HALF_THRUSTER_COUNT {
+                let position = constraints.constrain_images(
+                    point.fixed_view::<3, 1>(0, idx).into_owned(),
+                    2,
+                    mirror,
+                    mirror,
+                );
+                point.fixed_view_mut::<3, 1>(0, idx).copy_from(&position);
                 point.fixed_view_mut::<3, 1>(3, idx).normalize_mut();
             }
 
             point
         }
+
+        fn score_gradient(
+            &self,
+            point: &Self::Point<FloatType>,
+            directions: &[SpinDirection],
+            heuristic: &ScoreSettings,
+            motor_data: &ThrusterModels,
+        ) -> (FloatType, Self::Point<FloatType>) {
+            super::fixed_gradient(self, point, directions, heuristic, motor_data)
+        }
     }
 }
 
@@ -330,24 +606,37 @@ pub mod full {
         utils::VectorTransform, Direction, ErasedMotorId, FloatType, Motor, MotorConfig, Number,
     };
     use nalgebra::{vector, SMatrix};
+    use rand::Rng;
 
-    use super::{OptimizableConfig, OptimizationState};
+    use super::{dynamic::Symmetry, ArenaKind, ArenaType, OptimizableConfig};
+    use crate::{
+        constraints::PositionConstraints, heuristic::ScoreSettings, layout::SpinDirection,
+        thruster_models::ThrusterModels,
+    };
 
     pub struct FullOptimization<const THRUSTER_COUNT: usize>;
 
     impl<const THRUSTER_COUNT: usize> OptimizableConfig for FullOptimization<{ THRUSTER_COUNT }> {
-        const DIMENSIONALITY: usize = THRUSTER_COUNT * 6;
-
         type MotorId = ErasedMotorId;
         type Point<D: Number> = SMatrix<D, 6, { THRUSTER_COUNT }>;
 
+        fn parameter_shape(&self) -> (usize, usize) {
+            (6, THRUSTER_COUNT)
+        }
+
+        fn arena_kind(&self) -> ArenaKind {
+            ArenaKind::Type(ArenaType::Symmetric {
+                symmetry: Symmetry::None,
+                thrusters: THRUSTER_COUNT,
+            })
+        }
+
         fn initial_points(
             &self,
             count: usize,
-        ) -> impl Iterator<Item = OptimizationState<Self::Point<FloatType>>> {
-            (0..count)
-                .map(|_| Self::Point::<FloatType>::from_fn(|_, _| rand::random()))
-                .map(OptimizationState::new)
+            rng: &mut impl Rng,
+        ) -> impl Iterator<Item = Self::Point<FloatType>> {
+            (0..count).map(|_| Self::Point::<FloatType>::from_fn(|_, _| rng.gen()))
         }
 
         fn motor_config<D: Number>(&self, point: Self::Point<D>) -> MotorConfig<Self::MotorId, D> {
@@ -380,250 +669,1296 @@ pub mod full {
             )
         }
 
-        fn normalise_point<D: Number>(&self, mut point: Self::Point<D>) -> Self::Point<D> {
+        fn thruster_slot(&self, id: &Self::MotorId) -> usize {
+            *id as usize
+        }
+
+        fn normalise_point<D: Number>(
+            &self,
+            mut point: Self::Point<D>,
+            constraints: &PositionConstraints,
+        ) -> Self::Point<D> {
             for idx in 0..THRUSTER_COUNT {
+                let position = constraints.constrain(point.fixed_view::<3, 1>(0, idx).into_owned());
+                point.fixed_view_mut::<3, 1>(0, idx).copy_from(&position);
                 point.fixed_view_mut::<3, 1>(3, idx).normalize_mut();
             }
 
             point
         }
+
+        fn score_gradient(
+            &self,
+            point: &Self::Point<FloatType>,
+            directions: &[SpinDirection],
+            heuristic: &ScoreSettings,
+            motor_data: &ThrusterModels,
+        ) -> (FloatType, Self::Point<FloatType>) {
+            super::fixed_gradient(self, point, directions, heuristic, motor_data)
+        }
     }
 }
 
-pub trait OptimizationArena {
-    fn reset(&mut self, point_count: usize, heuristic: ScoreSettings);
-    fn set_heuristic(&mut self, heuristic: ScoreSettings);
-    fn step<'a>(
-        &'a mut self,
-        motor_data: &MotorData,
-    ) -> Box<dyn Iterator<Item = OptimizationOutput> + 'a>;
-
-    fn lookup_index(&self, idx: usize) -> Option<OptimizationOutput>;
-}
+pub mod dynamic {
+    use motor_math::{
+        utils::VectorTransform, Direction, ErasedMotorId, FloatType, Motor, MotorConfig, Number,
+    };
+    use nalgebra::{vector, DMatrix, Vector3};
+    use rand::Rng;
+    use serde::{Deserialize, Serialize};
+    use std::str::FromStr;
+
+    use super::{ArenaKind, ArenaType, OptimizableConfig};
+    use crate::{
+        constraints::PositionConstraints, heuristic::ScoreSettings, layout::SpinDirection,
+        thruster_models::ThrusterModels,
+    };
 
-#[derive(Debug, Clone)]
-pub struct OptimizationOutput {
-    pub idx: usize,
+    /// Like `SymerticalOptimization` and `FullOptimization` but with the thruster count and
+    /// symmetry chosen at runtime
+    pub struct DynamicOptimization {
+        pub thruster_count: usize,
+        pub symmetry: Symmetry,
+    }
 
-    pub score: FloatType,
-    pub motor_config: MotorConfig<ErasedMotorId, FloatType>,
-    pub parameters: DMatrix<FloatType>,
-    pub score_result_unscaled: ScoreResult<FloatType, Unscaled>,
-    pub score_result_scaled: ScoreResult<FloatType, Scaled>,
-}
+    /// A plane through the center of the vehicle
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub enum Plane {
+        XY,
+        XZ,
+        YZ,
+    }
 
-pub struct SyncOptimizationArena<Config: OptimizableConfig> {
-    config: Config,
-    heuristic: ScoreSettings,
-    points: Vec<(
-        usize,
-        FloatType,
-        OptimizationState<Config::Point<FloatType>>,
-        ScoreResult<FloatType, Unscaled>,
-    )>,
-
-    /// The step size/learn rate
-    step_size: FloatType,
-    /// The ratio by which a points score must improve to be considered an improvement
-    frontier_ratio_threshold: FloatType,
-    /// The number of time steps a point must not improve for it to be considered done
-    frontier_time_limit: i32,
-}
+    impl Plane {
+        fn reflect<D: Number>(&self, vector: Vector3<D>) -> Vector3<D> {
+            let transform = match self {
+                Plane::XY => VectorTransform::ReflectXY,
+                Plane::XZ => VectorTransform::ReflectXZ,
+                Plane::YZ => VectorTransform::ReflectYZ,
+            };
 
-impl<Config: OptimizableConfig> SyncOptimizationArena<Config> {
-    pub fn new(config: Config) -> Self {
-        Self {
-            config,
-            heuristic: ScoreSettings::default(),
-            points: vec![],
-            step_size: 0.002,
-            frontier_ratio_threshold: 1.01,
-            frontier_time_limit: 25,
+            transform.transform(vector)
         }
-    }
-}
 
-impl<const DIM1: usize, const DIM2: usize, Config: OptimizableConfig> OptimizationArena
-    for SyncOptimizationArena<Config>
-where
-    Config: OptimizableConfig<Point<FloatType> = SMatrix<FloatType, DIM1, DIM2>>
-        + OptimizableConfig<
-            Point<DualVec<FloatType, FloatType, Const<DIM1>, Const<DIM2>>> = SMatrix<
-                DualVec<FloatType, FloatType, Const<DIM1>, Const<DIM2>>,
-                DIM1,
-                DIM2,
-            >,
-        > + 'static,
-{
-    fn reset(&mut self, point_count: usize, heuristic: ScoreSettings) {
-        self.points = self
-            .config
-            .initial_points(point_count)
-            .enumerate()
-            .map(|(idx, it)| (idx, FloatType::NEG_INFINITY, it, Default::default()))
-            .collect_vec();
-        self.heuristic = heuristic;
+        fn name(&self) -> &'static str {
+            match self {
+                Plane::XY => "xy",
+                Plane::XZ => "xz",
+                Plane::YZ => "yz",
+            }
+        }
     }
 
-    fn set_heuristic(&mut self, heuristic: ScoreSettings) {
-        self.heuristic = heuristic;
+    /// How the free thrusters are copied to make up the rest of the config
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub enum Symmetry {
+        /// Every thruster is placed independently
+        None,
+        /// Reflected across a plane
+        Mirror(Plane),
+        /// Reflected left/right across YZ and fore/aft across XZ
+        DoubleMirror,
+        /// Repeated this many times around the vehicle's long (Y) axis
+        Rotational(usize),
     }
 
-    fn step<'a>(
-        &'a mut self,
-        motor_data: &MotorData,
-    ) -> Box<dyn Iterator<Item = OptimizationOutput> + 'a> {
-        for (_, score, point, breakdown) in &mut self.points {
-            if !point.done {
-                let ascent = adam_optimizer(
-                    point,
-                    &self.config,
-                    &self.heuristic,
-                    motor_data,
-                    self.step_size,
-                    self.frontier_ratio_threshold,
-                );
-                *point = ascent.new_point;
-                *score = ascent.old_score;
-                *breakdown = ascent.score_breakdown.to_float();
+    impl Symmetry {
+        /// The number of thrusters made from each free thruster
+        pub fn order(&self) -> usize {
+            match *self {
+                Symmetry::None => 1,
+                Symmetry::Mirror(_) => 2,
+                Symmetry::DoubleMirror => 4,
+                Symmetry::Rotational(fold) => fold.max(1),
+            }
+        }
 
-                if point.time - point.frontier_threshold.1 > self.frontier_time_limit {
-                    // point.done = true;
-                }
+        pub fn name(&self) -> String {
+            match self {
+                Symmetry::None => "none".to_owned(),
+                Symmetry::Mirror(plane) => format!("mirror-{}", plane.name()),
+                Symmetry::DoubleMirror => "double-mirror".to_owned(),
+                Symmetry::Rotational(fold) => format!("rotational-{fold}"),
             }
         }
 
-        self.points.sort_by(|a, b| FloatType::total_cmp(&a.1, &b.1));
+        /// Maps a vector of a free thruster to its `image`th copy, the 0th copy is the free
+        /// thruster itself
+        pub fn transform<D: Number>(&self, image: usize, vector: Vector3<D>) -> Vector3<D> {
+            match *self {
+                Symmetry::None => vector,
+                Symmetry::Mirror(plane) => {
+                    if image == 0 {
+                        vector
+                    } else {
+                        plane.reflect(vector)
+                    }
+                }
+                Symmetry::DoubleMirror => {
+                    let vector = if image & 1 == 1 {
+                        Plane::YZ.reflect(vector)
+                    } else {
+                        vector
+                    };
 
-        Box::new(
-            self.points
-                .iter()
-                .map(|(idx, score, point, breakdown)| OptimizationOutput {
-                    idx: *idx,
-                    score: *score,
-                    motor_config: self.config.motor_config(point.point).erase_lossy(),
-                    parameters: DMatrix::from_column_slice(DIM1, DIM2, point.point.as_slice()),
-                    score_result_unscaled: breakdown.clone(),
-                    score_result_scaled: breakdown.scale(&self.heuristic),
-                }),
-        )
+                    if image & 2 == 2 {
+                        Plane::XZ.reflect(vector)
+                    } else {
+                        vector
+                    }
+                }
+                Symmetry::Rotational(fold) => {
+                    let angle = core::f64::consts::TAU as FloatType * image as FloatType
+                        / fold.max(1) as FloatType;
+                    let (sin, cos) = angle.sin_cos();
+
+                    vector![
+                        vector.x * cos + vector.z * sin,
+                        vector.y,
+                        vector.z * cos - vector.x * sin
+                    ]
+                }
+            }
+        }
     }
 
-    fn lookup_index(&self, idx: usize) -> Option<OptimizationOutput> {
-        self.points
-            .iter()
-            .find(|(cur_idx, ..)| *cur_idx == idx)
-            .map(|(idx, score, point, breakdown)| OptimizationOutput {
-                idx: *idx,
-                score: *score,
-                motor_config: self.config.motor_config(point.point).erase_lossy(),
-                parameters: DMatrix::from_column_slice(DIM1, DIM2, point.point.as_slice()),
-                score_result_unscaled: breakdown.clone(),
-                score_result_scaled: breakdown.scale(&self.heuristic),
-            })
+    impl Symmetry {
+        /// Maps a vector of the `image`th copy back to the free thruster
+        pub fn inverse<D: Number>(&self, image: usize, vector: Vector3<D>) -> Vector3<D> {
+            match *self {
+                // Rotating by the remaining images completes a full turn
+                Symmetry::Rotational(fold) => {
+                    let fold = fold.max(1);
+                    self.transform((fold - image % fold) % fold, vector)
+                }
+                // Every reflection is its own inverse
+                _ => self.transform(image, vector),
+            }
+        }
     }
-}
-
-pub struct AsyncOptimizationArena<Config: OptimizableConfig> {
-    config: Config,
-    heuristic: ScoreSettings,
-    points: Vec<(
-        usize,
-        FloatType,
-        OptimizationState<Config::Point<FloatType>>,
-        ScoreResult<FloatType, Unscaled>,
-    )>,
-
-    /// The step size/learn rate
-    step_size: FloatType,
-    /// The ratio by which a points score must improve to be considered an improvement
-    frontier_ratio_threshold: FloatType,
-    /// The number of time steps a point must not improve for it to be considered done
-    frontier_time_limit: i32,
-}
 
-impl<Config: OptimizableConfig> AsyncOptimizationArena<Config> {
-    pub fn new(config: Config) -> Self {
-        Self {
-            config,
-            heuristic: ScoreSettings::default(),
-            points: vec![],
-            step_size: 0.002,
-            frontier_ratio_threshold: 1.01,
-            frontier_time_limit: 25,
+    impl FromStr for Symmetry {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            match s.to_ascii_lowercase().as_str() {
+                "none" => Ok(Symmetry::None),
+                "mirror-xy" => Ok(Symmetry::Mirror(Plane::XY)),
+                "mirror-xz" => Ok(Symmetry::Mirror(Plane::XZ)),
+                "mirror-yz" => Ok(Symmetry::Mirror(Plane::YZ)),
+                "double-mirror" => Ok(Symmetry::DoubleMirror),
+                other => other
+                    .strip_prefix("rotational-")
+                    .and_then(|fold| fold.parse().ok())
+                    .filter(|&fold| fold > 0)
+                    .map(Symmetry::Rotational)
+                    .ok_or_else(|| {
+                        format!(
+                            "Unknown symmetry `{s}`, expected none, mirror-xy, mirror-xz, \
+                             mirror-yz, double-mirror or rotational-N"
+                        )
+                    }),
+            }
+        }
+    }
+
+    impl DynamicOptimization {
+        /// Panics if `thruster_count` isn't a multiple of the symmetry's order
+        pub fn new(thruster_count: usize, symmetry: Symmetry) -> Self {
+            assert!(
+                thruster_count.is_multiple_of(symmetry.order()),
+                "{} symmetry needs a multiple of {} thrusters",
+                symmetry.name(),
+                symmetry.order()
+            );
+
+            Self {
+                thruster_count,
+                symmetry,
+            }
+        }
+
+        pub fn symmetrical(thruster_count: usize) -> Self {
+            Self::new(thruster_count, Symmetry::Mirror(Plane::YZ))
+        }
+
+        pub fn unconstrained(thruster_count: usize) -> Self {
+            Self::new(thruster_count, Symmetry::None)
+        }
+
+        /// The number of thrusters with their own parameters
+        fn free_thrusters(&self) -> usize {
+            self.thruster_count / self.symmetry.order()
+        }
+    }
+
+    impl OptimizableConfig for DynamicOptimization {
+        type MotorId = ErasedMotorId;
+        type Point<D: Number> = DMatrix<D>;
+
+        fn parameter_shape(&self) -> (usize, usize) {
+            (6, self.free_thrusters())
+        }
+
+        fn arena_kind(&self) -> ArenaKind {
+            ArenaKind::Type(ArenaType::Symmetric {
+                symmetry: self.symmetry,
+                thrusters: self.thruster_count,
+            })
+        }
+
+        fn initial_points(
+            &self,
+            count: usize,
+            rng: &mut impl Rng,
+        ) -> impl Iterator<Item = Self::Point<FloatType>> {
+            let columns = self.free_thrusters();
+            (0..count).map(move |_| DMatrix::from_fn(6, columns, |_, _| rng.gen()))
+        }
+
+        fn motor_config<D: Number>(&self, point: Self::Point<D>) -> MotorConfig<Self::MotorId, D> {
+            let free_thrusters = self.free_thrusters();
+
+            MotorConfig::<ErasedMotorId, _>::new_raw(
+                (0..self.thruster_count).map(|idx| {
+                    let (image, free) = (idx / free_thrusters, idx % free_thrusters);
+                    let position = point.fixed_view::<3, 1>(0, free).into_owned();
+                    let orientation = point.fixed_view::<3, 1>(3, free).into_owned();
+
+                    (
+                        idx as _,
+                        Motor {
+                            position: self.symmetry.transform(image, position),
+                            orientation: self.symmetry.transform(image, orientation),
+                            direction: Direction::Clockwise,
+                        },
+                    )
+                }),
+                vector![0.0, 0.0, 0.0].map(D::from),
+            )
+        }
+
+        fn thruster_slot(&self, id: &Self::MotorId) -> usize {
+            *id as usize % self.free_thrusters()
+        }
+
+        fn normalise_point<D: Number>(
+            &self,
+            mut point: Self::Point<D>,
+            constraints: &PositionConstraints,
+        ) -> Self::Point<D> {
+            for idx in 0..point.ncols() {
+                let position = constraints.constrain_images(
+                    point.fixed_view::<3, 1>(0, idx).into_owned(),
+                    self.symmetry.order(),
+                    |image, position| self.symmetry.transform(image, position),
+                    |image, position| self.symmetry.inverse(image, position),
+                );
+                point.fixed_view_mut::<3, 1>(0, idx).copy_from(&position);
+                point.fixed_view_mut::<3, 1>(3, idx).normalize_mut();
+            }
+
+            point
+        }
+
+        fn score_gradient(
+            &self,
+            point: &Self::Point<FloatType>,
+            directions: &[SpinDirection],
+            heuristic: &ScoreSettings,
+            motor_data: &ThrusterModels,
+        ) -> (FloatType, Self::Point<FloatType>) {
+            super::column_gradient::<6, _>(self, point, directions, heuristic, motor_data)
+        }
+    }
+}
+
+pub mod mounted {
+    use std::path::Path;
+
+    use anyhow::ensure;
+    use itertools::Itertools;
+    use motor_math::{Direction, ErasedMotorId, FloatType, Motor, MotorConfig, Number};
+    use nalgebra::{vector, DMatrix, Vector3};
+    use rand::{seq::index, Rng};
+    use serde::{Deserialize, Serialize};
+
+    use super::{
+        optimizers::OptimizerType, ArenaKind, Execution, OptimizableConfig, OptimizationArena,
+    };
+    use crate::{
+        constraints::PositionConstraints, format, heuristic::ScoreSettings, layout::SpinDirection,
+        thruster_models::ThrusterModels,
+    };
+
+    /// A set of holes a thruster can be bolted to
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct Mount {
+        /// Position in meters relative to the center of the vehicle
+        pub position: [FloatType; 3],
+        /// The center of the orientations the thruster can be mounted in
+        pub axis: [FloatType; 3],
+        /// Half angle in degrees of the cone around `axis` the thruster's orientation must lie
+        /// in, 180 allows any orientation
+        #[serde(default = "Mount::default_cone_angle")]
+        pub cone_angle: FloatType,
+    }
+
+    impl Mount {
+        fn default_cone_angle() -> FloatType {
+            180.0
+        }
+    }
+
+    /// The mounts of a frame, each can hold at most one thruster
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    pub struct MountingPlate {
+        pub mounts: Vec<Mount>,
+    }
+
+    impl MountingPlate {
+        pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+            format::load(path)
+        }
+
+        pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+            format::save(path, self)
+        }
+    }
+
+    /// Assigns each thruster to one of a fixed set of mounts and optimizes its orientation
+    /// within the mount's cone
+    ///
+    /// Each column of a point holds a thruster's orientation in rows 0..3 and the index of its
+    /// mount in row 3. The optimizers only change the orientations, mounts are reassigned by
+    /// searching `discrete_neighbours`
+    pub struct MountedOptimization {
+        plate: MountingPlate,
+        thruster_count: usize,
+        mounts: Vec<PreparedMount>,
+    }
+
+    struct PreparedMount {
+        position: Vector3<FloatType>,
+        axis: Vector3<FloatType>,
+        /// A unit vector perpendicular to `axis`, used to leave the cone's axis when an
+        /// orientation points straight away from it
+        perpendicular: Vector3<FloatType>,
+        cos: FloatType,
+        sin: FloatType,
+    }
+
+    impl PreparedMount {
+        /// Moves a unit `orientation` to the closest orientation inside the mount's cone
+        fn constrain<D: Number>(&self, orientation: Vector3<D>) -> Vector3<D> {
+            let axis = self.axis.map(D::from);
+            let along = orientation.dot(&axis);
+            if along.re() >= self.cos {
+                return orientation;
+            }
+
+            let mut perpendicular = orientation - axis * along;
+            if perpendicular.norm().re() < 1e-6 {
+                perpendicular = self.perpendicular.map(D::from);
+            } else {
+                perpendicular.normalize_mut();
+            }
+
+            axis * D::from(self.cos) + perpendicular * D::from(self.sin)
+        }
+    }
+
+    impl MountedOptimization {
+        pub fn new(plate: &MountingPlate, thruster_count: usize) -> anyhow::Result<Self> {
+            ensure!(thruster_count > 0, "Thruster count must be positive");
+            ensure!(
+                thruster_count <= plate.mounts.len(),
+                "{thruster_count} thrusters don't fit on {} mounts",
+                plate.mounts.len()
+            );
+
+            let mounts = plate
+                .mounts
+                .iter()
+                .enumerate()
+                .map(|(idx, mount)| {
+                    let axis = Vector3::from(mount.axis);
+                    ensure!(axis.norm() > 1e-6, "Mount {idx} has no axis");
+                    let axis = axis.normalize();
+
+                    let perpendicular = if axis.cross(&Vector3::x()).norm() > 1e-3 {
+                        axis.cross(&Vector3::x())
+                    } else {
+                        axis.cross(&Vector3::y())
+                    };
+                    let (sin, cos) = mount.cone_angle.clamp(0.0, 180.0).to_radians().sin_cos();
+
+                    Ok(PreparedMount {
+                        position: Vector3::from(mount.position),
+                        axis,
+                        perpendicular: perpendicular.normalize(),
+                        cos,
+                        sin,
+                    })
+                })
+                .collect::<anyhow::Result<_>>()?;
+
+            Ok(Self {
+                plate: plate.clone(),
+                thruster_count,
+                mounts,
+            })
+        }
+
+        fn mount_index<D: Number>(&self, value: D) -> usize {
+            (value.re().round().max(0.0) as usize).min(self.mounts.len() - 1)
+        }
+    }
+
+    /// The mount of each thruster of a mounted optimization's parameters
+    pub fn assigned_mounts(parameters: &DMatrix<FloatType>) -> Vec<usize> {
+        parameters
+            .row(3)
+            .iter()
+            .map(|it| it.round().max(0.0) as usize)
+            .collect()
+    }
+
+    /// Creates an arena that places `thruster_count` thrusters on the mounts of `plate`
+    pub fn new_arena(
+        plate: &MountingPlate,
+        thruster_count: usize,
+        execution: Execution,
+        optimizer: OptimizerType,
+    ) -> anyhow::Result<Box<dyn OptimizationArena + Send + Sync + 'static>> {
+        super::new_arena(
+            MountedOptimization::new(plate, thruster_count)?,
+            execution,
+            optimizer,
+        )
+    }
+
+    impl OptimizableConfig for MountedOptimization {
+        type MotorId = ErasedMotorId;
+        type Point<D: Number> = DMatrix<D>;
+
+        fn parameter_shape(&self) -> (usize, usize) {
+            (4, self.thruster_count)
+        }
+
+        fn arena_kind(&self) -> ArenaKind {
+            ArenaKind::Mounted {
+                plate: self.plate.clone(),
+                thrusters: self.thruster_count,
+            }
+        }
+
+        fn initial_points(
+            &self,
+            count: usize,
+            rng: &mut impl Rng,
+        ) -> impl Iterator<Item = Self::Point<FloatType>> {
+            (0..count).map(move |_| {
+                let mounts = index::sample(rng, self.mounts.len(), self.thruster_count);
+                let point = DMatrix::from_fn(4, self.thruster_count, |row, col| {
+                    if row < 3 {
+                        rng.gen::<FloatType>() * 2.0 - 1.0
+                    } else {
+                        mounts.index(col) as FloatType
+                    }
+                });
+
+                self.normalise_point(point, &PositionConstraints::default())
+            })
+        }
+
+        fn motor_config<D: Number>(&self, point: Self::Point<D>) -> MotorConfig<Self::MotorId, D> {
+            MotorConfig::<ErasedMotorId, _>::new_raw(
+                (0..self.thruster_count).map(|idx| {
+                    let mount = &self.mounts[self.mount_index(point[(3, idx)])];
+
+                    (
+                        idx as _,
+                        Motor {
+                            position: mount.position.map(D::from),
+                            orientation: point.fixed_view::<3, 1>(0, idx).into_owned(),
+                            direction: Direction::Clockwise,
+                        },
+                    )
+                }),
+                vector![0.0, 0.0, 0.0].map(D::from),
+            )
+        }
+
+        fn thruster_slot(&self, id: &Self::MotorId) -> usize {
+            *id as usize
+        }
+
+        // The mount indices only change by searching `discrete_neighbours`
+        fn optimized_rows(&self) -> usize {
+            3
+        }
+
+        /// Thrusters on a mount that is taken or outside of `constraints` are moved to the free
+        /// mount inside them closest to the requested mount's position. When `constraints` leave
+        /// too few mounts, the closest free mount outside them is used, as there are always
+        /// enough mounts for every thruster
+        fn normalise_point<D: Number>(
+            &self,
+            mut point: Self::Point<D>,
+            constraints: &PositionConstraints,
+        ) -> Self::Point<D> {
+            let allowed = self
+                .mounts
+                .iter()
+                .map(|mount| constraints.allows(mount.position))
+                .collect_vec();
+            let mut taken = vec![false; self.mounts.len()];
+
+            for idx in 0..point.ncols() {
+                let requested = self.mounts[self.mount_index(point[(3, idx)])].position;
+                let distance = |it: usize| (self.mounts[it].position - requested).norm_squared();
+                let mount = (0..self.mounts.len())
+                    .filter(|&it| !taken[it])
+                    .min_by(|&a, &b| {
+                        (!allowed[a])
+                            .cmp(&!allowed[b])
+                            .then_with(|| distance(a).total_cmp(&distance(b)))
+                    })
+                    .expect("No more thrusters than mounts");
+                taken[mount] = true;
+                point[(3, idx)] = D::from(mount as FloatType);
+
+                // An orientation that has collapsed to zero points along the mount's axis
+                let orientation = point.fixed_view::<3, 1>(0, idx);
+                let orientation = if orientation.norm().re() > 1e-6 {
+                    orientation.normalize()
+                } else {
+                    self.mounts[mount].axis.map(D::from)
+                };
+                let orientation = self.mounts[mount].constrain(orientation);
+                point.fixed_view_mut::<3, 1>(0, idx).copy_from(&orientation);
+            }
+
+            point
+        }
+
+        fn score_gradient(
+            &self,
+            point: &Self::Point<FloatType>,
+            directions: &[SpinDirection],
+            heuristic: &ScoreSettings,
+            motor_data: &ThrusterModels,
+        ) -> (FloatType, Self::Point<FloatType>) {
+            super::column_gradient::<3, _>(self, point, directions, heuristic, motor_data)
+        }
+
+        /// Every move of one thruster to a free mount and every swap of two thrusters' mounts
+        fn discrete_neighbours(
+            &self,
+            point: &Self::Point<FloatType>,
+            constraints: &PositionConstraints,
+        ) -> Vec<Self::Point<FloatType>> {
+            let assigned = assigned_mounts(point);
+            let free = (0..self.mounts.len())
+                .filter(|it| !assigned.contains(it))
+                .collect_vec();
+
+            let moves = (0..point.ncols())
+                .cartesian_product(free)
+                .map(|(idx, mount)| {
+                    let mut neighbour = point.clone();
+                    neighbour[(3, idx)] = mount as FloatType;
+                    neighbour
+                });
+            let swaps = (0..point.ncols()).tuple_combinations().map(|(a, b)| {
+                let mut neighbour = point.clone();
+                neighbour.swap((3, a), (3, b));
+                neighbour
+            });
+
+            moves
+                .chain(swaps)
+                .map(|neighbour| self.normalise_point(neighbour, constraints))
+                .collect()
+        }
+    }
+}
+
+pub trait OptimizationArena {
+    fn reset(&mut self, point_count: usize, heuristic: ScoreSettings, seed: u64);
+    fn set_heuristic(&mut self, heuristic: ScoreSettings);
+    fn hyperparameters(&self) -> Hyperparameters;
+    /// Sets the hyperparameters, retired points are reactivated if the convergence criteria
+    /// change
+    fn set_hyperparameters(&mut self, hyperparameters: Hyperparameters);
+    fn constraints(&self) -> &PositionConstraints;
+    /// The number of thruster slots of the config, each can use its own thruster model
+    fn slot_count(&self) -> usize;
+    /// Replaces the position constraints and moves every point to satisfy them
+    fn set_constraints(&mut self, constraints: PositionConstraints);
+    /// Steps every point with the thruster models of `motor_data` assigned to the config's
+    /// slots
+    fn step<'a>(
+        &'a mut self,
+        motor_data: &ThrusterModels,
+    ) -> Box<dyn Iterator<Item = OptimizationOutput> + 'a>;
+
+    fn lookup_index(&self, idx: usize) -> Option<OptimizationOutput>;
+
+    /// True once every point has met the convergence criteria
+    fn converged(&self) -> bool;
+
+    /// Captures the state of every point so the run can be resumed later
+    fn checkpoint(&self) -> ArenaCheckpoint;
+    /// Replaces the arena's points with the ones from `checkpoint`
+    fn restore(&mut self, checkpoint: ArenaCheckpoint) -> anyhow::Result<()>;
+}
+
+/// The state of an optimization arena
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArenaCheckpoint {
+    /// The arena the points belong to
+    pub arena: ArenaKind,
+    pub heuristic: ScoreSettings,
+    #[serde(default)]
+    pub hyperparameters: Hyperparameters,
+    #[serde(default)]
+    pub constraints: PositionConstraints,
+    pub seed: u64,
+    pub points: Vec<PointCheckpoint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PointCheckpoint {
+    pub idx: usize,
+    #[serde(with = "non_finite")]
+    pub score: FloatType,
+    pub state: OptimizationState<DMatrix<FloatType>, OptimizerState>,
+    pub score_result: ScoreResult<FloatType, Unscaled>,
+}
+
+/// The configurations an arena optimizes, arenas of the same kind give their parameters the same
+/// meaning
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum ArenaKind {
+    Type(ArenaType),
+    Mounted {
+        plate: MountingPlate,
+        thrusters: usize,
+    },
+}
+
+impl ArenaKind {
+    pub fn name(&self) -> String {
+        match self {
+            ArenaKind::Type(arena_type) => arena_type.name(),
+            ArenaKind::Mounted { plate, thrusters } => {
+                format!("{thrusters} thrusters on {} mounts", plate.mounts.len())
+            }
+        }
+    }
+}
+
+impl ArenaCheckpoint {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        format::load_with_paths(path)
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        format::save_with_paths(path, self)
+    }
+}
+
+impl FilePaths for ArenaCheckpoint {
+    fn load_paths(&mut self, directory: &Path) -> anyhow::Result<()> {
+        self.heuristic.load_paths(directory)
+    }
+
+    fn relative_paths(&mut self, directory: &Path) {
+        self.heuristic.relative_paths(directory);
+    }
+}
+
+fn erase_point<R: Dim, C: Dim>(point: &OMatrix<FloatType, R, C>) -> DMatrix<FloatType>
+where
+    DefaultAllocator: Allocator<R, C>,
+{
+    DMatrix::from_column_slice(point.nrows(), point.ncols(), point.as_slice())
+}
+
+fn unerase_point<R: Dim, C: Dim>(
+    point: DMatrix<FloatType>,
+    (rows, cols): (usize, usize),
+) -> anyhow::Result<OMatrix<FloatType, R, C>>
+where
+    DefaultAllocator: Allocator<R, C>,
+{
+    ensure!(
+        point.shape() == (rows, cols),
+        "Checkpoint has {}x{} parameters but the arena expects {rows}x{cols}",
+        point.nrows(),
+        point.ncols()
+    );
+
+    Ok(OMatrix::from_column_slice_generic(
+        R::from_usize(rows),
+        C::from_usize(cols),
+        point.as_slice(),
+    ))
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizationOutput {
+    pub idx: usize,
+
+    pub score: FloatType,
+    pub motor_config: MotorConfig<ErasedMotorId, FloatType>,
+    pub parameters: DMatrix<FloatType>,
+    pub score_result_unscaled: ScoreResult<FloatType, Unscaled>,
+    pub score_result_scaled: ScoreResult<FloatType, Scaled>,
+    /// The seed of the run that produced this config, if any
+    pub seed: Option<u64>,
+    /// Whether the point has stopped being optimized
+    pub converged: bool,
+    /// The thruster model of each thruster in `motors()` order, empty if every thruster uses
+    /// the default model
+    pub thruster_models: Vec<usize>,
+    /// Number of thrusters outside of the position constraints, which happens when the keep
+    /// out volumes and envelope can't all be satisfied at once
+    pub constraint_violations: usize,
+}
+
+impl OptimizationOutput {
+    /// Scores a fixed motor config outside of an optimization arena
+    pub fn evaluate(
+        motor_config: MotorConfig<ErasedMotorId, FloatType>,
+        heuristic: &ScoreSettings,
+        motor_data: &ThrusterModels,
+    ) -> Self {
+        // Every thruster of a fixed config is its own slot
+        let motor_data =
+            motor_data.for_thrusters(motor_config.motors().map(|(id, _)| *id as usize));
+        let (score, score_result) = evaluate(&motor_config, heuristic, &motor_data);
+        let motors = motor_config.motors().collect_vec();
+        let parameters = DMatrix::from_fn(6, motors.len(), |row, col| {
+            let (_, motor) = motors[col];
+            if row < 3 {
+                motor.position[row]
+            } else {
+                motor.orientation[row - 3]
+            }
+        });
+
+        Self {
+            idx: usize::MAX,
+            score,
+            parameters,
+            score_result_scaled: score_result.scale(heuristic),
+            score_result_unscaled: score_result,
+            motor_config,
+            seed: None,
+            // Fixed configs are never stepped
+            converged: true,
+            thruster_models: motor_data.assignment().to_vec(),
+            // Fixed configs aren't placed under any constraints
+            constraint_violations: 0,
+        }
+    }
+}
+
+/// Orders points from best to worst score, ties are broken by index so the order
+/// is independent of how the points were stepped. NaN scores are worse than any other
+fn top_config_order<Point>(
+    a: &(usize, FloatType, Point, ScoreResult<FloatType, Unscaled>),
+    b: &(usize, FloatType, Point, ScoreResult<FloatType, Unscaled>),
+) -> Ordering {
+    a.1.is_nan()
+        .cmp(&b.1.is_nan())
+        .then(FloatType::total_cmp(&a.1, &b.1).reverse())
+        .then(a.0.cmp(&b.0))
+}
+
+/// The first `rows` rows of every column of `point`, the parameters the optimizers change
+fn optimized_parameters<R: Dim, C: Dim>(
+    point: &OMatrix<FloatType, R, C>,
+    rows: usize,
+) -> DVector<FloatType>
+where
+    DefaultAllocator: Allocator<R, C>,
+{
+    DVector::from_iterator(rows * point.ncols(), point.rows(0, rows).iter().copied())
+}
+
+/// Adapts an optimizable config to the interface used by the optimizers
+struct ConfigObjective<'a, Config, R: Dim, C: Dim>
+where
+    DefaultAllocator: Allocator<R, C>,
+{
+    config: &'a Config,
+    heuristic: &'a ScoreSettings,
+    motor_data: &'a ThrusterModels,
+    directions: &'a [SpinDirection],
+    constraints: &'a PositionConstraints,
+    /// The point being stepped from, rows past `optimized_rows` are taken from it unchanged
+    base: &'a OMatrix<FloatType, R, C>,
+    optimized_rows: usize,
+}
+
+impl<R: Dim, C: Dim, Config> ConfigObjective<'_, Config, R, C>
+where
+    DefaultAllocator: Allocator<R, C>,
+{
+    fn flatten(&self, point: &OMatrix<FloatType, R, C>) -> DVector<FloatType> {
+        optimized_parameters(point, self.optimized_rows)
+    }
+
+    fn unflatten(&self, point: &DVector<FloatType>) -> OMatrix<FloatType, R, C> {
+        let mut matrix = self.base.clone();
+        for (value, parameter) in matrix
+            .rows_mut(0, self.optimized_rows)
+            .iter_mut()
+            .zip(point.iter())
+        {
+            *value = *parameter;
+        }
+
+        matrix
+    }
+}
+
+impl<R: Dim, C: Dim, Config> Objective for ConfigObjective<'_, Config, R, C>
+where
+    Config: OptimizableConfig<Point<FloatType> = OMatrix<FloatType, R, C>>,
+    DefaultAllocator: Allocator<R, C>,
+{
+    fn score(&self, point: &DVector<FloatType>) -> FloatType {
+        let motor_config = physics::with_directions(
+            self.config.motor_config::<FloatType>(self.unflatten(point)),
+            self.directions,
+        );
+
+        evaluate(&motor_config, self.heuristic, self.motor_data).0
+    }
+
+    fn score_gradient(&self, point: &DVector<FloatType>) -> (FloatType, DVector<FloatType>) {
+        let (score, grad) = self.config.score_gradient(
+            &self.unflatten(point),
+            self.directions,
+            self.heuristic,
+            self.motor_data,
+        );
+
+        (score, self.flatten(&grad))
+    }
+
+    fn normalise(&self, point: DVector<FloatType>) -> DVector<FloatType> {
+        let point = self
+            .config
+            .normalise_point::<FloatType>(self.unflatten(&point), self.constraints);
+
+        self.flatten(&point)
+    }
+}
+
+/// Takes one optimizer step for a single point of an arena
+#[allow(clippy::too_many_arguments)]
+fn step_point<R: Dim, C: Dim, Config, Opt: Optimizer>(
+    config: &Config,
+    optimizer: &Opt,
+    heuristic: &ScoreSettings,
+    constraints: &PositionConstraints,
+    motor_data: &ThrusterModels,
+    hyperparameters: &Hyperparameters,
+    score: &mut FloatType,
+    point: &mut OptimizationState<OMatrix<FloatType, R, C>, Opt::State>,
+    breakdown: &mut ScoreResult<FloatType, Unscaled>,
+) where
+    Config: OptimizableConfig<Point<FloatType> = OMatrix<FloatType, R, C>>,
+    DefaultAllocator: Allocator<R, C>,
+{
+    let objective = ConfigObjective {
+        config,
+        heuristic,
+        motor_data,
+        directions: &point.directions,
+        constraints,
+        base: &point.point,
+        optimized_rows: config.optimized_rows(),
+    };
+
+    let time = point.time + 1;
+    let step = optimizer.step(
+        &objective.flatten(&point.point),
+        &mut point.optimizer,
+        time,
+        hyperparameters.step_size(time),
+        &objective,
+    );
+    let new_point = objective.unflatten(&step.point);
+
+    let (new_score, new_breakdown) =
+        if hyperparameters.spin_interval > 0 && time % hyperparameters.spin_interval == 0 {
+            let (directions, new_score, new_breakdown) =
+                search_directions(config, &new_point, &point.directions, heuristic, motor_data);
+            if directions != point.directions {
+                point.directions = directions;
+                optimizer.objective_changed(&mut point.optimizer);
+            }
+
+            (new_score, new_breakdown)
+        } else {
+            evaluate(
+                &physics::with_directions(
+                    config.motor_config::<FloatType>(new_point.clone()),
+                    &point.directions,
+                ),
+                heuristic,
+                motor_data,
+            )
+        };
+
+    let (new_point, new_score, new_breakdown) =
+        if hyperparameters.discrete_interval > 0 && time % hyperparameters.discrete_interval == 0 {
+            match search_discrete(
+                config,
+                &new_point,
+                &point.directions,
+                heuristic,
+                constraints,
+                motor_data,
+            ) {
+                Some((neighbour, neighbour_score, neighbour_breakdown))
+                    if neighbour_score > new_score =>
+                {
+                    optimizer.objective_changed(&mut point.optimizer);
+                    (neighbour, neighbour_score, neighbour_breakdown)
+                }
+                _ => (new_point, new_score, new_breakdown),
+            }
+        } else {
+            (new_point, new_score, new_breakdown)
+        };
+    *score = new_score;
+    *breakdown = new_breakdown;
+
+    point.advance(
+        new_point,
+        new_score,
+        step.convergence,
+        &hyperparameters.convergence,
+    );
+}
+
+/// Scores the candidate spin directions for the thrusters of `point` and returns the best
+fn search_directions<Config: OptimizableConfig>(
+    config: &Config,
+    point: &Config::Point<FloatType>,
+    directions: &[SpinDirection],
+    heuristic: &ScoreSettings,
+    motor_data: &ThrusterModels,
+) -> (
+    Vec<SpinDirection>,
+    FloatType,
+    ScoreResult<FloatType, Unscaled>,
+)
+where
+    Config::Point<FloatType>: Clone,
+{
+    let motor_config = config.motor_config::<FloatType>(point.clone());
+    let thruster_count = motor_config.motors().count();
+
+    physics::direction_candidates(directions, thruster_count)
+        .into_iter()
+        .map(|directions| {
+            let (score, breakdown) = evaluate(
+                &physics::with_directions(motor_config.clone(), &directions),
+                heuristic,
+                motor_data,
+            );
+
+            (directions, score, breakdown)
+        })
+        .max_by(|(_, a, _), (_, b, _)| FloatType::total_cmp(a, b))
+        .expect("At least one assignment of spin directions")
+}
+
+/// Scores every discrete neighbour of `point` and returns the best, if it has any
+fn search_discrete<Config: OptimizableConfig>(
+    config: &Config,
+    point: &Config::Point<FloatType>,
+    directions: &[SpinDirection],
+    heuristic: &ScoreSettings,
+    constraints: &PositionConstraints,
+    motor_data: &ThrusterModels,
+) -> Option<(
+    Config::Point<FloatType>,
+    FloatType,
+    ScoreResult<FloatType, Unscaled>,
+)>
+where
+    Config::Point<FloatType>: Clone,
+{
+    config
+        .discrete_neighbours(point, constraints)
+        .into_iter()
+        .map(|neighbour| {
+            let (score, breakdown) = evaluate(
+                &physics::with_directions(
+                    config.motor_config::<FloatType>(neighbour.clone()),
+                    directions,
+                ),
+                heuristic,
+                motor_data,
+            );
+
+            (neighbour, score, breakdown)
+        })
+        .max_by(|(_, a, _), (_, b, _)| FloatType::total_cmp(a, b))
+}
+
+/// How the points of an arena are stepped
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Execution {
+    /// One point after another on the calling thread
+    Serial,
+    /// In parallel on rayon's global thread pool
+    #[default]
+    Rayon,
+    /// In parallel on a dedicated pool with this many threads
+    Threads(usize),
+}
+
+impl Execution {
+    fn thread_pool(&self) -> anyhow::Result<Option<ThreadPool>> {
+        match *self {
+            Execution::Threads(threads) => Ok(Some(
+                ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .with_context(|| format!("Build a thread pool of {threads} threads"))?,
+            )),
+            _ => Ok(None),
+        }
+    }
+}
+
+impl FromStr for Execution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "serial" => Ok(Execution::Serial),
+            "rayon" => Ok(Execution::Rayon),
+            threads => threads.parse().map(Execution::Threads).map_err(|_| {
+                format!("Unknown execution `{s}`, expected serial, rayon or a thread count")
+            }),
+        }
+    }
+}
+
+/// A point's index, score, state and score breakdown
+type ArenaPoint<Config, Opt> = (
+    usize,
+    FloatType,
+    OptimizationState<<Config as OptimizableConfig>::Point<FloatType>, <Opt as Optimizer>::State>,
+    ScoreResult<FloatType, Unscaled>,
+);
+
+/// Optimizes a set of points of an optimizable config
+pub struct ConfigArena<Config: OptimizableConfig, Opt: Optimizer> {
+    config: Config,
+    optimizer: Opt,
+    execution: Execution,
+    /// Only used for `Execution::Threads`
+    thread_pool: Option<ThreadPool>,
+    heuristic: ScoreSettings,
+    constraints: PositionConstraints,
+    /// The seed the current points were generated from
+    seed: u64,
+    points: Vec<ArenaPoint<Config, Opt>>,
+    /// The thruster model of each thruster as of the last step
+    thruster_models: Vec<usize>,
+
+    hyperparameters: Hyperparameters,
+}
+
+impl<Config: OptimizableConfig, Opt: Optimizer> ConfigArena<Config, Opt> {
+    pub fn new(config: Config, optimizer: Opt, execution: Execution) -> anyhow::Result<Self> {
+        Ok(Self {
+            config,
+            optimizer,
+            execution,
+            thread_pool: execution.thread_pool()?,
+            heuristic: ScoreSettings::default(),
+            constraints: PositionConstraints::default(),
+            seed: 0,
+            points: vec![],
+            thruster_models: vec![],
+            hyperparameters: Hyperparameters::default(),
+        })
+    }
+}
+
+impl<R: Dim, C: Dim, Config, Opt: Optimizer> ConfigArena<Config, Opt>
+where
+    Config: OptimizableConfig<Point<FloatType> = OMatrix<FloatType, R, C>>,
+    DefaultAllocator: Allocator<R, C>,
+{
+    fn output(
+        &self,
+        idx: usize,
+        score: FloatType,
+        point: &OptimizationState<OMatrix<FloatType, R, C>, Opt::State>,
+        breakdown: &ScoreResult<FloatType, Unscaled>,
+    ) -> OptimizationOutput {
+        let motor_config = physics::with_directions(
+            self.config.motor_config::<FloatType>(point.point.clone()),
+            &point.directions,
+        )
+        .erase_lossy();
+        let constraint_violations = motor_config
+            .motors()
+            .filter(|(_, motor)| !self.constraints.allows(motor.position))
+            .count();
+
+        OptimizationOutput {
+            idx,
+            score,
+            motor_config,
+            parameters: erase_point(&point.point),
+            score_result_unscaled: breakdown.clone(),
+            score_result_scaled: breakdown.scale(&self.heuristic),
+            seed: Some(self.seed),
+            converged: point.done,
+            thruster_models: self.thruster_models.clone(),
+            constraint_violations,
         }
     }
 }
 
-impl<const DIM1: usize, const DIM2: usize, Config: OptimizableConfig> OptimizationArena
-    for AsyncOptimizationArena<Config>
+impl<R: Dim, C: Dim, Config, Opt: Optimizer> OptimizationArena for ConfigArena<Config, Opt>
 where
-    Config: OptimizableConfig<Point<FloatType> = SMatrix<FloatType, DIM1, DIM2>>
-        + OptimizableConfig<
-            Point<DualVec<FloatType, FloatType, Const<DIM1>, Const<DIM2>>> = SMatrix<
-                DualVec<FloatType, FloatType, Const<DIM1>, Const<DIM2>>,
-                DIM1,
-                DIM2,
-            >,
-        > + Send
-        + Sync
-        + 'static,
+    Config: OptimizableConfig<Point<FloatType> = OMatrix<FloatType, R, C>> + Send + Sync + 'static,
+    DefaultAllocator: Allocator<R, C>,
+    OMatrix<FloatType, R, C>: Send + Sync,
 {
-    fn reset(&mut self, point_count: usize, heuristic: ScoreSettings) {
+    fn reset(&mut self, point_count: usize, heuristic: ScoreSettings, seed: u64) {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+
         self.points = self
             .config
-            .initial_points(point_count)
+            .initial_points(point_count, &mut rng)
+            .map(|point| {
+                let state = self
+                    .optimizer
+                    .initial_state(&optimized_parameters(&point, self.config.optimized_rows()));
+                OptimizationState::new(point, state)
+            })
             .enumerate()
             .map(|(idx, it)| (idx, FloatType::NEG_INFINITY, it, Default::default()))
             .collect_vec();
         self.heuristic = heuristic;
+        self.seed = seed;
     }
 
     fn set_heuristic(&mut self, heuristic: ScoreSettings) {
         self.heuristic = heuristic;
+
+        for (_, _, point, _) in &mut self.points {
+            point.reactivate();
+            self.optimizer.objective_changed(&mut point.optimizer);
+        }
+    }
+
+    fn hyperparameters(&self) -> Hyperparameters {
+        self.hyperparameters
+    }
+
+    fn set_hyperparameters(&mut self, hyperparameters: Hyperparameters) {
+        if hyperparameters.convergence != self.hyperparameters.convergence {
+            // Points are checked against the new criteria on their next step
+            for (_, _, point, _) in &mut self.points {
+                if point.done {
+                    point.reactivate();
+                }
+            }
+        }
+
+        self.hyperparameters = hyperparameters;
+    }
+
+    fn constraints(&self) -> &PositionConstraints {
+        &self.constraints
+    }
+
+    fn slot_count(&self) -> usize {
+        self.config.parameter_shape().1
+    }
+
+    fn set_constraints(&mut self, constraints: PositionConstraints) {
+        self.constraints = constraints;
+
+        for (_, _, point, _) in &mut self.points {
+            point.point = self
+                .config
+                .normalise_point::<FloatType>(point.point.clone(), &self.constraints);
+            point.reactivate();
+            self.optimizer.objective_changed(&mut point.optimizer);
+        }
     }
 
     fn step<'a>(
         &'a mut self,
-        motor_data: &MotorData,
+        motor_data: &ThrusterModels,
     ) -> Box<dyn Iterator<Item = OptimizationOutput> + 'a> {
-        self.points
-            .par_iter_mut()
-            .for_each(|(_, score, point, breakdown)| {
-                if !point.done {
-                    let ascent = adam_optimizer(
-                        point,
-                        &self.config,
-                        &self.heuristic,
-                        motor_data,
-                        self.step_size,
-                        self.frontier_ratio_threshold,
-                    );
-
-                    *point = ascent.new_point;
-                    *score = ascent.old_score;
-                    *breakdown = ascent.score_breakdown.to_float();
-
-                    if point.time - point.frontier_threshold.1 > self.frontier_time_limit {
-                        // point.done = true;
-                    }
-                }
-            });
+        // The slot of each thruster in `motors()` order is the same for every point
+        let slots = self
+            .points
+            .first()
+            .map(|(_, _, point, _)| {
+                self.config
+                    .motor_config::<FloatType>(point.point.clone())
+                    .motors()
+                    .map(|(id, _)| self.config.thruster_slot(id))
+                    .collect_vec()
+            })
+            .unwrap_or_default();
+        let motor_data = motor_data.for_thrusters(slots);
+        self.thruster_models = motor_data.assignment().to_vec();
 
-        self.points
-            .sort_by(|a, b| FloatType::total_cmp(&a.1, &b.1).reverse());
+        let step = |(_, score, point, breakdown): &mut ArenaPoint<Config, Opt>| {
+            if !point.done {
+                step_point(
+                    &self.config,
+                    &self.optimizer,
+                    &self.heuristic,
+                    &self.constraints,
+                    &motor_data,
+                    &self.hyperparameters,
+                    score,
+                    point,
+                    breakdown,
+                );
+            }
+        };
+
+        match (&self.execution, &self.thread_pool) {
+            (Execution::Serial, _) => self.points.iter_mut().for_each(step),
+            (Execution::Threads(_), Some(pool)) => {
+                pool.install(|| self.points.par_iter_mut().for_each(step))
+            }
+            (Execution::Rayon | Execution::Threads(_), _) => {
+                self.points.par_iter_mut().for_each(step)
+            }
+        }
+
+        self.points.sort_by(top_config_order);
 
         Box::new(
             self.points
                 .iter()
-                .map(|(idx, score, point, breakdown)| OptimizationOutput {
-                    idx: *idx,
-                    score: *score,
-                    motor_config: self.config.motor_config(point.point).erase_lossy(),
-                    parameters: DMatrix::from_column_slice(DIM1, DIM2, point.point.as_slice()),
-                    score_result_unscaled: breakdown.clone(),
-                    score_result_scaled: breakdown.scale(&self.heuristic),
-                }),
+                .map(|(idx, score, point, breakdown)| self.output(*idx, *score, point, breakdown)),
         )
     }
 
@@ -631,13 +1966,680 @@ where
         self.points
             .iter()
             .find(|(cur_idx, ..)| *cur_idx == idx)
-            .map(|(idx, score, point, breakdown)| OptimizationOutput {
-                idx: *idx,
-                score: *score,
-                motor_config: self.config.motor_config(point.point).erase_lossy(),
-                parameters: DMatrix::from_column_slice(DIM1, DIM2, point.point.as_slice()),
-                score_result_unscaled: breakdown.clone(),
-                score_result_scaled: breakdown.scale(&self.heuristic),
+            .map(|(idx, score, point, breakdown)| self.output(*idx, *score, point, breakdown))
+    }
+
+    fn converged(&self) -> bool {
+        self.points.iter().all(|(_, _, point, _)| point.done)
+    }
+
+    fn checkpoint(&self) -> ArenaCheckpoint {
+        ArenaCheckpoint {
+            arena: self.config.arena_kind(),
+            heuristic: self.heuristic.clone(),
+            hyperparameters: self.hyperparameters,
+            constraints: self.constraints.clone(),
+            seed: self.seed,
+            points: self
+                .points
+                .iter()
+                .map(|(idx, score, point, breakdown)| PointCheckpoint {
+                    idx: *idx,
+                    score: *score,
+                    state: point
+                        .clone()
+                        .try_map(|point| Ok(erase_point(&point)), |state| Ok(state.into()))
+                        .expect("Erasing a point is infallible"),
+                    score_result: breakdown.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    fn restore(&mut self, checkpoint: ArenaCheckpoint) -> anyhow::Result<()> {
+        let arena = self.config.arena_kind();
+        ensure!(
+            checkpoint.arena == arena,
+            "Checkpoint is of a {} arena but this is a {} arena",
+            checkpoint.arena.name(),
+            arena.name()
+        );
+
+        let shape = self.config.parameter_shape();
+        self.points = checkpoint
+            .points
+            .into_iter()
+            .map(|point| {
+                Ok((
+                    point.idx,
+                    point.score,
+                    point
+                        .state
+                        .try_map(|point| unerase_point(point, shape), Opt::State::try_from)?,
+                    point.score_result,
+                ))
             })
+            .collect::<anyhow::Result<_>>()?;
+        self.heuristic = checkpoint.heuristic;
+        self.hyperparameters = checkpoint.hyperparameters;
+        self.constraints = checkpoint.constraints;
+        self.seed = checkpoint.seed;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ArenaType {
+    X3d,
+    /// X3d with the position of the thrusters optimized alongside their orientation
+    X3dDynamic,
+    Symmetrical3,
+    Unconstrained6,
+    /// This many thrusters arranged with the given symmetry, the count must be a multiple of
+    /// the symmetry's order. `symmetrical-N` is mirrored across YZ and `unconstrained-N` has
+    /// no symmetry
+    Symmetric {
+        symmetry: Symmetry,
+        thrusters: usize,
+    },
+}
+
+impl ArenaType {
+    pub const ALL: &'static [ArenaType] = &[
+        ArenaType::X3d,
+        ArenaType::X3dDynamic,
+        ArenaType::Symmetrical3,
+        ArenaType::Unconstrained6,
+    ];
+
+    pub fn name(&self) -> String {
+        match self {
+            ArenaType::X3d => "x3d".to_owned(),
+            ArenaType::X3dDynamic => "x3d-dynamic".to_owned(),
+            ArenaType::Symmetrical3 => "symmetrical3".to_owned(),
+            ArenaType::Unconstrained6 => "unconstrained6".to_owned(),
+            ArenaType::Symmetric {
+                symmetry,
+                thrusters,
+            } => format!("{}-{thrusters}", symmetry.name()),
+        }
+    }
+
+    /// The number of thrusters of the configs produced by this arena type
+    pub fn thruster_count(&self) -> usize {
+        match *self {
+            ArenaType::X3d | ArenaType::X3dDynamic => 8,
+            ArenaType::Symmetrical3 | ArenaType::Unconstrained6 => 6,
+            ArenaType::Symmetric { thrusters, .. } => thrusters,
+        }
+    }
+
+    pub fn new_arena(
+        &self,
+        execution: Execution,
+        optimizer: OptimizerType,
+    ) -> anyhow::Result<Box<dyn OptimizationArena + Send + Sync + 'static>> {
+        match *self {
+            ArenaType::X3d => new_arena(
+                FixedX3dOptimization {
+                    width: WIDTH / 2.0,
+                    length: LENGTH / 2.0,
+                    height: HEIGHT / 2.0,
+                },
+                execution,
+                optimizer,
+            ),
+            ArenaType::X3dDynamic => new_arena(
+                DynamicX3dOptimization {
+                    width: WIDTH / 2.0,
+                    length: LENGTH / 2.0,
+                    height: HEIGHT / 2.0,
+                },
+                execution,
+                optimizer,
+            ),
+            ArenaType::Symmetrical3 => new_arena(SymerticalOptimization::<3>, execution, optimizer),
+            ArenaType::Unconstrained6 => new_arena(FullOptimization::<6>, execution, optimizer),
+            ArenaType::Symmetric {
+                symmetry,
+                thrusters,
+            } => new_arena(
+                DynamicOptimization::new(thruster_count_checked(symmetry, thrusters)?, symmetry),
+                execution,
+                optimizer,
+            ),
+        }
+    }
+}
+
+/// `thrusters` if it's a multiple of the order of `symmetry`
+fn thruster_count_checked(symmetry: Symmetry, thrusters: usize) -> anyhow::Result<usize> {
+    ensure!(
+        thrusters.is_multiple_of(symmetry.order()),
+        "{} symmetry needs a multiple of {} thrusters, got {thrusters}",
+        symmetry.name(),
+        symmetry.order()
+    );
+
+    Ok(thrusters)
+}
+
+fn new_arena<R: Dim, C: Dim, Config>(
+    config: Config,
+    execution: Execution,
+    optimizer: OptimizerType,
+) -> anyhow::Result<Box<dyn OptimizationArena + Send + Sync + 'static>>
+where
+    Config: OptimizableConfig<Point<FloatType> = OMatrix<FloatType, R, C>> + Send + Sync + 'static,
+    DefaultAllocator: Allocator<R, C>,
+    OMatrix<FloatType, R, C>: Send + Sync,
+{
+    Ok(match optimizer {
+        OptimizerType::Adam(it) => Box::new(ConfigArena::new(config, it, execution)?),
+        OptimizerType::Momentum(it) => Box::new(ConfigArena::new(config, it, execution)?),
+        OptimizerType::Lbfgs(it) => Box::new(ConfigArena::new(config, it, execution)?),
+        OptimizerType::NelderMead(it) => Box::new(ConfigArena::new(config, it, execution)?),
+    })
+}
+
+impl FromStr for ArenaType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let lower = s.to_ascii_lowercase();
+        let thruster_count = |count: &str| {
+            count
+                .parse::<usize>()
+                .ok()
+                .filter(|&count| count > 0)
+                .ok_or_else(|| format!("Invalid thruster count `{count}` in arena type `{s}`"))
+        };
+
+        if let Some((symmetry, count)) = lower.rsplit_once('-').and_then(|(symmetry, count)| {
+            let symmetry = match symmetry {
+                "symmetrical" => Symmetry::Mirror(Plane::YZ),
+                "unconstrained" => Symmetry::None,
+                other => other.parse().ok()?,
+            };
+            Some((symmetry, count))
+        }) {
+            let thrusters = thruster_count_checked(symmetry, thruster_count(count)?)
+                .map_err(|err| err.to_string())?;
+
+            return Ok(ArenaType::Symmetric {
+                symmetry,
+                thrusters,
+            });
+        }
+
+        ArenaType::ALL
+            .iter()
+            .find(|it| it.name() == lower)
+            .copied()
+            .ok_or_else(|| {
+                format!(
+                    "Unknown arena type `{s}`, expected symmetrical-N, unconstrained-N, \
+                     <symmetry>-N or one of: {}",
+                    ArenaType::ALL.iter().map(ArenaType::name).join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use motor_math::{motor_preformance, FloatType};
+
+    use nalgebra::{dvector, vector, DMatrix, DVector, SVector, Vector3};
+
+    use super::{
+        dynamic::{DynamicOptimization, Plane, Symmetry},
+        mounted::{assigned_mounts, Mount, MountedOptimization, MountingPlate},
+        optimized_parameters,
+        optimizers::{AdamState, OptimizerState, OptimizerType},
+        top_config_order,
+        x3d_dyn::DynamicX3dOptimization,
+        ArenaCheckpoint, ArenaKind, ArenaType, Execution, OptimizableConfig, OptimizationState,
+        PointCheckpoint,
+    };
+    use crate::{
+        constraints::{Envelope, KeepOut, PositionConstraints, Volume},
+        format::test_directory,
+        heuristic::{ScoreResult, ScoreSettings, Unscaled},
+        thruster_models::ThrusterModels,
+    };
+
+    /// The indices of `scores` from best to worst, and only the first `n`
+    fn top(scores: &[FloatType], n: usize) -> Vec<usize> {
+        let mut points = scores
+            .iter()
+            .enumerate()
+            .map(|(idx, &score)| {
+                (
+                    idx,
+                    score,
+                    (),
+                    ScoreResult::<FloatType, Unscaled>::default(),
+                )
+            })
+            .collect::<Vec<_>>();
+        points.sort_by(top_config_order);
+
+        points.iter().take(n).map(|(idx, ..)| *idx).collect()
+    }
+
+    #[test]
+    fn top_configs_are_best_first() {
+        assert_eq!(top(&[-3.0, 1.0, -1.0, 2.0], 4), vec![3, 1, 2, 0]);
+        assert_eq!(top(&[-3.0, 1.0, -1.0, 2.0], 2), vec![3, 1]);
+    }
+
+    #[test]
+    fn top_config_ties_keep_index_order() {
+        assert_eq!(top(&[1.0, 2.0, 1.0, 2.0, 1.0], 5), vec![1, 3, 0, 2, 4]);
+        // Stepping in parallel can hand the points back in any order
+        let mut points = [(4, 1.0), (2, 1.0), (0, 1.0)].map(|(idx, score)| {
+            (
+                idx,
+                score,
+                (),
+                ScoreResult::<FloatType, Unscaled>::default(),
+            )
+        });
+        points.sort_by(top_config_order);
+        assert_eq!(points.map(|(idx, ..)| idx), [0, 2, 4]);
+    }
+
+    #[test]
+    fn nan_scores_are_worst() {
+        let nan = FloatType::NAN;
+        assert_eq!(
+            top(&[nan, -1.0, FloatType::NEG_INFINITY, -nan, 3.0], 5),
+            vec![4, 1, 2, 0, 3]
+        );
+        assert_eq!(top(&[nan, nan], 2), vec![0, 1]);
+    }
+
+    #[test]
+    fn top_n_larger_than_the_arena() {
+        assert_eq!(top(&[0.5, 1.5], 10), vec![1, 0]);
+        assert!(top(&[], 3).is_empty());
+    }
+
+    #[test]
+    fn seeded_runs_match_across_executions() {
+        let motor_data = ThrusterModels::from(
+            motor_preformance::read_motor_data_from_path(concat!(
+                env!("CARGO_MANIFEST_DIR"),
+                "/motor_data.csv"
+            ))
+            .expect("Read motor data"),
+        );
+
+        let run = |execution| {
+            let mut arena = ArenaType::Symmetrical3
+                .new_arena(execution, OptimizerType::default())
+                .expect("Create arena");
+            arena.reset(8, ScoreSettings::default(), 7);
+            for _ in 0..20 {
+                arena.step(&motor_data).for_each(drop);
+            }
+
+            // Compared bit for bit, any difference in the order of float operations shows
+            arena
+                .step(&motor_data)
+                .map(|it| {
+                    let parameters = it.parameters.iter().map(|it| it.to_bits()).collect();
+                    (it.idx, it.score.to_bits(), parameters)
+                })
+                .collect::<Vec<(usize, u64, Vec<u64>)>>()
+        };
+
+        let serial = run(Execution::Serial);
+        assert_eq!(serial.len(), 8);
+        assert_eq!(serial, run(Execution::Rayon));
+        assert_eq!(serial, run(Execution::Threads(3)));
+    }
+
+    #[test]
+    fn symmetrical_copies_mirror_back_to_their_free_thruster() {
+        let optimization = DynamicOptimization::symmetrical(6);
+        assert_eq!(optimization.parameter_shape(), (6, 3));
+
+        let vector = vector![0.3, -0.2, 0.7];
+        let mirrored = optimization.symmetry.transform(1, vector);
+        assert_eq!(mirrored, vector![-0.3, -0.2, 0.7]);
+        assert_eq!(optimization.symmetry.transform(1, mirrored), vector);
+        assert_eq!(optimization.symmetry.inverse(1, mirrored), vector);
+
+        for id in 0..3 {
+            assert_eq!(
+                optimization.thruster_slot(&id),
+                optimization.thruster_slot(&(id + 3))
+            );
+        }
+    }
+
+    #[test]
+    fn symmetry_images_round_trip() {
+        let symmetries = [
+            Symmetry::None,
+            Symmetry::Mirror(Plane::XY),
+            Symmetry::Mirror(Plane::XZ),
+            Symmetry::Mirror(Plane::YZ),
+            Symmetry::DoubleMirror,
+            Symmetry::Rotational(1),
+            Symmetry::Rotational(3),
+            Symmetry::Rotational(4),
+            Symmetry::Rotational(5),
+        ];
+        let vector = vector![0.3, -0.2, 0.7];
+
+        for symmetry in symmetries {
+            assert_eq!(symmetry.name().parse(), Ok(symmetry));
+            assert_eq!(symmetry.transform(0, vector), vector, "{symmetry:?}");
+
+            let images = (0..symmetry.order())
+                .map(|image| symmetry.transform(image, vector))
+                .collect::<Vec<_>>();
+            for (image, &copy) in images.iter().enumerate() {
+                let back = symmetry.inverse(image, copy);
+                assert!((back - vector).norm() < 1e-9, "{symmetry:?} {image}");
+                let there = symmetry.transform(image, symmetry.inverse(image, vector));
+                assert!((there - vector).norm() < 1e-9, "{symmetry:?} {image}");
+                assert!(
+                    (copy.norm() - vector.norm()).abs() < 1e-9,
+                    "{symmetry:?} {image}"
+                );
+            }
+            for (a, b) in images.iter().tuple_combinations() {
+                assert!((a - b).norm() > 1e-3, "{symmetry:?} has repeated images");
+            }
+        }
+    }
+
+    #[test]
+    fn arena_names_round_trip() {
+        let symmetrical = ArenaType::Symmetric {
+            symmetry: Symmetry::Mirror(Plane::YZ),
+            thrusters: 8,
+        };
+        assert_eq!("symmetrical-8".parse(), Ok(symmetrical));
+        assert_eq!(
+            "unconstrained-5".parse(),
+            Ok(ArenaType::Symmetric {
+                symmetry: Symmetry::None,
+                thrusters: 5,
+            })
+        );
+
+        let arenas = [
+            symmetrical,
+            ArenaType::Symmetric {
+                symmetry: Symmetry::Rotational(3),
+                thrusters: 6,
+            },
+            ArenaType::Symmetric {
+                symmetry: Symmetry::DoubleMirror,
+                thrusters: 8,
+            },
+        ];
+        for arena in ArenaType::ALL.iter().copied().chain(arenas) {
+            assert_eq!(arena.name().parse(), Ok(arena), "{arena:?}");
+        }
+
+        assert!("symmetrical-7".parse::<ArenaType>().is_err());
+        assert!("rotational-3-4".parse::<ArenaType>().is_err());
+        assert!("mirror-xz-0".parse::<ArenaType>().is_err());
+    }
+
+    #[test]
+    fn checkpoints_round_trip() {
+        let arena_type = ArenaType::Symmetric {
+            symmetry: Symmetry::Mirror(Plane::XY),
+            thrusters: 8,
+        };
+        let point = DMatrix::from_fn(6, 4, |row, col| (row * 4 + col) as FloatType / 10.0 - 1.0);
+        let mut state = OptimizationState::new(
+            point,
+            OptimizerState::from(AdamState {
+                first_moment: DVector::from_fn(24, |idx, _| idx as FloatType * 0.01),
+                second_moment: DVector::from_element(24, 1e-7),
+            }),
+        );
+        state.time = 12;
+        let checkpoint = ArenaCheckpoint {
+            arena: ArenaKind::Type(arena_type),
+            heuristic: ScoreSettings::default(),
+            hyperparameters: Default::default(),
+            constraints: Default::default(),
+            seed: 7,
+            points: vec![PointCheckpoint {
+                idx: 3,
+                score: FloatType::NEG_INFINITY,
+                state,
+                score_result: Default::default(),
+            }],
+        };
+        let expected = serde_json::to_value(&checkpoint).unwrap();
+
+        let directory = test_directory("checkpoint-round-trip");
+        for name in ["checkpoint.toml", "checkpoint.json"] {
+            let path = directory.join(name);
+            checkpoint.save(&path).unwrap();
+
+            let mut arena = arena_type
+                .new_arena(Execution::Serial, OptimizerType::default())
+                .unwrap();
+            arena
+                .restore(ArenaCheckpoint::load(&path).unwrap())
+                .unwrap();
+            let restored = arena.checkpoint();
+
+            assert_eq!(restored.points[0].score, FloatType::NEG_INFINITY);
+            assert_eq!(serde_json::to_value(&restored).unwrap(), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn new_convergence_criteria_reactivate_done_points() {
+        let arena_type = ArenaType::Symmetric {
+            symmetry: Symmetry::None,
+            thrusters: 2,
+        };
+        let mut arena = arena_type
+            .new_arena(Execution::Serial, OptimizerType::default())
+            .unwrap();
+        let mut state = OptimizationState::new(
+            DMatrix::from_element(6, 2, 0.5),
+            OptimizerState::from(AdamState {
+                first_moment: DVector::zeros(12),
+                second_moment: DVector::zeros(12),
+            }),
+        );
+        state.time = 100;
+        state.done = true;
+        let mut checkpoint = arena.checkpoint();
+        checkpoint.points = vec![PointCheckpoint {
+            idx: 0,
+            score: 1.0,
+            state,
+            score_result: Default::default(),
+        }];
+        arena.restore(checkpoint).unwrap();
+
+        let mut hyperparameters = arena.hyperparameters();
+        hyperparameters.step_size *= 2.0;
+        arena.set_hyperparameters(hyperparameters);
+        assert!(arena.converged());
+
+        hyperparameters.convergence.max_iterations *= 2;
+        arena.set_hyperparameters(hyperparameters);
+        assert!(!arena.converged());
+    }
+
+    #[test]
+    fn checkpoints_only_restore_into_their_arena() {
+        let checkpoint = |arena_type: ArenaType| {
+            arena_type
+                .new_arena(Execution::Serial, OptimizerType::default())
+                .unwrap()
+                .checkpoint()
+        };
+        let mirror_xy = ArenaType::Symmetric {
+            symmetry: Symmetry::Mirror(Plane::XY),
+            thrusters: 8,
+        };
+        let mirror_xz = ArenaType::Symmetric {
+            symmetry: Symmetry::Mirror(Plane::XZ),
+            thrusters: 8,
+        };
+
+        let mut arena = mirror_xz
+            .new_arena(Execution::Serial, OptimizerType::default())
+            .unwrap();
+        assert!(arena.restore(checkpoint(mirror_xy)).is_err());
+        assert!(arena.restore(checkpoint(mirror_xz)).is_ok());
+
+        // The fixed size symmetrical arena means the same as a 6 thruster YZ mirror
+        let mirror_yz = "mirror-yz-6".parse::<ArenaType>().unwrap();
+        let mut arena = mirror_yz
+            .new_arena(Execution::Serial, OptimizerType::default())
+            .unwrap();
+        assert!(arena.restore(checkpoint(ArenaType::Symmetrical3)).is_ok());
+        assert!(arena
+            .restore(checkpoint(ArenaType::Unconstrained6))
+            .is_err());
+    }
+
+    #[test]
+    fn thread_pools_are_only_built_for_threads() {
+        assert!(Execution::Serial.thread_pool().unwrap().is_none());
+        assert!(Execution::Threads(2).thread_pool().unwrap().is_some());
+    }
+
+    fn plate() -> MountingPlate {
+        MountingPlate {
+            mounts: (0..4)
+                .map(|idx| Mount {
+                    position: [idx as FloatType * 0.1, 0.0, 0.0],
+                    axis: [0.0, 0.0, 1.0],
+                    cone_angle: 45.0,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn mounts_are_never_shared() {
+        let config = MountedOptimization::new(&plate(), 3).unwrap();
+        // Only the first mount is allowed, the others still have to go somewhere
+        let constraints = PositionConstraints {
+            envelope: Some(Envelope {
+                min: [-0.05; 3],
+                max: [0.05; 3],
+            }),
+            ..Default::default()
+        };
+
+        // Every thruster asks for the same mount
+        let point = DMatrix::from_fn(4, 3, |row, _| [0.0, 0.0, 1.0, 2.0][row]);
+        let point = config.normalise_point(point, &constraints);
+
+        let mut mounts = assigned_mounts(&point);
+        assert_eq!(mounts[0], 0);
+        mounts.sort();
+        mounts.dedup();
+        assert_eq!(mounts.len(), 3, "{point}");
+    }
+
+    #[test]
+    fn taken_mounts_move_to_the_closest_free_mount() {
+        // Mount order has nothing to do with where the mounts are
+        let positions = [
+            [0.0, 0.0, 0.0],
+            [0.5, 0.0, 0.0],
+            [0.0, 0.1, 0.0],
+            [-0.3, 0.0, 0.0],
+        ];
+        let plate = MountingPlate {
+            mounts: positions
+                .iter()
+                .map(|&position| Mount {
+                    position,
+                    axis: [0.0, 0.0, 1.0],
+                    cone_angle: 45.0,
+                })
+                .collect(),
+        };
+        let config = MountedOptimization::new(&plate, 3).unwrap();
+
+        let point = DMatrix::from_fn(4, 3, |row, _| [0.0, 0.0, 1.0, 0.0][row]);
+        let point = config.normalise_point(point, &PositionConstraints::default());
+
+        assert_eq!(assigned_mounts(&point), vec![0, 2, 3]);
+    }
+
+    #[test]
+    fn zero_orientations_point_along_the_mount() {
+        let config = MountedOptimization::new(&plate(), 2).unwrap();
+        let point = DMatrix::from_columns(&[
+            dvector![0.0, 0.0, 0.0, 0.0],
+            dvector![FloatType::NAN, 0.0, 0.0, 1.0],
+        ]);
+        let point = config.normalise_point(point, &PositionConstraints::default());
+
+        for idx in 0..2 {
+            assert_eq!(
+                point.fixed_view::<3, 1>(0, idx).into_owned(),
+                Vector3::z(),
+                "{point}"
+            );
+        }
+    }
+
+    #[test]
+    fn optimizers_leave_mounts_alone() {
+        let config = MountedOptimization::new(&plate(), 2).unwrap();
+        let point =
+            DMatrix::from_columns(&[dvector![0.1, 0.0, 1.0, 3.0], dvector![0.0, 0.1, 1.0, 1.0]]);
+
+        assert_eq!(config.optimized_rows(), 3);
+        assert_eq!(
+            optimized_parameters(&point, config.optimized_rows()).as_slice(),
+            &[0.1, 0.0, 1.0, 0.0, 0.1, 1.0]
+        );
+    }
+
+    #[test]
+    fn x3d_positions_satisfy_the_frame_and_constraints() {
+        let config = DynamicX3dOptimization {
+            width: 0.3,
+            length: 0.3,
+            height: 0.3,
+        };
+        // Pushing the thruster straight out of the sphere leaves the frame
+        let constraints = PositionConstraints {
+            keep_out: vec![KeepOut {
+                name: "Battery".to_owned(),
+                volume: Volume::Sphere {
+                    center: [0.0; 3],
+                    radius: 0.35,
+                },
+            }],
+            ..Default::default()
+        };
+
+        let point = SVector::<FloatType, 6>::from([0.2, 0.1, 0.05, 0.0, 0.0, 1.0]);
+        let point = config.normalise_point(point, &constraints);
+        let position = point.fixed_rows::<3>(0).into_owned();
+
+        assert!(
+            position.iter().all(|it| it.abs() <= 0.3 + 1e-12),
+            "{position}"
+        );
+        assert!(constraints.allows(position), "{position}");
     }
 }
@@ -0,0 +1,684 @@
+//! Update rules used by the optimization arenas
+//!
+//! Optimizers work on the flattened parameters of a point and always maximize the score.
+
+use std::{collections::VecDeque, fmt::Debug, str::FromStr};
+
+use anyhow::bail;
+use itertools::Itertools;
+use motor_math::FloatType;
+use nalgebra::DVector;
+use serde::{Deserialize, Serialize};
+
+use crate::format::{dvector, non_finite};
+
+/// The function being optimized
+pub trait Objective {
+    fn score(&self, point: &DVector<FloatType>) -> FloatType;
+    fn score_gradient(&self, point: &DVector<FloatType>) -> (FloatType, DVector<FloatType>);
+    /// Projects a point back onto the valid parameter space
+    fn normalise(&self, point: DVector<FloatType>) -> DVector<FloatType>;
+}
+
+pub struct Step {
+    pub point: DVector<FloatType>,
+    pub convergence: Convergence,
+}
+
+/// How close an optimizer is to a stationary point
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Convergence {
+    /// Norm of the gradient at the previous point, compared against the gradient tolerance
+    GradientNorm(FloatType),
+    /// Gradient free methods have no gradient to compare, so they decide for themselves
+    Converged(bool),
+}
+
+impl Convergence {
+    pub fn is_converged(&self, gradient_tolerance: FloatType) -> bool {
+        match *self {
+            Convergence::GradientNorm(norm) => norm < gradient_tolerance,
+            Convergence::Converged(converged) => converged,
+        }
+    }
+}
+
+pub trait Optimizer: Debug + Clone + Send + Sync + 'static {
+    type State: Debug
+        + Clone
+        + Send
+        + Sync
+        + Into<OptimizerState>
+        + TryFrom<OptimizerState, Error = anyhow::Error>;
+
+    fn initial_state(&self, point: &DVector<FloatType>) -> Self::State;
+
+    /// Takes a single step from `point`, `time` starts at 1 for the first step
+    fn step(
+        &self,
+        point: &DVector<FloatType>,
+        state: &mut Self::State,
+        time: i32,
+        step_size: FloatType,
+        objective: &impl Objective,
+    ) -> Step;
+
+    /// Called when the objective changes, for example when the heuristic is edited
+    fn objective_changed(&self, _state: &mut Self::State) {}
+}
+
+/// The per point state of any optimizer, used for checkpoints
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum OptimizerState {
+    Adam(AdamState),
+    Momentum(MomentumState),
+    Lbfgs(LbfgsState),
+    NelderMead(NelderMeadState),
+}
+
+macro_rules! optimizer_state {
+    ($variant:ident, $state:ident) => {
+        impl From<$state> for OptimizerState {
+            fn from(state: $state) -> Self {
+                OptimizerState::$variant(state)
+            }
+        }
+
+        impl TryFrom<OptimizerState> for $state {
+            type Error = anyhow::Error;
+
+            fn try_from(state: OptimizerState) -> anyhow::Result<Self> {
+                match state {
+                    OptimizerState::$variant(state) => Ok(state),
+                    other => bail!(
+                        "Expected {} optimizer state but found {}",
+                        stringify!($variant),
+                        other.name()
+                    ),
+                }
+            }
+        }
+    };
+}
+
+optimizer_state!(Adam, AdamState);
+optimizer_state!(Momentum, MomentumState);
+optimizer_state!(Lbfgs, LbfgsState);
+optimizer_state!(NelderMead, NelderMeadState);
+
+impl OptimizerState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OptimizerState::Adam(_) => "Adam",
+            OptimizerState::Momentum(_) => "Momentum",
+            OptimizerState::Lbfgs(_) => "Lbfgs",
+            OptimizerState::NelderMead(_) => "NelderMead",
+        }
+    }
+}
+
+/// Adam without weight decay
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Adam {
+    pub beta_1: FloatType,
+    pub beta_2: FloatType,
+    pub epsilon: FloatType,
+}
+
+impl Default for Adam {
+    fn default() -> Self {
+        Self {
+            beta_1: 0.9,
+            beta_2: 0.999,
+            epsilon: 1e-10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AdamState {
+    #[serde(with = "dvector")]
+    pub first_moment: DVector<FloatType>,
+    #[serde(with = "dvector")]
+    pub second_moment: DVector<FloatType>,
+}
+
+impl Optimizer for Adam {
+    type State = AdamState;
+
+    fn initial_state(&self, point: &DVector<FloatType>) -> Self::State {
+        AdamState {
+            first_moment: DVector::zeros(point.len()),
+            second_moment: DVector::zeros(point.len()),
+        }
+    }
+
+    fn step(
+        &self,
+        point: &DVector<FloatType>,
+        state: &mut Self::State,
+        time: i32,
+        step_size: FloatType,
+        objective: &impl Objective,
+    ) -> Step {
+        let (_, grad) = objective.score_gradient(point);
+
+        let Adam {
+            beta_1,
+            beta_2,
+            epsilon,
+        } = *self;
+
+        state.first_moment = beta_1 * &state.first_moment + (1.0 - beta_1) * &grad;
+        state.second_moment =
+            beta_2 * &state.second_moment + (1.0 - beta_2) * grad.component_mul(&grad);
+
+        let first_moment_hat = &state.first_moment / (1.0 - beta_1.powi(time));
+        let second_moment_hat = &state.second_moment / (1.0 - beta_2.powi(time));
+
+        let new_point = point
+            + step_size
+                * first_moment_hat
+                    .component_div(&second_moment_hat.map(|it| it.sqrt()).add_scalar(epsilon));
+
+        Step {
+            point: objective.normalise(new_point),
+            convergence: Convergence::GradientNorm(grad.norm()),
+        }
+    }
+}
+
+/// Gradient ascent with dampened heavy ball momentum
+///
+/// The velocity is an average of past gradients, and steps are divided by the average gradient
+/// norm so a step is at most `step_size` long like Adam's, however large the gradient.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Momentum {
+    pub momentum: FloatType,
+    pub epsilon: FloatType,
+}
+
+impl Default for Momentum {
+    fn default() -> Self {
+        Self {
+            momentum: 0.9,
+            epsilon: 1e-10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MomentumState {
+    #[serde(with = "dvector")]
+    pub velocity: DVector<FloatType>,
+    /// Average of the norms of past gradients
+    #[serde(default)]
+    pub gradient_scale: FloatType,
+}
+
+impl Optimizer for Momentum {
+    type State = MomentumState;
+
+    fn initial_state(&self, point: &DVector<FloatType>) -> Self::State {
+        MomentumState {
+            velocity: DVector::zeros(point.len()),
+            gradient_scale: 0.0,
+        }
+    }
+
+    fn step(
+        &self,
+        point: &DVector<FloatType>,
+        state: &mut Self::State,
+        _time: i32,
+        step_size: FloatType,
+        objective: &impl Objective,
+    ) -> Step {
+        let (_, grad) = objective.score_gradient(point);
+
+        let Momentum { momentum, epsilon } = *self;
+
+        // Both averages are biased towards zero early on by the same factor, which cancels out
+        state.velocity = momentum * &state.velocity + (1.0 - momentum) * &grad;
+        state.gradient_scale = momentum * state.gradient_scale + (1.0 - momentum) * grad.norm();
+        let new_point = point + step_size * &state.velocity / (state.gradient_scale + epsilon);
+
+        Step {
+            point: objective.normalise(new_point),
+            convergence: Convergence::GradientNorm(grad.norm()),
+        }
+    }
+}
+
+/// Limited memory BFGS with a backtracking line search
+///
+/// The step size is only used to scale the first step before any curvature is known.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Lbfgs {
+    /// Number of past updates used to approximate the hessian
+    pub memory: usize,
+    /// Fraction of the predicted improvement a step must achieve to be accepted
+    pub sufficient_increase: FloatType,
+    /// Maximum number of times the step is halved before giving up
+    pub max_line_search: usize,
+}
+
+impl Default for Lbfgs {
+    fn default() -> Self {
+        Self {
+            memory: 8,
+            sufficient_increase: 1e-4,
+            max_line_search: 20,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LbfgsState {
+    /// Position and gradient differences of past steps
+    pub history: VecDeque<LbfgsPair>,
+    /// The point and gradient of the previous step
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub previous: Option<LbfgsPair>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LbfgsPair {
+    #[serde(with = "dvector")]
+    pub point: DVector<FloatType>,
+    #[serde(with = "dvector")]
+    pub gradient: DVector<FloatType>,
+}
+
+impl Optimizer for Lbfgs {
+    type State = LbfgsState;
+
+    fn initial_state(&self, _point: &DVector<FloatType>) -> Self::State {
+        LbfgsState::default()
+    }
+
+    fn step(
+        &self,
+        point: &DVector<FloatType>,
+        state: &mut Self::State,
+        _time: i32,
+        step_size: FloatType,
+        objective: &impl Objective,
+    ) -> Step {
+        let (score, grad) = objective.score_gradient(point);
+
+        // Everything below minimizes the negated score
+        if let Some(previous) = state.previous.take() {
+            let s = point - previous.point;
+            let y = previous.gradient - &grad;
+
+            if s.dot(&y) > FloatType::EPSILON {
+                state.history.push_back(LbfgsPair {
+                    point: s,
+                    gradient: y,
+                });
+                while state.history.len() > self.memory {
+                    state.history.pop_front();
+                }
+            }
+        }
+
+        let mut direction = -&grad;
+        let mut alphas = Vec::with_capacity(state.history.len());
+        for LbfgsPair {
+            point: s,
+            gradient: y,
+        } in state.history.iter().rev()
+        {
+            let rho = 1.0 / y.dot(s);
+            let alpha = rho * s.dot(&direction);
+            direction -= alpha * y;
+            alphas.push((rho, alpha));
+        }
+
+        let gamma = match state.history.back() {
+            Some(LbfgsPair {
+                point: s,
+                gradient: y,
+            }) => s.dot(y) / y.dot(y),
+            None => step_size / grad.norm().max(FloatType::EPSILON),
+        };
+        direction *= gamma;
+
+        for (
+            LbfgsPair {
+                point: s,
+                gradient: y,
+            },
+            (rho, alpha),
+        ) in state.history.iter().zip(alphas.iter().rev())
+        {
+            let beta = rho * y.dot(&direction);
+            direction += (alpha - beta) * s;
+        }
+        direction = -direction;
+
+        let mut step = 1.0;
+        let mut new_point = None;
+        for _ in 0..self.max_line_search {
+            let candidate = objective.normalise(point + step * &direction);
+            let predicted = grad.dot(&(&candidate - point)).max(0.0);
+
+            if objective.score(&candidate) >= score + self.sufficient_increase * predicted {
+                new_point = Some(candidate);
+                break;
+            }
+
+            step *= 0.5;
+        }
+
+        let new_point = match new_point {
+            Some(new_point) => {
+                state.previous = Some(LbfgsPair {
+                    point: point.clone(),
+                    gradient: grad.clone(),
+                });
+                new_point
+            }
+            None => {
+                // The curvature estimate is no longer useful, start over from plain gradient ascent
+                state.history.clear();
+                point.clone()
+            }
+        };
+
+        Step {
+            point: new_point,
+            convergence: Convergence::GradientNorm(grad.norm()),
+        }
+    }
+
+    fn objective_changed(&self, state: &mut Self::State) {
+        *state = LbfgsState::default();
+    }
+}
+
+/// The gradient free Nelder-Mead simplex method
+///
+/// The step size is not used, the initial simplex is sized by `simplex_size`. It has converged
+/// once the simplex has both collapsed to within `diameter_tolerance` of the best vertex and
+/// its scores are within `score_tolerance`, the gradient tolerance is not used.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NelderMead {
+    /// Distance from the starting point to the other vertices of the initial simplex
+    pub simplex_size: FloatType,
+    pub reflection: FloatType,
+    pub expansion: FloatType,
+    pub contraction: FloatType,
+    pub shrink: FloatType,
+    pub diameter_tolerance: FloatType,
+    pub score_tolerance: FloatType,
+}
+
+impl Default for NelderMead {
+    fn default() -> Self {
+        Self {
+            simplex_size: 0.05,
+            reflection: 1.0,
+            expansion: 2.0,
+            contraction: 0.5,
+            shrink: 0.5,
+            diameter_tolerance: 1e-5,
+            score_tolerance: 1e-6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NelderMeadState {
+    /// Vertices sorted from best to worst
+    pub simplex: Vec<Vertex>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Vertex {
+    #[serde(with = "dvector")]
+    pub point: DVector<FloatType>,
+    #[serde(with = "non_finite")]
+    pub score: FloatType,
+}
+
+impl Vertex {
+    fn new(objective: &impl Objective, point: DVector<FloatType>) -> Self {
+        let point = objective.normalise(point);
+        let score = objective.score(&point);
+
+        Self { point, score }
+    }
+}
+
+impl Optimizer for NelderMead {
+    type State = NelderMeadState;
+
+    fn initial_state(&self, _point: &DVector<FloatType>) -> Self::State {
+        NelderMeadState::default()
+    }
+
+    fn step(
+        &self,
+        point: &DVector<FloatType>,
+        state: &mut Self::State,
+        _time: i32,
+        _step_size: FloatType,
+        objective: &impl Objective,
+    ) -> Step {
+        let simplex = &mut state.simplex;
+
+        if simplex.is_empty() {
+            simplex.push(Vertex::new(objective, point.clone()));
+            for idx in 0..point.len() {
+                let mut vertex = point.clone();
+                vertex[idx] += self.simplex_size;
+                simplex.push(Vertex::new(objective, vertex));
+            }
+        } else {
+            let worst_idx = simplex.len() - 1;
+            let worst = simplex[worst_idx].clone();
+            let second_worst_score = simplex[worst_idx.saturating_sub(1)].score;
+            let best_score = simplex[0].score;
+
+            let centroid = simplex[..worst_idx]
+                .iter()
+                .fold(DVector::zeros(point.len()), |acc, vertex| {
+                    acc + &vertex.point
+                })
+                / worst_idx.max(1) as FloatType;
+
+            let reflected = Vertex::new(
+                objective,
+                &centroid + self.reflection * (&centroid - &worst.point),
+            );
+
+            if reflected.score > best_score {
+                let expanded = Vertex::new(
+                    objective,
+                    &centroid + self.expansion * (&reflected.point - &centroid),
+                );
+
+                simplex[worst_idx] = if expanded.score > reflected.score {
+                    expanded
+                } else {
+                    reflected
+                };
+            } else if reflected.score > second_worst_score {
+                simplex[worst_idx] = reflected;
+            } else {
+                // Contract towards the better of the reflected and worst points
+                let outside = if reflected.score > worst.score {
+                    reflected
+                } else {
+                    worst
+                };
+                let contracted = Vertex::new(
+                    objective,
+                    &centroid + self.contraction * (&outside.point - &centroid),
+                );
+
+                if contracted.score > outside.score {
+                    simplex[worst_idx] = contracted;
+                } else {
+                    let best = simplex[0].point.clone();
+                    for vertex in &mut simplex[1..] {
+                        *vertex =
+                            Vertex::new(objective, &best + self.shrink * (&vertex.point - &best));
+                    }
+                }
+            }
+        }
+
+        simplex.sort_by(|a, b| FloatType::total_cmp(&a.score, &b.score).reverse());
+
+        let best = &simplex[0];
+        let worst = &simplex[simplex.len() - 1];
+        let diameter = simplex
+            .iter()
+            .map(|vertex| (&vertex.point - &best.point).norm())
+            .fold(0.0, FloatType::max);
+        let converged = diameter < self.diameter_tolerance
+            && (best.score - worst.score).abs() < self.score_tolerance;
+
+        Step {
+            point: best.point.clone(),
+            convergence: Convergence::Converged(converged),
+        }
+    }
+
+    fn objective_changed(&self, state: &mut Self::State) {
+        *state = NelderMeadState::default();
+    }
+}
+
+/// Selects the optimizer used by an arena along with its settings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OptimizerType {
+    Adam(Adam),
+    Momentum(Momentum),
+    Lbfgs(Lbfgs),
+    NelderMead(NelderMead),
+}
+
+impl OptimizerType {
+    /// Every optimizer with its default settings
+    pub fn all() -> [OptimizerType; 4] {
+        [
+            OptimizerType::Adam(Default::default()),
+            OptimizerType::Momentum(Default::default()),
+            OptimizerType::Lbfgs(Default::default()),
+            OptimizerType::NelderMead(Default::default()),
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OptimizerType::Adam(_) => "adam",
+            OptimizerType::Momentum(_) => "momentum",
+            OptimizerType::Lbfgs(_) => "lbfgs",
+            OptimizerType::NelderMead(_) => "nelder-mead",
+        }
+    }
+}
+
+impl Default for OptimizerType {
+    fn default() -> Self {
+        OptimizerType::Adam(Default::default())
+    }
+}
+
+impl FromStr for OptimizerType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        OptimizerType::all()
+            .into_iter()
+            .find(|it| it.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown optimizer `{s}`, expected one of: {}",
+                    OptimizerType::all()
+                        .iter()
+                        .map(OptimizerType::name)
+                        .join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use motor_math::FloatType;
+    use nalgebra::{dvector, DVector};
+
+    use super::{Momentum, NelderMead, Objective, Optimizer};
+
+    /// A paraboloid peaking at (1, -2)
+    struct Paraboloid;
+
+    impl Objective for Paraboloid {
+        fn score(&self, point: &DVector<FloatType>) -> FloatType {
+            -(point - dvector![1.0, -2.0]).norm_squared()
+        }
+
+        fn score_gradient(&self, point: &DVector<FloatType>) -> (FloatType, DVector<FloatType>) {
+            (self.score(point), -2.0 * (point - dvector![1.0, -2.0]))
+        }
+
+        fn normalise(&self, point: DVector<FloatType>) -> DVector<FloatType> {
+            point
+        }
+    }
+
+    #[test]
+    fn nelder_mead_converges_on_its_own_tolerances() {
+        let optimizer = NelderMead::default();
+        let mut point = dvector![0.0, 0.0];
+        let mut state = optimizer.initial_state(&point);
+
+        let step = optimizer.step(&point, &mut state, 1, 0.0, &Paraboloid);
+        assert!(!step.convergence.is_converged(FloatType::INFINITY));
+
+        for time in 1..=1000 {
+            let step = optimizer.step(&point, &mut state, time, 0.0, &Paraboloid);
+            point = step.point;
+
+            // A tiny gradient tolerance must not stop it
+            if step.convergence.is_converged(0.0) {
+                assert!((&point - dvector![1.0, -2.0]).norm() < 1e-4, "{point}");
+                return;
+            }
+        }
+        panic!("Nelder-Mead did not converge, reached {point}");
+    }
+
+    #[test]
+    fn momentum_steps_are_bounded_by_the_step_size() {
+        let optimizer = Momentum::default();
+        let step_size = 0.01;
+
+        // Far away the gradient is huge, but steps stay the length of the step size
+        let mut point = dvector![1000.0, 1000.0];
+        let mut state = optimizer.initial_state(&point);
+        for time in 1..=50 {
+            let step = optimizer.step(&point, &mut state, time, step_size, &Paraboloid);
+            assert!((&step.point - &point).norm() <= step_size * (1.0 + 1e-9));
+            point = step.point;
+        }
+
+        let mut point = dvector![0.0, 0.0];
+        let mut state = optimizer.initial_state(&point);
+        for time in 1..=2000 {
+            point = optimizer
+                .step(&point, &mut state, time, step_size, &Paraboloid)
+                .point;
+        }
+        assert!((&point - dvector![1.0, -2.0]).norm() < 0.05, "{point}");
+    }
+}
@@ -0,0 +1,226 @@
+use std::str::FromStr;
+
+use itertools::Itertools;
+use motor_math::FloatType;
+use serde::{Deserialize, Serialize};
+
+use super::ConvergenceCriteria;
+
+/// The tunable parameters of an optimization arena
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Hyperparameters {
+    /// The step size/learn rate
+    pub step_size: FloatType,
+    /// How the step size changes over the lifetime of a point
+    pub schedule: StepSchedule,
+    /// When points are retired
+    pub convergence: ConvergenceCriteria,
+    /// Number of steps between searches for the best spin direction of each thruster, 0 leaves
+    /// every thruster spinning clockwise
+    pub spin_interval: i32,
+    /// Number of steps between searches for better discrete choices, such as which mount each
+    /// thruster is bolted to, 0 disables them
+    pub discrete_interval: i32,
+}
+
+impl Default for Hyperparameters {
+    fn default() -> Self {
+        Self {
+            step_size: 0.002,
+            schedule: StepSchedule::Constant,
+            convergence: ConvergenceCriteria::default(),
+            spin_interval: 0,
+            discrete_interval: 25,
+        }
+    }
+}
+
+impl Hyperparameters {
+    /// The step size to use for the `time`th step of a point, starting at 1
+    pub fn step_size(&self, time: i32) -> FloatType {
+        self.schedule.step_size(self.step_size, time)
+    }
+}
+
+/// Step size schedules, all times are measured in steps of an individual point
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum StepSchedule {
+    Constant,
+    /// Decays the step size to `min_ratio` of its initial value along a half cosine
+    Cosine {
+        period: i32,
+        min_ratio: FloatType,
+    },
+    /// Multiplies the step size by `factor` every `interval` steps
+    StepDecay {
+        interval: i32,
+        factor: FloatType,
+    },
+    /// Cosine decay that restarts every `period` steps, with each period `period_multiplier`
+    /// times longer than the last
+    WarmRestarts {
+        period: i32,
+        period_multiplier: FloatType,
+        min_ratio: FloatType,
+    },
+}
+
+impl StepSchedule {
+    pub fn all() -> [StepSchedule; 4] {
+        [
+            StepSchedule::Constant,
+            StepSchedule::Cosine {
+                period: 1000,
+                min_ratio: 0.01,
+            },
+            StepSchedule::StepDecay {
+                interval: 250,
+                factor: 0.5,
+            },
+            StepSchedule::WarmRestarts {
+                period: 250,
+                period_multiplier: 2.0,
+                min_ratio: 0.01,
+            },
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StepSchedule::Constant => "constant",
+            StepSchedule::Cosine { .. } => "cosine",
+            StepSchedule::StepDecay { .. } => "step-decay",
+            StepSchedule::WarmRestarts { .. } => "warm-restarts",
+        }
+    }
+
+    pub fn step_size(&self, step_size: FloatType, time: i32) -> FloatType {
+        let elapsed = (time - 1).max(0);
+
+        match *self {
+            StepSchedule::Constant => step_size,
+            StepSchedule::Cosine { period, min_ratio } => {
+                let period = period.max(1);
+                cosine_decay(
+                    step_size,
+                    min_ratio,
+                    elapsed.min(period) as FloatType,
+                    period as FloatType,
+                )
+            }
+            StepSchedule::StepDecay { interval, factor } => {
+                step_size * factor.powi(elapsed / interval.max(1))
+            }
+            StepSchedule::WarmRestarts {
+                period,
+                period_multiplier,
+                min_ratio,
+            } => {
+                let mut elapsed = elapsed as FloatType;
+                let mut period = period.max(1) as FloatType;
+                while elapsed >= period {
+                    elapsed -= period;
+                    period *= period_multiplier.max(1.0);
+                }
+
+                cosine_decay(step_size, min_ratio, elapsed, period)
+            }
+        }
+    }
+}
+
+fn cosine_decay(
+    step_size: FloatType,
+    min_ratio: FloatType,
+    elapsed: FloatType,
+    period: FloatType,
+) -> FloatType {
+    let progress = elapsed / period;
+    let min = step_size * min_ratio;
+
+    min + (step_size - min) * 0.5 * (1.0 + (core::f64::consts::PI as FloatType * progress).cos())
+}
+
+impl FromStr for StepSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        StepSchedule::all()
+            .into_iter()
+            .find(|it| it.name().eq_ignore_ascii_case(s))
+            .ok_or_else(|| {
+                format!(
+                    "Unknown schedule `{s}`, expected one of: {}",
+                    StepSchedule::all()
+                        .iter()
+                        .map(StepSchedule::name)
+                        .join(", ")
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use motor_math::FloatType;
+
+    use super::StepSchedule;
+
+    fn assert_close(actual: FloatType, expected: FloatType) {
+        assert!((actual - expected).abs() < 1e-12, "{actual} != {expected}");
+    }
+
+    #[test]
+    fn cosine_decays_over_one_period() {
+        let schedule = StepSchedule::Cosine {
+            period: 100,
+            min_ratio: 0.1,
+        };
+
+        assert_close(schedule.step_size(1.0, 1), 1.0);
+        assert_close(schedule.step_size(1.0, 51), 0.55);
+        assert_close(schedule.step_size(1.0, 101), 0.1);
+        // Stays at the minimum after the period
+        assert_close(schedule.step_size(1.0, 500), 0.1);
+    }
+
+    #[test]
+    fn step_decay_drops_at_each_interval() {
+        let schedule = StepSchedule::StepDecay {
+            interval: 10,
+            factor: 0.5,
+        };
+
+        assert_close(schedule.step_size(2.0, 1), 2.0);
+        assert_close(schedule.step_size(2.0, 10), 2.0);
+        assert_close(schedule.step_size(2.0, 11), 1.0);
+        assert_close(schedule.step_size(2.0, 20), 1.0);
+        assert_close(schedule.step_size(2.0, 21), 0.5);
+    }
+
+    #[test]
+    fn warm_restarts_restart_with_longer_periods() {
+        let schedule = StepSchedule::WarmRestarts {
+            period: 10,
+            period_multiplier: 2.0,
+            min_ratio: 0.0,
+        };
+
+        // The first period covers steps 1 to 10, the second 11 to 30
+        assert_close(schedule.step_size(1.0, 1), 1.0);
+        assert_close(schedule.step_size(1.0, 6), 0.5);
+        assert!(schedule.step_size(1.0, 10) < 0.03);
+        assert_close(schedule.step_size(1.0, 11), 1.0);
+        assert_close(schedule.step_size(1.0, 21), 0.5);
+        assert!(schedule.step_size(1.0, 30) < 0.01);
+        assert_close(schedule.step_size(1.0, 31), 1.0);
+    }
+
+    #[test]
+    fn constant_never_changes() {
+        for time in [0, 1, 1000] {
+            assert_close(StepSchedule::Constant.step_size(0.3, time), 0.3);
+        }
+    }
+}
@@ -0,0 +1,325 @@
+use anyhow::ensure;
+use motor_math::{
+    solve::reverse::{self, Axis},
+    ErasedMotorId, FloatType, MotorConfig,
+};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    dynamic::{Plane, Symmetry},
+    optimizers::OptimizerType,
+    schedule::Hyperparameters,
+    ArenaType, Execution, OptimizationArena, OptimizationOutput,
+};
+use crate::{
+    constraints::PositionConstraints, geometry::VehicleGeometry, heuristic::ScoreSettings,
+    layout::ThrusterLayout, physics, thruster_models::ThrusterModels,
+};
+
+/// Optimizes the same heuristic with several thruster counts so the counts can be compared
+pub struct ThrusterCountSearch {
+    arenas: Vec<CountArena>,
+    heuristic: ScoreSettings,
+}
+
+struct CountArena {
+    arena_type: ArenaType,
+    arena: Box<dyn OptimizationArena + Send + Sync + 'static>,
+    /// The best point as of the last step
+    best: Option<OptimizationOutput>,
+}
+
+/// How the best config of one thruster count performed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThrusterCountResult {
+    pub arena: ArenaType,
+    pub thruster_count: usize,
+    /// The score of the best config, including the thruster count loss
+    pub score: FloatType,
+    pub converged: bool,
+    pub axis_maximums: AxisValues,
+    /// The current drawn by all thrusters together while producing each axis maximum
+    pub total_current: AxisValues,
+    pub layout: ThrusterLayout,
+}
+
+/// A value for each of the six axes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AxisValues {
+    pub x: FloatType,
+    pub y: FloatType,
+    pub z: FloatType,
+    pub x_rot: FloatType,
+    pub y_rot: FloatType,
+    pub z_rot: FloatType,
+}
+
+impl AxisValues {
+    pub fn from_fn(mut value: impl FnMut(Axis) -> FloatType) -> Self {
+        Self {
+            x: value(Axis::X),
+            y: value(Axis::Y),
+            z: value(Axis::Z),
+            x_rot: value(Axis::XRot),
+            y_rot: value(Axis::YRot),
+            z_rot: value(Axis::ZRot),
+        }
+    }
+
+    pub fn get(&self, axis: Axis) -> FloatType {
+        match axis {
+            Axis::X => self.x,
+            Axis::Y => self.y,
+            Axis::Z => self.z,
+            Axis::XRot => self.x_rot,
+            Axis::YRot => self.y_rot,
+            Axis::ZRot => self.z_rot,
+        }
+    }
+}
+
+impl ThrusterCountSearch {
+    /// Creates an arena for each of `thruster_counts`, symmetrical arenas need even counts
+    pub fn new(
+        thruster_counts: &[usize],
+        symmetrical: bool,
+        execution: Execution,
+        optimizer: OptimizerType,
+    ) -> anyhow::Result<Self> {
+        let arenas = thruster_counts
+            .iter()
+            .map(|&thruster_count| {
+                ensure!(thruster_count > 0, "Thruster counts must be positive");
+
+                let arena_type = ArenaType::Symmetric {
+                    symmetry: if symmetrical {
+                        Symmetry::Mirror(Plane::YZ)
+                    } else {
+                        Symmetry::None
+                    },
+                    thrusters: thruster_count,
+                };
+
+                Ok(CountArena {
+                    arena_type,
+                    arena: arena_type.new_arena(execution, optimizer)?,
+                    best: None,
+                })
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self {
+            arenas,
+            heuristic: ScoreSettings::default(),
+        })
+    }
+
+    pub fn reset(&mut self, point_count: usize, heuristic: ScoreSettings, seed: u64) {
+        for it in &mut self.arenas {
+            it.arena.reset(point_count, heuristic.clone(), seed);
+            it.best = None;
+        }
+
+        self.heuristic = heuristic;
+    }
+
+    pub fn set_hyperparameters(&mut self, hyperparameters: Hyperparameters) {
+        for it in &mut self.arenas {
+            it.arena.set_hyperparameters(hyperparameters);
+        }
+    }
+
+    pub fn set_constraints(&mut self, constraints: &PositionConstraints) {
+        for it in &mut self.arenas {
+            it.arena.set_constraints(constraints.clone());
+        }
+    }
+
+    /// Steps every arena that still has unconverged points
+    pub fn step(&mut self, motor_data: &ThrusterModels) {
+        for it in &mut self.arenas {
+            if it.best.is_some() && it.arena.converged() {
+                continue;
+            }
+
+            it.best = it.arena.step(motor_data).next();
+        }
+    }
+
+    /// True once every arena has converged
+    pub fn converged(&self) -> bool {
+        self.arenas.iter().all(|it| it.arena.converged())
+    }
+
+    /// The best score of each thruster count as of the last step
+    pub fn best_scores(&self) -> impl Iterator<Item = (usize, FloatType)> + '_ {
+        self.arenas.iter().filter_map(|it| {
+            it.best
+                .as_ref()
+                .map(|best| (it.arena_type.thruster_count(), best.score))
+        })
+    }
+
+    /// Summarizes the best config of each thruster count, in the order the counts were given
+    pub fn comparison(&self, motor_data: &ThrusterModels) -> Vec<ThrusterCountResult> {
+        self.arenas
+            .iter()
+            .filter_map(|it| {
+                let best = it.best.as_ref()?;
+                let breakdown = &best.score_result_unscaled;
+                let axis_maximums = AxisValues {
+                    x: breakdown.x,
+                    y: breakdown.y,
+                    z: breakdown.z,
+                    x_rot: breakdown.x_rot,
+                    y_rot: breakdown.y_rot,
+                    z_rot: breakdown.z_rot,
+                };
+                let total_current = AxisValues::from_fn(|axis| {
+                    total_current(
+                        &best.motor_config,
+                        &self.heuristic.geometry,
+                        &motor_data.resolved(best.thruster_models.clone()),
+                        axis,
+                        axis_maximums.get(axis),
+                    )
+                });
+
+                Some(ThrusterCountResult {
+                    arena: it.arena_type,
+                    thruster_count: it.arena_type.thruster_count(),
+                    score: best.score,
+                    converged: best.converged,
+                    axis_maximums,
+                    total_current,
+                    layout: ThrusterLayout::from_output(best, &self.heuristic),
+                })
+            })
+            .collect()
+    }
+}
+
+/// The current drawn by all thrusters while producing `magnitude` along `axis`
+fn total_current(
+    motor_config: &MotorConfig<ErasedMotorId, FloatType>,
+    geometry: &VehicleGeometry,
+    motor_data: &ThrusterModels,
+    axis: Axis,
+    magnitude: FloatType,
+) -> FloatType {
+    let motor_config = physics::with_vehicle(motor_config.clone(), geometry);
+    let forces = reverse::reverse_solve(physics::axis_movement(axis, magnitude), &motor_config);
+    let motor_cmds = motor_data.forces_to_cmds(forces, &motor_config);
+
+    motor_cmds.values().map(|cmd| cmd.current).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use motor_math::{solve::reverse::Axis, Direction, FloatType, Motor, MotorConfig};
+    use nalgebra::vector;
+    use stable_hashmap::StableHashMap;
+
+    use super::{total_current, AxisValues};
+    use crate::{
+        geometry::VehicleGeometry,
+        heuristic::{ScoreResult, ScoreSettings, Unscaled},
+        motor_fit::{MotorFit, DEFAULT_FIT_DEGREE},
+        motor_library::ThrustCurve,
+        thruster_models::ThrusterModels,
+    };
+
+    /// The breakdown of a config of `thruster_count` thrusters with `thrust` along X
+    fn breakdown(thruster_count: usize, thrust: FloatType) -> ScoreResult<FloatType, Unscaled> {
+        let mut breakdown = ScoreResult::default();
+        breakdown.x = thrust;
+        breakdown.thruster_count_loss = thruster_count as FloatType;
+        breakdown
+    }
+
+    #[test]
+    fn thruster_cost_orders_the_counts() {
+        // Each thruster adds 10 N
+        let counts = [4, 6, 8];
+        let best = |thruster_count_loss| {
+            let settings = ScoreSettings {
+                x: 1.0,
+                thruster_count_loss,
+                ..Default::default()
+            };
+
+            counts
+                .into_iter()
+                .max_by(|&a, &b| {
+                    let score =
+                        |count| breakdown(count, count as FloatType * 10.0).score(&settings);
+                    score(a).total_cmp(&score(b))
+                })
+                .unwrap()
+        };
+
+        assert_eq!(best(0.0), 8);
+        assert_eq!(best(-5.0), 8);
+        // Once a thruster costs more than it adds the fewest thrusters win
+        assert_eq!(best(-15.0), 4);
+    }
+
+    #[test]
+    fn axis_values_follow_their_axis() {
+        let axes = [
+            Axis::X,
+            Axis::Y,
+            Axis::Z,
+            Axis::XRot,
+            Axis::YRot,
+            Axis::ZRot,
+        ];
+        let values = AxisValues::from_fn(|axis| {
+            axes.iter().position(|&it| it == axis).unwrap() as FloatType
+        });
+
+        for (idx, axis) in axes.into_iter().enumerate() {
+            assert_eq!(values.get(axis), idx as FloatType);
+        }
+    }
+
+    #[test]
+    fn total_current_sums_every_thruster() {
+        let curve = ThrustCurve::parse(12.0, include_str!("../../motor_data.csv"), 1.0).unwrap();
+        let fit = MotorFit::fit(&curve, DEFAULT_FIT_DEGREE).unwrap();
+        let motor_data = ThrusterModels::from(fit.clone());
+        let geometry = VehicleGeometry {
+            prop_torque_ratio: 0.0,
+            ..Default::default()
+        };
+
+        // Two thrusters side by side pushing along X share the force evenly
+        let motor_config = MotorConfig::new_raw(
+            [-0.1, 0.1].into_iter().enumerate().map(|(id, y)| {
+                (
+                    id as _,
+                    Motor {
+                        position: vector![0.0, y, 0.0],
+                        orientation: vector![1.0, 0.0, 0.0],
+                        direction: Direction::Clockwise,
+                    },
+                )
+            }),
+            vector![0.0, 0.0, 0.0],
+        );
+        let one = fit
+            .forces_to_cmds(StableHashMap::from_iter([(0, 10.0)]))
+            .remove(&0)
+            .unwrap();
+
+        let current = total_current(&motor_config, &geometry, &motor_data, Axis::X, 20.0);
+        assert!(
+            (current - 2.0 * one.current).abs() < 1e-6,
+            "{current} {one:?}"
+        );
+        assert_eq!(
+            total_current(&motor_config, &geometry, &motor_data, Axis::X, 0.0),
+            0.0
+        );
+    }
+}
@@ -0,0 +1,317 @@
+use std::{fmt::Debug, hash::Hash};
+
+use itertools::Itertools;
+use motor_math::{
+    solve::reverse::Axis, Direction, FloatType, Motor, MotorConfig, Movement, Number,
+};
+use nalgebra::{Matrix3, Vector3};
+
+use crate::{geometry::VehicleGeometry, layout::SpinDirection};
+
+const PSEUDO_INVERSE_EPSILON: FloatType = 0.00001;
+
+/// The most thrusters every assignment of spin directions is tried for, there are
+/// `2^thrusters` of them
+pub const MAX_EXHAUSTIVE_SPIN_THRUSTERS: usize = 12;
+
+/// Adds the reaction torque of each propeller, `prop_torque_ratio` times its thrust, to the motor
+/// config's matrix
+///
+/// A clockwise propeller pushing along its orientation twists the vehicle counter clockwise
+/// about that orientation, a counter clockwise propeller the other way
+pub fn with_reaction_torque<MotorId: Ord + Debug + Clone + Hash, D: Number>(
+    mut motor_config: MotorConfig<MotorId, D>,
+    prop_torque_ratio: FloatType,
+) -> MotorConfig<MotorId, D> {
+    if prop_torque_ratio == 0.0 {
+        return motor_config;
+    }
+
+    // Columns of the matrix follow the order of `motors()`
+    let torques = motor_config
+        .motors()
+        .map(|(_, motor)| {
+            let sign = match motor.direction {
+                Direction::Clockwise => prop_torque_ratio,
+                Direction::CounterClockwise => -prop_torque_ratio,
+            };
+
+            motor.orientation * D::from(sign)
+        })
+        .collect_vec();
+
+    for (column, torque) in torques.into_iter().enumerate() {
+        let mut reaction_torque = motor_config.matrix.fixed_view_mut::<3, 1>(3, column);
+        reaction_torque += torque;
+    }
+
+    motor_config.pseudo_inverse = motor_config
+        .matrix
+        .clone()
+        .pseudo_inverse(D::from(PSEUDO_INVERSE_EPSILON))
+        .expect("Pseudo inverse of motor matrix");
+
+    motor_config
+}
+
+/// The motor config as the vehicle sees it, rotating about the center of mass of the vehicle's
+/// mass model and with the reaction torque of each propeller
+pub fn with_vehicle<MotorId: Ord + Debug + Clone + Hash, D: Number>(
+    motor_config: MotorConfig<MotorId, D>,
+    geometry: &VehicleGeometry,
+) -> MotorConfig<MotorId, D> {
+    let motor_config = match &geometry.mass {
+        Some(mass_model) => {
+            let center_of_mass = mass_model.properties(&motor_config).center_of_mass;
+            with_center(motor_config, center_of_mass)
+        }
+        None => motor_config,
+    };
+
+    with_reaction_torque(motor_config, geometry.prop_torque_ratio)
+}
+
+/// Rebuilds the motor config so torques are taken about `center` instead of the origin
+pub fn with_center<MotorId: Ord + Debug + Clone + Hash, D: Number>(
+    motor_config: MotorConfig<MotorId, D>,
+    center: Vector3<D>,
+) -> MotorConfig<MotorId, D> {
+    MotorConfig::new_raw(
+        motor_config.motors().map(|(id, motor)| {
+            (
+                id.clone(),
+                Motor {
+                    direction: motor.direction,
+                    ..*motor
+                },
+            )
+        }),
+        center,
+    )
+}
+
+/// The point the motor config's torques are taken about, recovered from its matrix
+///
+/// The torque of each motor's column is `(position - center) × orientation`, plus any reaction
+/// torque along the orientation, so `center × orientation = position × orientation - torque` is
+/// solved by least squares. Orientations have no torque about their own axis, so the reaction
+/// torque doesn't change the result and neither does a center moved along every orientation
+pub fn center<MotorId: Ord + Debug + Clone + Hash, D: Number>(
+    motor_config: &MotorConfig<MotorId, D>,
+) -> Vector3<D> {
+    let mut normal_matrix = Matrix3::<D>::zeros();
+    let mut normal_vector = Vector3::<D>::zeros();
+
+    // Columns of the matrix follow the order of `motors()`
+    for (column, (_, motor)) in motor_config.motors().enumerate() {
+        let torque: Vector3<D> = motor_config.matrix.fixed_view::<3, 1>(3, column).into();
+        // `center × orientation` as a matrix times the center
+        let cross = -motor.orientation.cross_matrix();
+        let expected = motor.position.cross(&motor.orientation) - torque;
+
+        normal_matrix += cross.transpose() * cross;
+        normal_vector += cross.transpose() * expected;
+    }
+
+    normal_matrix
+        .pseudo_inverse(D::from(PSEUDO_INVERSE_EPSILON))
+        .expect("Pseudo inverse of center equations")
+        * normal_vector
+}
+
+/// Rebuilds the motor config with the spin directions of `directions`, given in the order of
+/// `motors()`, keeping its center. An empty slice leaves the config unchanged
+pub fn with_directions<MotorId: Ord + Debug + Clone + Hash, D: Number>(
+    motor_config: MotorConfig<MotorId, D>,
+    directions: &[SpinDirection],
+) -> MotorConfig<MotorId, D> {
+    if directions.is_empty() {
+        return motor_config;
+    }
+
+    let center = center(&motor_config);
+    MotorConfig::new_raw(
+        motor_config
+            .motors()
+            .zip(directions)
+            .map(|((id, motor), direction)| {
+                (
+                    id.clone(),
+                    Motor {
+                        direction: (*direction).into(),
+                        ..*motor
+                    },
+                )
+            }),
+        center,
+    )
+}
+
+/// A pure force or torque of `magnitude` along `axis`
+pub fn axis_movement<D: Number>(axis: Axis, magnitude: D) -> Movement<D> {
+    let direction = match axis {
+        Axis::X | Axis::XRot => Vector3::x(),
+        Axis::Y | Axis::YRot => Vector3::y(),
+        Axis::Z | Axis::ZRot => Vector3::z(),
+    }
+    .map(D::from);
+
+    match axis {
+        Axis::X | Axis::Y | Axis::Z => Movement {
+            force: direction * magnitude,
+            torque: Vector3::zeros(),
+        },
+        Axis::XRot | Axis::YRot | Axis::ZRot => Movement {
+            force: Vector3::zeros(),
+            torque: direction * magnitude,
+        },
+    }
+}
+
+/// The assignments of spin directions to try in place of `current` for `thruster_count`
+/// thrusters, where thrusters past the end of `current` spin clockwise
+///
+/// Every assignment for up to `MAX_EXHAUSTIVE_SPIN_THRUSTERS` thrusters, past that `current` and
+/// each assignment with a single thruster flipped
+pub fn direction_candidates(
+    current: &[SpinDirection],
+    thruster_count: usize,
+) -> Vec<Vec<SpinDirection>> {
+    if thruster_count <= MAX_EXHAUSTIVE_SPIN_THRUSTERS {
+        return all_directions(thruster_count).collect();
+    }
+
+    let current = current
+        .iter()
+        .copied()
+        .chain(std::iter::repeat(SpinDirection::Clockwise))
+        .take(thruster_count)
+        .collect_vec();
+    let flipped = (0..thruster_count).map(|idx| {
+        let mut directions = current.clone();
+        directions[idx] = match directions[idx] {
+            SpinDirection::Clockwise => SpinDirection::CounterClockwise,
+            SpinDirection::CounterClockwise => SpinDirection::Clockwise,
+        };
+        directions
+    });
+
+    std::iter::once(current.clone()).chain(flipped).collect()
+}
+
+/// Every assignment of spin directions to `thruster_count` thrusters, at most
+/// `MAX_EXHAUSTIVE_SPIN_THRUSTERS` of them
+fn all_directions(thruster_count: usize) -> impl Iterator<Item = Vec<SpinDirection>> {
+    assert!(thruster_count <= MAX_EXHAUSTIVE_SPIN_THRUSTERS);
+
+    (0..1usize << thruster_count).map(move |mask| {
+        (0..thruster_count)
+            .map(|idx| {
+                if mask & (1 << idx) == 0 {
+                    SpinDirection::Clockwise
+                } else {
+                    SpinDirection::CounterClockwise
+                }
+            })
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+
+    use motor_math::{Direction, FloatType, Motor, MotorConfig};
+    use nalgebra::{vector, Vector3};
+
+    use super::{
+        center, direction_candidates, with_directions, with_reaction_torque,
+        MAX_EXHAUSTIVE_SPIN_THRUSTERS,
+    };
+    use crate::layout::SpinDirection;
+
+    fn config_about(center: Vector3<FloatType>) -> MotorConfig<u8, FloatType> {
+        let motors = [
+            (vector![0.2, 0.1, 0.0], vector![1.0, 0.0, 0.0]),
+            (vector![-0.2, 0.1, 0.1], vector![0.0, 1.0, 0.0]),
+            (vector![0.0, -0.2, 0.1], vector![0.0, 0.6, 0.8]),
+        ];
+
+        MotorConfig::new_raw(
+            motors
+                .into_iter()
+                .enumerate()
+                .map(|(idx, (position, orientation))| {
+                    (
+                        idx as u8,
+                        Motor {
+                            position,
+                            orientation,
+                            direction: Direction::Clockwise,
+                        },
+                    )
+                }),
+            center,
+        )
+    }
+
+    #[test]
+    fn centers_are_recovered_from_the_matrix() {
+        let expected = vector![0.1, -0.2, 0.05];
+        let motor_config = config_about(expected);
+        assert!((center(&motor_config) - expected).norm() < 1e-9);
+
+        let motor_config = with_reaction_torque(motor_config, 0.05);
+        assert!((center(&motor_config) - expected).norm() < 1e-9);
+    }
+
+    #[test]
+    fn directions_keep_the_center() {
+        let expected = vector![0.1, -0.2, 0.05];
+        let motor_config = config_about(expected);
+        let directions = [
+            SpinDirection::CounterClockwise,
+            SpinDirection::Clockwise,
+            SpinDirection::CounterClockwise,
+        ];
+
+        let flipped = with_directions(motor_config, &directions);
+        assert!((center(&flipped) - expected).norm() < 1e-9);
+        assert_eq!(
+            flipped.motor(&0).unwrap().direction,
+            Direction::CounterClockwise
+        );
+        assert_eq!(flipped.motor(&1).unwrap().direction, Direction::Clockwise);
+    }
+
+    #[test]
+    fn few_thrusters_try_every_direction() {
+        let candidates = direction_candidates(&[], 3);
+
+        assert_eq!(candidates.len(), 8);
+        assert!(candidates.iter().tuple_combinations().all(|(a, b)| a != b));
+    }
+
+    #[test]
+    fn many_thrusters_flip_one_at_a_time() {
+        for thruster_count in [MAX_EXHAUSTIVE_SPIN_THRUSTERS + 1, 64, 100] {
+            let candidates =
+                direction_candidates(&[SpinDirection::CounterClockwise], thruster_count);
+
+            assert_eq!(candidates.len(), thruster_count + 1);
+            assert_eq!(candidates[0][0], SpinDirection::CounterClockwise);
+            assert!(candidates[0][1..]
+                .iter()
+                .all(|it| *it == SpinDirection::Clockwise));
+            for (idx, candidate) in candidates[1..].iter().enumerate() {
+                assert_eq!(candidate.len(), thruster_count);
+                let changed = candidate
+                    .iter()
+                    .zip(&candidates[0])
+                    .positions(|(a, b)| a != b)
+                    .collect_vec();
+                assert_eq!(changed, vec![idx]);
+            }
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use std::{fmt::Debug, hash::Hash};
+
+use motor_math::{
+    motor_preformance::MotorRecord,
+    solve::reverse::{self, Axis},
+    FloatType, MotorConfig, Number,
+};
+use serde::{Deserialize, Serialize};
+use stable_hashmap::StableHashMap;
+
+use crate::{format::non_finite, physics, thruster_models::ThrusterModels};
+
+/// Precision in amps of the searches for the largest force within a budget
+pub const CURRENT_EPSILON: FloatType = 0.001;
+
+/// The current the thrusters may draw, every thrust maximum is limited by it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PowerBudget {
+    /// Amps all thrusters may draw together, the fuse or tether limit
+    pub total_current: FloatType,
+    /// Amps each thruster may draw, unlimited by default so only the total applies
+    #[serde(with = "non_finite")]
+    pub thruster_current: FloatType,
+    /// Volts of the power bus
+    pub bus_voltage: FloatType,
+}
+
+impl Default for PowerBudget {
+    fn default() -> Self {
+        Self {
+            total_current: 25.0,
+            thruster_current: FloatType::INFINITY,
+            bus_voltage: 12.0,
+        }
+    }
+}
+
+impl PowerBudget {
+    /// The largest force or torque along each axis within the budget
+    pub fn axis_maximums<MotorId: Debug + Ord + Hash + Clone, D: Number>(
+        &self,
+        motor_config: &MotorConfig<MotorId, D>,
+        motor_data: &ThrusterModels,
+    ) -> StableHashMap<Axis, D> {
+        let mut maximums =
+            motor_data.axis_maximums(motor_config, self.total_current, CURRENT_EPSILON);
+
+        for (axis, maximum) in &mut maximums {
+            let forces =
+                reverse::reverse_solve(physics::axis_movement(*axis, *maximum), motor_config);
+            let motor_cmds = motor_data.forces_to_cmds(forces, motor_config);
+
+            *maximum *= self.thruster_ratio(&motor_cmds, motor_config, motor_data);
+        }
+
+        maximums
+    }
+
+    /// The largest multiple of the forces of `motor_cmds` within the budget
+    pub fn force_ratio<MotorId: Debug + Ord + Hash + Clone, D: Number>(
+        &self,
+        motor_cmds: &StableHashMap<MotorId, MotorRecord<D>>,
+        motor_config: &MotorConfig<MotorId, D>,
+        motor_data: &ThrusterModels,
+    ) -> D {
+        let ratio = motor_data.binary_search_force_ratio(
+            motor_cmds,
+            motor_config,
+            self.total_current,
+            CURRENT_EPSILON,
+        );
+
+        let forces = motor_cmds
+            .iter()
+            .map(|(id, cmd)| (id.clone(), cmd.force * ratio))
+            .collect();
+        let scaled_cmds = motor_data.forces_to_cmds(forces, motor_config);
+
+        ratio * self.thruster_ratio(&scaled_cmds, motor_config, motor_data)
+    }
+
+    /// The multiple of the forces of `motor_cmds` that brings every thruster within its own
+    /// limit, at most 1
+    fn thruster_ratio<MotorId: Debug + Ord + Hash + Clone, D: Number>(
+        &self,
+        motor_cmds: &StableHashMap<MotorId, MotorRecord<D>>,
+        motor_config: &MotorConfig<MotorId, D>,
+        motor_data: &ThrusterModels,
+    ) -> D {
+        let mut ratio = D::one();
+
+        for (id, cmd) in motor_cmds {
+            if cmd.current.re() <= self.thruster_current {
+                continue;
+            }
+
+            // Searching with only this thruster finds the ratio where it alone hits the limit
+            let mut single = motor_cmds.clone();
+            single.retain(|other, _| other == id);
+            ratio = ratio.min(motor_data.binary_search_force_ratio(
+                &single,
+                motor_config,
+                self.thruster_current,
+                CURRENT_EPSILON,
+            ));
+        }
+
+        ratio
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use motor_math::{solve::reverse::Axis, Direction, FloatType, Motor, MotorConfig};
+    use nalgebra::vector;
+    use stable_hashmap::StableHashMap;
+
+    use super::PowerBudget;
+    use crate::{
+        motor_fit::{MotorFit, DEFAULT_FIT_DEGREE},
+        motor_library::ThrustCurve,
+        thruster_models::ThrusterModels,
+    };
+
+    fn shipped_fit() -> MotorFit {
+        let curve = ThrustCurve::parse(12.0, include_str!("../motor_data.csv"), 1.0).unwrap();
+        MotorFit::fit(&curve, DEFAULT_FIT_DEGREE).unwrap()
+    }
+
+    /// Two thrusters side by side pushing along X
+    fn side_by_side() -> MotorConfig<u8, FloatType> {
+        MotorConfig::new_raw(
+            [-0.1, 0.1].into_iter().enumerate().map(|(id, y)| {
+                (
+                    id as u8,
+                    Motor {
+                        position: vector![0.0, y, 0.0],
+                        orientation: vector![1.0, 0.0, 0.0],
+                        direction: Direction::Clockwise,
+                    },
+                )
+            }),
+            vector![0.0, 0.0, 0.0],
+        )
+    }
+
+    fn currents(fit: &MotorFit, forces: [FloatType; 2]) -> [FloatType; 2] {
+        forces.map(|force| fit.record(force).current)
+    }
+
+    #[test]
+    fn total_current_limits_the_force_ratio() {
+        let fit = shipped_fit();
+        let motor_data = ThrusterModels::from(fit.clone());
+        let motor_config = side_by_side();
+        let budget = PowerBudget {
+            total_current: 10.0,
+            ..PowerBudget::default()
+        };
+
+        let motor_cmds = fit.forces_to_cmds(StableHashMap::from_iter([(0, 10.0), (1, 5.0)]));
+        let ratio = budget.force_ratio(&motor_cmds, &motor_config, &motor_data);
+
+        let [a, b] = currents(&fit, [10.0 * ratio, 5.0 * ratio]);
+        assert!((a + b - 10.0).abs() < 0.01, "{ratio} {a} {b}");
+    }
+
+    #[test]
+    fn thruster_current_limits_the_busiest_thruster() {
+        let fit = shipped_fit();
+        let motor_data = ThrusterModels::from(fit.clone());
+        let motor_config = side_by_side();
+        let budget = PowerBudget {
+            total_current: 100.0,
+            thruster_current: 3.0,
+            ..PowerBudget::default()
+        };
+
+        let motor_cmds = fit.forces_to_cmds(StableHashMap::from_iter([(0, 10.0), (1, 5.0)]));
+        let ratio = budget.force_ratio(&motor_cmds, &motor_config, &motor_data);
+
+        let [a, b] = currents(&fit, [10.0 * ratio, 5.0 * ratio]);
+        assert!((a - 3.0).abs() < 0.01, "{ratio} {a} {b}");
+        assert!(b < 3.0, "{ratio} {a} {b}");
+    }
+
+    #[test]
+    fn unlimited_thrusters_only_apply_the_total() {
+        let fit = shipped_fit();
+        let motor_data = ThrusterModels::from(fit.clone());
+        let motor_config = side_by_side();
+        let unlimited = PowerBudget::default();
+        let at_total = PowerBudget {
+            thruster_current: unlimited.total_current,
+            ..unlimited.clone()
+        };
+        assert_eq!(unlimited.thruster_current, FloatType::INFINITY);
+
+        let motor_cmds = fit.forces_to_cmds(StableHashMap::from_iter([(0, 10.0), (1, 5.0)]));
+        assert_eq!(
+            unlimited.force_ratio(&motor_cmds, &motor_config, &motor_data),
+            at_total.force_ratio(&motor_cmds, &motor_config, &motor_data)
+        );
+        assert_eq!(
+            unlimited.axis_maximums(&motor_config, &motor_data),
+            at_total.axis_maximums(&motor_config, &motor_data)
+        );
+    }
+
+    #[test]
+    fn axis_maximums_scale_with_the_budget() {
+        let fit = shipped_fit();
+        let motor_data = ThrusterModels::from(fit.clone());
+        let motor_config = side_by_side();
+
+        // Each thruster carries half of the X force
+        let total = PowerBudget {
+            total_current: 10.0,
+            ..PowerBudget::default()
+        };
+        let maximum = total.axis_maximums(&motor_config, &motor_data)[&Axis::X];
+        let [a, b] = currents(&fit, [maximum / 2.0; 2]);
+        assert!((a + b - 10.0).abs() < 0.01, "{maximum} {a} {b}");
+
+        let per_thruster = PowerBudget {
+            thruster_current: 2.0,
+            ..total
+        };
+        let limited = per_thruster.axis_maximums(&motor_config, &motor_data)[&Axis::X];
+        let [a, _] = currents(&fit, [limited / 2.0; 2]);
+        assert!(limited < maximum, "{limited} {maximum}");
+        assert!((a - 2.0).abs() < 0.01, "{limited} {a}");
+    }
+}
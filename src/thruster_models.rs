@@ -0,0 +1,565 @@
+use std::{fmt::Debug, hash::Hash, sync::Arc};
+
+use anyhow::{ensure, Context};
+use itertools::Itertools;
+use motor_math::{
+    motor_preformance::{MotorData, MotorRecord},
+    solve::reverse::{self, Axis},
+    FloatType, Motor, MotorConfig, Number,
+};
+use nalgebra::Vector3;
+use stable_hashmap::StableHashMap;
+
+use crate::{motor_fit::MotorFit, motor_library::MotorLibrary, physics};
+
+const AXES: [Axis; 6] = [
+    Axis::X,
+    Axis::Y,
+    Axis::Z,
+    Axis::XRot,
+    Axis::YRot,
+    Axis::ZRot,
+];
+
+/// Times the force ratio is doubled looking for the current limit before the thrusters are
+/// taken to be saturated
+const MAX_RATIO_DOUBLINGS: usize = 16;
+const MAX_BISECTIONS: usize = 64;
+
+/// The motor data of the thruster models a config is scored with and the model of each of its
+/// thrusters
+///
+/// The assignment is indexed by slot, a parameter column of an optimizable config, until it is
+/// resolved to the config's thrusters with `for_thrusters`, after which it is indexed by
+/// thruster in `motors()` order. Anything without an assignment uses the default model
+#[derive(Clone)]
+pub struct ThrusterModels {
+    names: Arc<[String]>,
+    data: Arc<[ThrusterModel]>,
+    assignment: Vec<usize>,
+    default: usize,
+}
+
+/// How a thruster's commands are found from its force
+pub enum ThrusterModel {
+    /// Looked up in the measured table
+    Table(MotorData),
+    /// Evaluated from polynomials fitted to the table
+    Fit(Box<MotorFit>),
+}
+
+impl ThrusterModels {
+    pub fn new(models: Vec<(String, ThrusterModel)>, default: usize) -> anyhow::Result<Self> {
+        ensure!(
+            default < models.len(),
+            "No thruster model {default} of {}",
+            models.len()
+        );
+        let (names, data): (Vec<_>, Vec<_>) = models.into_iter().unzip();
+
+        Ok(Self {
+            names: names.into(),
+            data: data.into(),
+            assignment: vec![],
+            default,
+        })
+    }
+
+    /// Every model of `library` at `voltage`, with `default` used by unassigned thrusters. With
+    /// `fit_degree` the models are polynomials of that degree fitted to the curves
+    pub fn from_library(
+        library: &MotorLibrary,
+        voltage: FloatType,
+        default: &str,
+        fit_degree: Option<usize>,
+    ) -> anyhow::Result<Self> {
+        let models = library
+            .models()
+            .iter()
+            .map(|model| {
+                let curve = model.curve_at(voltage);
+                let thruster_model = match fit_degree {
+                    Some(degree) => {
+                        MotorFit::fit(&curve, degree).map(|fit| ThrusterModel::Fit(Box::new(fit)))
+                    }
+                    None => curve.motor_data().map(ThrusterModel::Table),
+                }
+                .with_context(|| format!("Motor data of {}", model.name))?;
+                Ok((model.name.clone(), thruster_model))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        let default = library
+            .models()
+            .iter()
+            .position(|it| it.name == default)
+            .with_context(|| format!("No motor model {default}"))?;
+
+        Self::new(models, default)
+    }
+
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    pub fn default_model(&self) -> usize {
+        self.default
+    }
+
+    pub fn assignment(&self) -> &[usize] {
+        &self.assignment
+    }
+
+    /// The index of the model called `name`
+    pub fn index_of(&self, name: &str) -> anyhow::Result<usize> {
+        self.names
+            .iter()
+            .position(|it| it == name)
+            .with_context(|| {
+                format!(
+                    "No thruster model {name}, expected one of {}",
+                    self.names.iter().join(", ")
+                )
+            })
+    }
+
+    /// The same models with `assignment` in place of the current one, models that don't exist
+    /// fall back to the default
+    pub fn with_assignment(&self, assignment: Vec<usize>) -> Self {
+        Self {
+            assignment,
+            ..self.clone()
+        }
+    }
+
+    /// The model of the `idx`th slot or thruster
+    pub fn model_of(&self, idx: usize) -> usize {
+        self.assignment
+            .get(idx)
+            .copied()
+            .filter(|&it| it < self.data.len())
+            .unwrap_or(self.default)
+    }
+
+    /// Resolves the assignment of each slot to the thrusters made from `slots`, given in the
+    /// order of `motors()`
+    pub fn for_thrusters(&self, slots: impl IntoIterator<Item = usize>) -> Self {
+        self.resolved(slots.into_iter().map(|slot| self.model_of(slot)).collect())
+    }
+
+    /// The same models with `thruster_models` as the model of each of a config's thrusters, in
+    /// the order of `motors()`
+    pub fn resolved(&self, thruster_models: Vec<usize>) -> Self {
+        let mut resolved = self.with_assignment(thruster_models);
+
+        // Every thruster is assigned, so the default only matters for spotting uniform configs
+        if let Some(&model) = resolved.assignment.first() {
+            if model < resolved.data.len() && resolved.assignment.iter().all(|&it| it == model) {
+                resolved.default = model;
+            }
+        }
+
+        resolved
+    }
+
+    /// The model of the `idx`th thruster
+    pub fn thruster_model(&self, idx: usize) -> &ThrusterModel {
+        &self.data[self.model_of(idx)]
+    }
+
+    /// The motor data shared by every thruster, if they all use the default model and it's a
+    /// table
+    pub fn uniform(&self) -> Option<&MotorData> {
+        let ThrusterModel::Table(motor_data) = &self.data[self.default] else {
+            return None;
+        };
+
+        (0..self.assignment.len())
+            .all(|idx| self.model_of(idx) == self.default)
+            .then_some(motor_data)
+    }
+
+    /// The fit shared by every thruster, if they all use the default model and it's a fit
+    fn uniform_fit(&self) -> Option<&MotorFit> {
+        let ThrusterModel::Fit(fit) = &self.data[self.default] else {
+            return None;
+        };
+
+        (0..self.assignment.len())
+            .all(|idx| self.model_of(idx) == self.default)
+            .then_some(fit)
+    }
+
+    fn models_by_id<MotorId: Debug + Ord + Hash + Clone, D: Number>(
+        &self,
+        motor_config: &MotorConfig<MotorId, D>,
+    ) -> StableHashMap<MotorId, usize> {
+        motor_config
+            .motors()
+            .enumerate()
+            .map(|(idx, (id, _))| (id.clone(), self.model_of(idx)))
+            .collect()
+    }
+
+    /// Like `reverse::forces_to_cmds` with each thruster's force looked up in its own model
+    pub fn forces_to_cmds<MotorId: Debug + Ord + Hash + Clone, D: Number>(
+        &self,
+        forces: StableHashMap<MotorId, D>,
+        motor_config: &MotorConfig<MotorId, D>,
+    ) -> StableHashMap<MotorId, MotorRecord<D>> {
+        if let Some(motor_data) = self.uniform() {
+            return reverse::forces_to_cmds(forces, motor_config, motor_data);
+        }
+        if let Some(fit) = self.uniform_fit() {
+            return fit.forces_to_cmds(forces);
+        }
+
+        let models = self.models_by_id(motor_config);
+        self.data
+            .iter()
+            .enumerate()
+            .flat_map(|(model, thruster_model)| {
+                let group: StableHashMap<_, _> = forces
+                    .iter()
+                    .filter(|(id, _)| models.get(*id).copied() == Some(model))
+                    .map(|(id, force)| (id.clone(), *force))
+                    .collect();
+
+                match thruster_model {
+                    ThrusterModel::Table(motor_data) => {
+                        reverse::forces_to_cmds(group, motor_config, motor_data)
+                    }
+                    ThrusterModel::Fit(fit) => fit.forces_to_cmds(group),
+                }
+            })
+            .collect()
+    }
+
+    /// Like `reverse::binary_search_force_ratio` with each thruster's current looked up in its
+    /// own model
+    pub fn binary_search_force_ratio<MotorId: Debug + Ord + Hash + Clone, D: Number>(
+        &self,
+        motor_cmds: &StableHashMap<MotorId, MotorRecord<D>>,
+        motor_config: &MotorConfig<MotorId, D>,
+        amps: FloatType,
+        epsilon: FloatType,
+    ) -> D {
+        if let Some(motor_data) = self.uniform() {
+            return reverse::binary_search_force_ratio(
+                motor_cmds,
+                motor_config,
+                motor_data,
+                amps,
+                epsilon,
+            );
+        }
+
+        let current = |ratio: D| {
+            let forces = motor_cmds
+                .iter()
+                .map(|(id, cmd)| (id.clone(), cmd.force * ratio))
+                .collect();
+
+            self.forces_to_cmds(forces, motor_config)
+                .values()
+                .fold(D::zero(), |total, cmd| total + cmd.current)
+        };
+
+        // The search is done in floats, only the final Newton step is in `D`
+        let float_forces = motor_cmds
+            .iter()
+            .map(|(id, cmd)| (id.clone(), cmd.force.re()))
+            .collect_vec();
+        // Uniform fits don't look at the config, so it's only rebuilt without them
+        let uniform_fit = self.uniform_fit();
+        let float_config = uniform_fit.is_none().then(|| float_config(motor_config));
+        let float_current = |ratio: FloatType| {
+            let forces = float_forces
+                .iter()
+                .map(|(id, force)| (id.clone(), force * ratio))
+                .collect();
+            let cmds = match &float_config {
+                Some(float_config) => self.forces_to_cmds(forces, float_config),
+                None => uniform_fit
+                    .map(|fit| fit.forces_to_cmds(forces))
+                    .unwrap_or_default(),
+            };
+
+            cmds.values().map(|cmd| cmd.current).sum::<FloatType>()
+        };
+
+        // Fits stop at the strongest measured force like the tables, so the ratio can't take
+        // a thruster past it
+        let saturation = self.saturation_ratio(motor_cmds, motor_config);
+        let saturated = saturation.map(|it| it.re()).unwrap_or(FloatType::INFINITY);
+
+        let (mut low, mut high) = (0.0, saturated.min(1.0));
+        for _ in 0..MAX_RATIO_DOUBLINGS {
+            if float_current(high) >= amps || high >= saturated {
+                break;
+            }
+            low = high;
+            high = (high * 2.0).min(saturated);
+        }
+        if float_current(high) < amps {
+            return saturation
+                .filter(|it| it.re() == high)
+                .unwrap_or(D::from(high));
+        }
+
+        for _ in 0..MAX_BISECTIONS {
+            let middle = (low + high) / 2.0;
+            let middle_current = float_current(middle);
+            if (middle_current - amps).abs() < epsilon {
+                (low, high) = (middle, middle);
+                break;
+            }
+
+            if middle_current < amps {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+        let ratio = (low + high) / 2.0;
+
+        // The search only finds the value of the ratio, one Newton step in `D` adds how it
+        // changes with the config
+        let step = (ratio * 1e-4).max(1e-9);
+        let slope = (float_current(ratio + step) - float_current(ratio)) / step;
+        if slope <= 0.0 {
+            return D::from(ratio);
+        }
+
+        D::from(ratio) + (D::from(amps) - current(D::from(ratio))) / slope
+    }
+
+    /// The largest ratio keeping every thruster with a fitted model within its strongest
+    /// measured force, if any thruster has one
+    fn saturation_ratio<MotorId: Debug + Ord + Hash + Clone, D: Number>(
+        &self,
+        motor_cmds: &StableHashMap<MotorId, MotorRecord<D>>,
+        motor_config: &MotorConfig<MotorId, D>,
+    ) -> Option<D> {
+        let models = self.models_by_id(motor_config);
+
+        motor_cmds
+            .iter()
+            .filter(|(_, cmd)| cmd.force.re() != 0.0)
+            .filter_map(|(id, cmd)| {
+                let ThrusterModel::Fit(fit) = &self.data[*models.get(id)?] else {
+                    return None;
+                };
+
+                Some(D::from(fit.max_force(cmd.force.re())) / cmd.force.abs())
+            })
+            .min_by(|a, b| a.re().total_cmp(&b.re()))
+    }
+
+    /// Like `reverse::axis_maximums` with each thruster using its own model
+    pub fn axis_maximums<MotorId: Debug + Ord + Hash + Clone, D: Number>(
+        &self,
+        motor_config: &MotorConfig<MotorId, D>,
+        amps: FloatType,
+        epsilon: FloatType,
+    ) -> StableHashMap<Axis, D> {
+        if let Some(motor_data) = self.uniform() {
+            return reverse::axis_maximums(motor_config, motor_data, amps, epsilon);
+        }
+
+        AXES.into_iter()
+            .map(|axis| {
+                let forces =
+                    reverse::reverse_solve(physics::axis_movement(axis, D::one()), motor_config);
+                let motor_cmds = self.forces_to_cmds(forces, motor_config);
+
+                (
+                    axis,
+                    self.binary_search_force_ratio(&motor_cmds, motor_config, amps, epsilon),
+                )
+            })
+            .collect()
+    }
+}
+
+impl From<MotorData> for ThrusterModels {
+    fn from(motor_data: MotorData) -> Self {
+        ThrusterModel::Table(motor_data).into()
+    }
+}
+
+impl From<MotorFit> for ThrusterModels {
+    fn from(fit: MotorFit) -> Self {
+        ThrusterModel::Fit(Box::new(fit)).into()
+    }
+}
+
+impl From<ThrusterModel> for ThrusterModels {
+    fn from(thruster_model: ThrusterModel) -> Self {
+        Self {
+            names: Arc::new(["Default".to_owned()]),
+            data: Arc::new([thruster_model]),
+            assignment: vec![],
+            default: 0,
+        }
+    }
+}
+
+/// `motor_config` without the derivatives, for lookups that only need the values
+fn float_config<MotorId: Debug + Ord + Hash + Clone, D: Number>(
+    motor_config: &MotorConfig<MotorId, D>,
+) -> MotorConfig<MotorId, FloatType> {
+    MotorConfig::new_raw(
+        motor_config.motors().map(|(id, motor)| {
+            (
+                id.clone(),
+                Motor {
+                    position: motor.position.map(|it| it.re()),
+                    orientation: motor.orientation.map(|it| it.re()),
+                    direction: motor.direction,
+                },
+            )
+        }),
+        Vector3::zeros(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use motor_math::{Direction, FloatType, Motor, MotorConfig};
+    use nalgebra::vector;
+    use stable_hashmap::StableHashMap;
+
+    use super::{ThrusterModel, ThrusterModels};
+    use crate::{
+        motor_fit::{MotorFit, DEFAULT_FIT_DEGREE},
+        motor_library::ThrustCurve,
+    };
+
+    /// The shipped curve with its force multiplied by `force_scale`
+    fn curve(force_scale: FloatType) -> ThrustCurve {
+        ThrustCurve::parse(12.0, include_str!("../motor_data.csv"), force_scale).unwrap()
+    }
+
+    fn tables() -> ThrusterModels {
+        let models = [("Small", 1.0), ("Large", 2.0)]
+            .map(|(name, scale)| {
+                let motor_data = curve(scale).motor_data().unwrap();
+                (name.to_owned(), ThrusterModel::Table(motor_data))
+            })
+            .into();
+
+        ThrusterModels::new(models, 0).unwrap()
+    }
+
+    fn fits() -> ThrusterModels {
+        let models = [("Small", 1.0), ("Large", 2.0)]
+            .map(|(name, scale)| {
+                let fit = MotorFit::fit(&curve(scale), DEFAULT_FIT_DEGREE).unwrap();
+                (name.to_owned(), ThrusterModel::Fit(Box::new(fit)))
+            })
+            .into();
+
+        ThrusterModels::new(models, 0).unwrap()
+    }
+
+    /// Two thrusters side by side pushing along X
+    fn side_by_side() -> MotorConfig<u8, FloatType> {
+        MotorConfig::new_raw(
+            [-0.1, 0.1].into_iter().enumerate().map(|(id, y)| {
+                (
+                    id as u8,
+                    Motor {
+                        position: vector![0.0, y, 0.0],
+                        orientation: vector![1.0, 0.0, 0.0],
+                        direction: Direction::Clockwise,
+                    },
+                )
+            }),
+            vector![0.0, 0.0, 0.0],
+        )
+    }
+
+    #[test]
+    fn slots_use_their_assigned_model() {
+        let models = tables().with_assignment(vec![1, 0, 5]);
+
+        assert_eq!(models.model_of(0), 1);
+        assert_eq!(models.model_of(1), 0);
+        // Models that don't exist and slots past the assignment use the default
+        assert_eq!(models.model_of(2), 0);
+        assert_eq!(models.model_of(3), 0);
+        assert_eq!(models.index_of("Large").unwrap(), 1);
+        assert!(models.index_of("Missing").is_err());
+
+        let thrusters = models.for_thrusters([0, 0, 3]);
+        assert_eq!(thrusters.assignment(), [1, 1, 0]);
+    }
+
+    #[test]
+    fn uniform_assignments_use_the_shared_model() {
+        let models = tables().with_assignment(vec![1]);
+        assert!(models.uniform().is_none());
+
+        // Resolving every thruster to the same model makes it the default
+        let resolved = models.for_thrusters([0, 0]);
+        assert_eq!(resolved.default_model(), 1);
+        assert!(resolved.uniform().is_some());
+        assert!(tables().uniform().is_some());
+    }
+
+    #[test]
+    fn each_thruster_is_commanded_from_its_own_table() {
+        let motor_config = side_by_side();
+        let models = tables().resolved(vec![0, 1]);
+        let forces = StableHashMap::from_iter([(0, 10.0), (1, 10.0)]);
+
+        let cmds = models.forces_to_cmds(forces.clone(), &motor_config);
+        let mut by_model = [None; 2];
+        // The assignment follows the order of `motors()`
+        for (idx, (id, _)) in motor_config.motors().enumerate() {
+            let model = models.model_of(idx);
+            let alone = tables().resolved(vec![model; 2]);
+            let expected = &alone.forces_to_cmds(forces.clone(), &motor_config)[id];
+
+            let cmd = &cmds[id];
+            let name = &models.names()[model];
+            assert_eq!(cmd.pwm, expected.pwm, "{name}");
+            assert_eq!(cmd.current, expected.current, "{name}");
+            by_model[model] = Some((cmd.pwm, cmd.current));
+        }
+
+        // The large thruster needs less of its range for the same force
+        let [Some(small), Some(large)] = by_model else {
+            panic!("Both models are used {by_model:?}");
+        };
+        assert!(large.0 < small.0, "{small:?} {large:?}");
+        assert!(large.1 < small.1, "{small:?} {large:?}");
+    }
+
+    #[test]
+    fn mixed_models_share_the_current_budget() {
+        let motor_config = side_by_side();
+        let models = fits().resolved(vec![0, 1]);
+        let forces = StableHashMap::from_iter([(0, 10.0), (1, 10.0)]);
+        let motor_cmds = models.forces_to_cmds(forces, &motor_config);
+
+        let ratio = models.binary_search_force_ratio(&motor_cmds, &motor_config, 10.0, 0.001);
+        let ThrusterModel::Fit(small) = models.thruster_model(0) else {
+            unreachable!();
+        };
+        let ThrusterModel::Fit(large) = models.thruster_model(1) else {
+            unreachable!();
+        };
+        let current = small.record(10.0 * ratio).current + large.record(10.0 * ratio).current;
+        assert!((current - 10.0).abs() < 0.01, "{ratio} {current}");
+
+        // Swapping the models keeps the total, as both thrusters push the same force
+        let swapped = fits().resolved(vec![1, 0]);
+        let swapped_ratio =
+            swapped.binary_search_force_ratio(&motor_cmds, &motor_config, 10.0, 0.001);
+        assert!(
+            (swapped_ratio - ratio).abs() < 1e-6,
+            "{swapped_ratio} {ratio}"
+        );
+    }
+}